@@ -0,0 +1,318 @@
+use crate::{board::Square, engine::Score};
+
+/// The numeric annotation glyphs that assess a move, and their symbols
+const GLYPHS: [(u8, &str); 6] = [
+    (1, "!"),
+    (2, "?"),
+    (3, "!!"),
+    (4, "??"),
+    (5, "!?"),
+    (6, "?!"),
+];
+
+/// The color of an arrow or marked square
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AnnotationColor {
+    /// Green, the default
+    Green,
+
+    /// Red
+    Red,
+
+    /// Yellow
+    Yellow,
+
+    /// Blue
+    Blue,
+}
+
+impl AnnotationColor {
+    /// Returns the letter of this color in PGN comment commands, i.e. 'G' for green
+    pub const fn letter(&self) -> char {
+        match self {
+            Self::Green => 'G',
+            Self::Red => 'R',
+            Self::Yellow => 'Y',
+            Self::Blue => 'B',
+        }
+    }
+
+    /// Returns the color with the given letter in PGN comment commands
+    pub const fn from_letter(letter: char) -> Option<Self> {
+        match letter {
+            'G' => Some(Self::Green),
+            'R' => Some(Self::Red),
+            'Y' => Some(Self::Yellow),
+            'B' => Some(Self::Blue),
+            _ => None,
+        }
+    }
+}
+
+/// An arrow drawn between two squares
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Arrow {
+    /// The square the arrow starts on
+    pub from: Square,
+
+    /// The square the arrow points to
+    pub to: Square,
+
+    /// The color of the arrow
+    pub color: AnnotationColor,
+}
+
+/// Returns the symbol of the given numeric annotation glyph if it assesses a move, i.e. "?" for
+/// 2, a mistake
+pub fn glyph(nag: u8) -> Option<&'static str> {
+    GLYPHS
+        .iter()
+        .find(|(number, _)| *number == nag)
+        .map(|(_, symbol)| *symbol)
+}
+
+/// Returns the numeric annotation glyph with the given symbol, i.e. 2 for "?"
+pub fn glyph_nag(symbol: &str) -> Option<u8> {
+    GLYPHS
+        .iter()
+        .find(|(_, other)| *other == symbol)
+        .map(|(number, _)| *number)
+}
+
+/// The arrows and marked squares drawn on a position, and what is said about the move reaching it
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Annotations {
+    /// The arrows, in the order they were drawn
+    pub arrows: Vec<Arrow>,
+
+    /// The marked squares and their colors, in the order they were marked
+    pub squares: Vec<(Square, AnnotationColor)>,
+
+    /// The numeric annotation glyphs of the move reaching the position, i.e. 2 for a mistake
+    pub nags: Vec<u8>,
+
+    /// How good the position is for white, as an engine judged it
+    pub eval: Option<Score>,
+}
+
+impl Annotations {
+    /// Returns true if nothing is drawn
+    pub fn is_empty(&self) -> bool {
+        self.arrows.is_empty() && self.squares.is_empty()
+    }
+
+    /// Removes every arrow and marked square
+    pub fn clear(&mut self) {
+        self.arrows.clear();
+        self.squares.clear();
+    }
+
+    /// Adds the given arrow, replacing any other arrow between the same squares, or removes it if
+    /// it is already drawn in the same color
+    pub fn toggle_arrow(&mut self, arrow: Arrow) {
+        let existing = self
+            .arrows
+            .iter()
+            .position(|other| other.from == arrow.from && other.to == arrow.to);
+        match existing {
+            Some(index) if self.arrows[index].color == arrow.color => {
+                self.arrows.remove(index);
+            }
+            Some(index) => self.arrows[index] = arrow,
+            None => self.arrows.push(arrow),
+        }
+    }
+
+    /// Marks the given square, replacing any other mark on it, or removes the mark if it is
+    /// already in the same color
+    pub fn toggle_square(&mut self, square: Square, color: AnnotationColor) {
+        let existing = self.squares.iter().position(|(other, _)| *other == square);
+        match existing {
+            Some(index) if self.squares[index].1 == color => {
+                self.squares.remove(index);
+            }
+            Some(index) => self.squares[index] = (square, color),
+            None => self.squares.push((square, color)),
+        }
+    }
+
+    /// Returns the first glyph assessing the move reaching the position, if any
+    pub fn assessment(&self) -> Option<&'static str> {
+        self.nags.iter().find_map(|&nag| glyph(nag))
+    }
+
+    /// Returns the annotations as PGN comment commands, i.e. `[%csl Rd4][%cal Ge2e4,Rg8f6]`, or an
+    /// empty string if nothing is drawn and there is no evaluation
+    pub fn to_commands(&self) -> String {
+        let mut commands = String::new();
+        match self.eval {
+            Some(Score::Centipawns(centipawns)) => {
+                commands.push_str(&format!("[%eval {:.2}]", centipawns as f32 / 100.0));
+            }
+            Some(Score::Mate(moves)) => commands.push_str(&format!("[%eval #{moves}]")),
+            None => {}
+        }
+        if !self.squares.is_empty() {
+            let squares: Vec<_> = self
+                .squares
+                .iter()
+                .map(|(square, color)| format!("{}{square}", color.letter()))
+                .collect();
+            commands.push_str(&format!("[%csl {}]", squares.join(",")));
+        }
+        if !self.arrows.is_empty() {
+            let arrows: Vec<_> = self
+                .arrows
+                .iter()
+                .map(|arrow| format!("{}{}{}", arrow.color.letter(), arrow.from, arrow.to))
+                .collect();
+            commands.push_str(&format!("[%cal {}]", arrows.join(",")));
+        }
+        commands
+    }
+
+    /// Adds the annotations described by any `[%csl]`, `[%cal]`, and `[%eval]` commands in the
+    /// given PGN comment, skipping anything malformed
+    pub fn add_commands(&mut self, comment: &str) {
+        for (name, arguments) in commands(comment) {
+            if name == "eval" {
+                self.eval = parse_eval(arguments).or(self.eval);
+                continue;
+            }
+            for argument in arguments.split(',').map(str::trim) {
+                let mut chars = argument.chars();
+                let Some(color) = chars.next().and_then(AnnotationColor::from_letter) else {
+                    continue;
+                };
+                let squares = chars.as_str();
+                match (name, squares.len()) {
+                    ("csl", 2) => {
+                        if let Ok(square) = squares.parse() {
+                            self.squares.push((square, color));
+                        }
+                    }
+                    // slicing by byte needs every character to be one byte
+                    ("cal", 4) if squares.is_ascii() => {
+                        if let (Ok(from), Ok(to)) = (squares[..2].parse(), squares[2..].parse()) {
+                            self.arrows.push(Arrow { from, to, color });
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+/// Parses the argument of an `[%eval]` command, in pawns for white, i.e. "0.35", or as a mate,
+/// i.e. "#-3"
+fn parse_eval(argument: &str) -> Option<Score> {
+    // engines may add the depth after a comma
+    let argument = argument.split(',').next()?.trim();
+    match argument.strip_prefix('#') {
+        Some(moves) => moves.parse().ok().map(Score::Mate),
+        None => {
+            let pawns: f32 = argument.parse().ok()?;
+            Some(Score::Centipawns((pawns * 100.0).round() as i32))
+        }
+    }
+}
+
+/// Returns the names and arguments of the `[%name arguments]` commands in a PGN comment
+pub fn commands(comment: &str) -> impl Iterator<Item = (&str, &str)> {
+    comment.split("[%").skip(1).filter_map(|command| {
+        let (command, _) = command.split_once(']')?;
+        let (name, arguments) = command.split_once(char::is_whitespace)?;
+        Some((name, arguments.trim()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(name: &str) -> Square {
+        name.parse().unwrap()
+    }
+
+    fn annotations() -> Annotations {
+        Annotations {
+            arrows: vec![
+                Arrow {
+                    from: square("e2"),
+                    to: square("e4"),
+                    color: AnnotationColor::Green,
+                },
+                Arrow {
+                    from: square("g8"),
+                    to: square("f6"),
+                    color: AnnotationColor::Red,
+                },
+            ],
+            squares: vec![
+                (square("d4"), AnnotationColor::Yellow),
+                (square("h7"), AnnotationColor::Blue),
+            ],
+            nags: Vec::new(),
+            eval: Some(Score::Centipawns(-135)),
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let commands = annotations().to_commands();
+        assert_eq!(commands, "[%eval -1.35][%csl Yd4,Bh7][%cal Ge2e4,Rg8f6]");
+        let mut read = Annotations::default();
+        read.add_commands(&format!("A plan {commands} for later"));
+        assert_eq!(read, annotations());
+
+        let mate = Annotations {
+            eval: Some(Score::Mate(-3)),
+            ..Annotations::default()
+        };
+        assert_eq!(mate.to_commands(), "[%eval #-3]");
+        let mut read = Annotations::default();
+        read.add_commands(&mate.to_commands());
+        assert_eq!(read, mate);
+        assert_eq!(Annotations::default().to_commands(), "");
+    }
+
+    #[test]
+    fn malformed_commands() {
+        let mut read = Annotations::default();
+        read.add_commands(concat!(
+            "[%csl Rd4, Xe5,R,Ri9,Ge55,é] [%cal Ge2e4,Ge2,Ge2e9,Xe2e4,Gaé1,Ge2e4e6] ",
+            "[%eval 0.5,20] [%eval mate] [%clk 0:05:00] [%csl] [%cal Rb1c3",
+        ));
+        assert_eq!(read.squares, [(square("d4"), AnnotationColor::Red)]);
+        assert_eq!(
+            read.arrows,
+            [Arrow {
+                from: square("e2"),
+                to: square("e4"),
+                color: AnnotationColor::Green,
+            }]
+        );
+        assert_eq!(read.eval, Some(Score::Centipawns(50)));
+    }
+
+    #[test]
+    fn toggling() {
+        let mut annotations = annotations();
+        let arrow = annotations.arrows[0];
+        annotations.toggle_arrow(arrow);
+        assert_eq!(annotations.arrows.len(), 1);
+        annotations.toggle_arrow(Arrow {
+            color: AnnotationColor::Blue,
+            ..annotations.arrows[0]
+        });
+        assert_eq!(annotations.arrows[0].color, AnnotationColor::Blue);
+
+        annotations.toggle_square(square("d4"), AnnotationColor::Red);
+        assert_eq!(annotations.squares[0], (square("d4"), AnnotationColor::Red));
+        annotations.toggle_square(square("d4"), AnnotationColor::Red);
+        annotations.toggle_square(square("h7"), AnnotationColor::Blue);
+        annotations.toggle_arrow(annotations.arrows[0]);
+        assert!(annotations.is_empty());
+    }
+}
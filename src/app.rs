@@ -1,51 +1,1167 @@
-use eframe::egui;
+use eframe::egui::{self, Key, KeyboardShortcut, Modifiers};
 
-use self::board_view::BoardView;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use crate::{
+    board::Board,
+    book::Book,
+    clock::Clock,
+    eco,
+    engine::{strength::Strength, uci::UciEngine, ClockTime, Engine, Event, Limits, Thinking},
+    game::{EditPast, Game, NodeId},
+    pgn,
+    piece::PieceColor,
+    r#move::Move,
+    tablebase::Tablebase,
+};
+
+use self::{
+    analysis::{Analysis, EVAL_BAR_WIDTH},
+    animation::Animation,
+    board_view::{BoardView, Coordinates, MoveInput},
+    book_moves::BookMoves,
+    clock_view::{ClockSettings, ClockView},
+    command::Command,
+    database::DatabaseBrowser,
+    editor::BoardEditor,
+    explorer::Explorer,
+    layout::{Layout, PanelKind},
+    move_entry::MoveEntry,
+    move_list::{MoveList, MoveListAction},
+    new_game::{engine_name, NewGame, NewGameDialog, Player},
+    painting::Painting,
+    palette::{Palette, PaletteItem},
+    players::PlayersView,
+    preferences::{Preferences, Session, Settings, SESSION_KEY, SETTINGS_KEY},
+    review::GameReview,
+};
+
+/// The shortcut that opens the command palette
+const PALETTE_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::P);
+
+/// The most recently opened or saved files remembered
+const MAX_RECENT_FILES: usize = 10;
+
+/// How long engines think about each move in untimed games
+const UNTIMED_MOVE_TIME: Duration = Duration::from_secs(2);
+
+/// Engine analysis of the position being viewed
+pub mod analysis;
+
+/// Pieces sliding between squares as the position changes
+pub mod animation;
 
 /// A view of the chess board
 pub mod board_view;
 
+/// The moves an opening book suggests
+pub mod book_moves;
+
+/// The moves played in a database of games
+pub mod explorer;
+
+/// The game clock and its settings
+pub mod clock_view;
+
+/// Actions available from the menus and the command palette
+pub mod command;
+
+/// Browsing and searching the game database
+pub mod database;
+
+/// Setting up positions by hand
+pub mod editor;
+
+/// The arrangement of panels around the board
+pub mod layout;
+
+/// Typing moves from the keyboard
+pub mod move_entry;
+
+/// The list of moves played
+pub mod move_list;
+
+/// The dialog for starting a new game
+pub mod new_game;
+
+/// Drawing arrows and marking squares on the board
+pub mod painting;
+
+/// The fuzzy-finding command palette
+pub mod palette;
+
+/// The players and the material they have captured
+pub mod players;
+
+/// Settings remembered between launches, and the window for editing them
+pub mod preferences;
+
+/// Annotating the moves of the game with an engine's judgement of them
+pub mod review;
+
+/// Board colors, textures, and piece sets
+pub mod theme;
+
 /// The chess user interface
 pub struct App {
-    ui_scale: f32,
+    settings: Settings,
+    preferences: Preferences,
+    game: Game,
+    move_input: MoveInput,
+    painting: Painting,
+    animation: Animation,
+    move_entry: MoveEntry,
+    clock: Option<Clock>,
+    clock_settings: ClockSettings,
+    layout: Layout,
+    palette: Palette,
+    new_game: NewGameDialog,
+    editor: BoardEditor,
+    database: DatabaseBrowser,
+    analysis: Analysis,
+    review: GameReview,
+
+    /// The engine playing white, or `None` if a person is
+    white_engine: Option<Engine>,
+
+    /// The engine playing black, or `None` if a person is
+    black_engine: Option<Engine>,
+
+    /// The engine thinking about its move, and the position it is thinking about
+    thinking: Option<(Thinking, NodeId)>,
+
+    /// The opening book, and the file it was read from
+    book: Option<(PathBuf, Book)>,
+
+    /// Whether the engines play from the opening book while they can
+    engine_book: bool,
+
+    /// The moves played in the games database
+    explorer: Explorer,
+
+    /// The endgame tablebase, and the directory it was found in
+    tablebase: Option<(PathBuf, Arc<Tablebase>)>,
+
+    /// The file the game was loaded from or last saved to
+    path: Option<PathBuf>,
+
+    /// Whether the game has changed since it was last saved
+    dirty: bool,
+
+    /// An action waiting for the user to decide what to do with unsaved changes
+    pending: Option<Pending>,
+    error: Option<String>,
+}
+
+/// An action that would discard the current game
+enum Pending {
+    /// Starting the given game
+    NewGame(Box<NewGame>),
+
+    /// Loading a game from a file
+    Load,
+
+    /// Loading the game or position in the given file
+    LoadFile(PathBuf),
+
+    /// Opening the given game from the game database
+    Open(Box<Game>),
+
+    /// Closing the app
+    Quit,
 }
 
 impl App {
     /// Creates a new chess app
-    pub fn new(_cc: &eframe::CreationContext<'_>) -> Self {
-        Self { ui_scale: 2.0 }
+    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
+        egui_extras::install_image_loaders(&cc.egui_ctx);
+
+        let settings: Settings = cc
+            .storage
+            .and_then(|storage| eframe::get_value(storage, SETTINGS_KEY))
+            .unwrap_or_default();
+        cc.egui_ctx.set_zoom_factor(settings.ui_scale);
+        cc.egui_ctx.set_theme(settings.theme);
+
+        let mut clock_settings = ClockSettings::new();
+        let time_control = settings.time_control.as_deref();
+        clock_settings.set_time_control(time_control.and_then(|text| text.parse().ok()));
+        let session = match settings.restore_session {
+            true => cc
+                .storage
+                .and_then(|storage| eframe::get_value::<Option<Session>>(storage, SESSION_KEY))
+                .flatten(),
+            false => None,
+        };
+
+        let mut app = Self {
+            settings,
+            preferences: Preferences::new(),
+            game: Game::new(),
+            move_input: MoveInput::default(),
+            painting: Painting::new(),
+            animation: Animation::new(),
+            move_entry: MoveEntry::new(),
+            clock: clock_settings.time_control().cloned().map(Clock::new),
+            clock_settings,
+            layout: Layout::new(),
+            palette: Palette::new(),
+            new_game: NewGameDialog::new(),
+            editor: BoardEditor::new(),
+            database: DatabaseBrowser::new(),
+            analysis: Analysis::new(),
+            review: GameReview::new(),
+            white_engine: None,
+            black_engine: None,
+            thinking: None,
+            book: None,
+            engine_book: false,
+            explorer: Explorer::new(),
+            tablebase: None,
+            path: None,
+            dirty: false,
+            pending: None,
+            error: None,
+        };
+        if let Some(session) = session {
+            app.restore(session);
+        }
+        app
+    }
+
+    /// Restores the game in progress when the app was last closed
+    fn restore(&mut self, session: Session) {
+        // a session that can no longer be read is silently dropped, leaving a new game
+        if let Ok(game) = pgn::read(&session.pgn) {
+            self.replace_game(game, session.path);
+            self.dirty = session.dirty;
+        }
+    }
+
+    /// Views the position at the given node
+    fn go_to(&mut self, id: NodeId) {
+        self.game.go_to(id);
+        self.move_input.clear();
+    }
+
+    /// Returns true if the user may play a move from the position being viewed
+    fn can_move(&self) -> bool {
+        match self.game.is_at_end() {
+            true => self.game.outcome().is_none(),
+            false => self.settings.allow_editing_past,
+        }
+    }
+
+    /// Returns the engine playing the given side, or `None` if a person is
+    fn engine(&self, color: PieceColor) -> Option<&Engine> {
+        match color {
+            PieceColor::White => self.white_engine.as_ref(),
+            PieceColor::Black => self.black_engine.as_ref(),
+        }
+    }
+
+    /// Returns the side the user plays when playing against an engine
+    fn human(&self) -> Option<PieceColor> {
+        match (&self.white_engine, &self.black_engine) {
+            (None, Some(_)) => Some(PieceColor::White),
+            (Some(_), None) => Some(PieceColor::Black),
+            _ => None,
+        }
+    }
+
+    /// Returns true if an engine plays the side to move in the position being viewed
+    fn is_engine_turn(&self) -> bool {
+        self.engine(self.game.board().to_move()).is_some()
+    }
+
+    /// Plays the given move in the game, pressing the clock if it continues the main line
+    fn play_move(&mut self, r#move: Move) -> Result<(), &'static str> {
+        let color = self.game.board().to_move();
+        let at_end = self.game.is_at_end();
+        self.game
+            .set_edit_past(match self.settings.allow_editing_past {
+                true => self.settings.edit_past,
+                false => EditPast::Forbid,
+            });
+        self.game.play(r#move)?;
+        self.dirty = true;
+        // a piece selected on the board may no longer be there, however the move was played
+        self.move_input.deselect();
+
+        if let Some(clock) = &mut self.clock {
+            match self.game.outcome() {
+                Some(_) => clock.stop(),
+                None if at_end => clock.press(color),
+                None => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Ends the game if a player's time has run out, and keeps the clock display ticking
+    fn update_clock(&mut self, ctx: &egui::Context) {
+        let Some(clock) = &mut self.clock else {
+            return;
+        };
+        if let Some(color) = clock.flagged() {
+            self.game.flag(color);
+        }
+        if clock.running().is_some() {
+            ctx.request_repaint_after(Duration::from_millis(50));
+        }
+    }
+
+    /// Lets an engine think when it is its turn, and plays its move once it has chosen one
+    fn update_engine(&mut self, ctx: &egui::Context) {
+        // the search is abandoned if the position changes, as on a takeback, or the game ends
+        if self
+            .thinking
+            .as_ref()
+            .is_some_and(|(_, id)| *id != self.game.current())
+            || self.game.outcome().is_some()
+        {
+            self.thinking = None;
+        }
+
+        let color = self.game.board().to_move();
+        if self.thinking.is_none() && self.game.is_at_end() && self.game.outcome().is_none() {
+            if let Some(r#move) = self.engine_book_move() {
+                self.play_move(r#move).expect("book moves are legal");
+                return;
+            }
+            if let Some(engine) = self.engine(color) {
+                let moves = self.game.moves_to(self.game.current());
+                let thinking = engine.think(self.game.start(), &moves, self.engine_limits());
+                self.thinking = Some((thinking, self.game.current()));
+            }
+        }
+
+        let events = match &self.thinking {
+            Some((thinking, _)) => thinking.events(),
+            None => return,
+        };
+        for event in events {
+            match event {
+                Event::Info(_) => {}
+                Event::BestMove(best) => {
+                    self.thinking = None;
+                    if let Some(r#move) = best {
+                        self.play_move(r#move)
+                            .expect("engines only choose legal moves");
+                    }
+                    return;
+                }
+                Event::Failed(error) => {
+                    // the user takes over from an engine that failed
+                    self.thinking = None;
+                    match color {
+                        PieceColor::White => self.white_engine = None,
+                        PieceColor::Black => self.black_engine = None,
+                    }
+                    self.error = Some(format!("The engine failed: {error}"));
+                    return;
+                }
+            }
+        }
+        ctx.request_repaint_after(Duration::from_millis(50));
+    }
+
+    /// Returns a move from the opening book for the engine to move, if it plays from the book
+    /// and the position is in it
+    fn engine_book_move(&self) -> Option<Move> {
+        self.engine(self.game.board().to_move())?;
+        match (self.engine_book, &self.book) {
+            (true, Some((_, book))) => book.choose(self.game.board()),
+            _ => None,
+        }
+    }
+
+    /// Reads the opening book chosen in the settings, if it changed since it was last read
+    fn update_book(&mut self) {
+        let path = self.settings.book.as_ref();
+        if path == self.book.as_ref().map(|(loaded, _)| loaded) {
+            return;
+        }
+        self.book = None;
+        if let Some(path) = path {
+            match Book::open(path) {
+                Ok(book) => self.book = Some((path.clone(), book)),
+                Err(error) => {
+                    self.error = Some(format!("Unable to open {}: {error}", path.display()));
+                    // a book that cannot be read is forgotten, so the error is shown only once
+                    self.settings.book = None;
+                }
+            }
+        }
+    }
+
+    /// Finds the endgame tablebase in the directory chosen in the settings, if it changed since
+    /// it was last found
+    fn update_tablebase(&mut self) {
+        let directory = self.settings.tablebase.as_ref();
+        if directory == self.tablebase.as_ref().map(|(found, _)| found) {
+            return;
+        }
+        self.tablebase = None;
+        if let Some(directory) = directory {
+            match Tablebase::open(directory) {
+                Ok(tablebase) => self.tablebase = Some((directory.clone(), Arc::new(tablebase))),
+                Err(error) => {
+                    self.error = Some(format!("Unable to open {}: {error}", directory.display()));
+                    self.settings.tablebase = None;
+                }
+            }
+        }
+        self.analysis.set_tablebase(self.tablebase());
+    }
+
+    /// Returns the endgame tablebase, if one was found
+    fn tablebase(&self) -> Option<Arc<Tablebase>> {
+        self.tablebase
+            .as_ref()
+            .map(|(_, tablebase)| Arc::clone(tablebase))
+    }
+
+    /// Starts reviewing the game with the analysis engine
+    fn start_review(&mut self) {
+        let engine = match self.settings.analysis_engine.clone() {
+            None => Engine::BuiltIn(Strength::FULL, self.tablebase()),
+            Some(path) => match UciEngine::spawn(&path) {
+                Ok(engine) => Engine::External(engine),
+                Err(error) => {
+                    self.error = Some(format!("Unable to start {}: {error}", path.display()));
+                    return;
+                }
+            },
+        };
+        self.review.start(&self.game, engine);
+    }
+
+    /// Returns how long engines may think, from the clock if the game is timed
+    fn engine_limits(&self) -> Limits {
+        let to_move = self.game.board().to_move();
+        let clock = self.clock.as_ref().map(|clock| ClockTime {
+            white: clock.remaining(PieceColor::White),
+            black: clock.remaining(PieceColor::Black),
+            white_increment: clock.increment(PieceColor::White),
+            black_increment: clock.increment(PieceColor::Black),
+            moves_to_go: clock.moves_to_go(to_move),
+        });
+        Limits {
+            move_time: clock.is_none().then_some(UNTIMED_MOVE_TIME),
+            clock,
+            ..Limits::default()
+        }
+    }
+
+    /// Starts the given new game, replacing the current one
+    fn start_game(&mut self, new_game: NewGame) {
+        let mut game = Game::from_board(new_game.board);
+        let headers = game.headers_mut();
+        headers.set("White", new_game.white);
+        headers.set("Black", new_game.black);
+        if new_game.chess960 {
+            headers.set("Variant", "Chess960");
+        }
+
+        self.clock_settings.set_time_control(new_game.time_control);
+        self.replace_game(game, None);
+        self.white_engine = self.start_engine(new_game.white_player);
+        self.black_engine = self.start_engine(new_game.black_player);
+        self.engine_book = new_game.book;
+    }
+
+    /// Starts the engine for the given player, or returns `None` for a person or an engine that
+    /// could not be started
+    fn start_engine(&mut self, player: Player) -> Option<Engine> {
+        match player {
+            Player::Human => None,
+            Player::BuiltIn { level, human_like } => {
+                let strength = Strength::from_level(level, human_like);
+                Some(Engine::BuiltIn(strength, self.tablebase()))
+            }
+            Player::External(path) => match UciEngine::spawn(&path) {
+                Ok(engine) => Some(Engine::External(engine)),
+                Err(error) => {
+                    self.error = Some(format!("Unable to start {}: {error}", path.display()));
+                    None
+                }
+            },
+        }
+    }
+
+    /// Replaces the current game with the given one, which was loaded from the given file, if any
+    fn replace_game(&mut self, game: Game, path: Option<PathBuf>) {
+        self.game = game;
+        self.path = path;
+        self.dirty = false;
+        self.clock = self.clock_settings.time_control().cloned().map(Clock::new);
+        self.move_input.clear();
+        self.move_entry.clear();
+        self.thinking = None;
+        self.white_engine = None;
+        self.black_engine = None;
+        self.review.cancel();
+    }
+
+    /// Runs the given action if there are no unsaved changes, otherwise asks the user first
+    fn discard_then(&mut self, ctx: &egui::Context, pending: Pending) {
+        match self.dirty {
+            true => self.pending = Some(pending),
+            false => self.proceed(ctx, pending),
+        }
+    }
+
+    /// Runs the given action, discarding any unsaved changes
+    fn proceed(&mut self, ctx: &egui::Context, pending: Pending) {
+        match pending {
+            Pending::NewGame(new_game) => self.start_game(*new_game),
+            Pending::Load => self.load_game(),
+            Pending::LoadFile(path) => self.load_file(path),
+            Pending::Open(game) => self.replace_game(*game, None),
+            Pending::Quit => {
+                self.dirty = false;
+                ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+            }
+        }
+    }
+
+    /// Saves the game to its file, asking for one if it has none, returning true if it was saved
+    fn save_game(&mut self) -> bool {
+        match self.path.clone() {
+            Some(path) => self.save_to(path),
+            None => self.save_game_as(),
+        }
+    }
+
+    /// Asks for a file and saves the game to it, returning true if it was saved
+    fn save_game_as(&mut self) -> bool {
+        let file_name = match &self.path {
+            Some(path) => path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned(),
+            None => String::from("game.pgn"),
+        };
+        let picked = rfd::FileDialog::new()
+            .add_filter("PGN game", &["pgn"])
+            .add_filter("FEN position", &["fen"])
+            .set_file_name(file_name)
+            .save_file();
+        match picked {
+            Some(path) => self.save_to(path),
+            None => false,
+        }
+    }
+
+    /// Writes the game to the given file as PGN, or only the position being viewed for FEN files,
+    /// returning true if it was written
+    fn save_to(&mut self, path: PathBuf) -> bool {
+        let fen = is_fen(&path);
+        let text = match fen {
+            true => format!("{}\n", self.game.board().fen()),
+            false => {
+                self.set_opening_headers();
+                pgn::write(&self.game)
+            }
+        };
+
+        if let Err(error) = fs::write(&path, text) {
+            self.error = Some(format!("Unable to save {}: {error}", path.display()));
+            return false;
+        }
+        if !fen {
+            self.remember_file(&path);
+            self.path = Some(path);
+            self.dirty = false;
+        }
+        true
+    }
+
+    /// Names the opening of the game's main line in its headers, keeping any names it already
+    /// has if its opening is not known
+    fn set_opening_headers(&mut self) {
+        let end = self
+            .game
+            .main_line()
+            .last()
+            .copied()
+            .unwrap_or(NodeId::ROOT);
+        if let Some(opening) = eco::classify_game(&self.game, end) {
+            let headers = self.game.headers_mut();
+            headers.set("ECO", opening.eco);
+            headers.set("Opening", opening.name);
+        }
+    }
+
+    /// Asks for a PGN or FEN file and loads the game or position in it
+    fn load_game(&mut self) {
+        let picked = rfd::FileDialog::new()
+            .add_filter("Games and positions", &["pgn", "fen"])
+            .add_filter("PGN game", &["pgn"])
+            .add_filter("FEN position", &["fen"])
+            .pick_file();
+        if let Some(path) = picked {
+            self.load_file(path);
+        }
+    }
+
+    /// Loads the game or position in the given file
+    fn load_file(&mut self, path: PathBuf) {
+        let fen = is_fen(&path);
+        let loaded = fs::read_to_string(&path)
+            .map_err(|error| error.to_string())
+            .and_then(|text| {
+                match fen {
+                    true => Board::from_fen(text.trim())
+                        .and_then(|board| board.validate().map(|_| Game::from_board(board))),
+                    false => pgn::read(&text),
+                }
+                .map_err(String::from)
+            });
+        match loaded {
+            Ok(game) if fen => self.replace_game(game, None),
+            Ok(game) => {
+                self.remember_file(&path);
+                self.replace_game(game, Some(path));
+            }
+            Err(error) => {
+                self.error = Some(format!("Unable to load {}: {error}", path.display()));
+                // a file that cannot be read is no longer offered as a recent one
+                self.settings.recent_files.retain(|file| *file != path);
+            }
+        }
+    }
+
+    /// Puts the given PGN file first among the recently opened or saved ones
+    fn remember_file(&mut self, path: &Path) {
+        let files = &mut self.settings.recent_files;
+        files.retain(|file| file != path);
+        files.insert(0, path.to_path_buf());
+        files.truncate(MAX_RECENT_FILES);
+    }
+
+    /// Asks what to do with unsaved changes if an action is waiting on it
+    fn show_pending(&mut self, ctx: &egui::Context) {
+        let Some(pending) = self.pending.take() else {
+            return;
+        };
+
+        let (mut save, mut discard, mut cancel) = (false, false, false);
+        egui::Window::new("Unsaved changes")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label("The current game has unsaved changes.");
+                ui.horizontal(|ui| {
+                    save = ui.button("Save").clicked();
+                    discard = ui.button("Discard").clicked();
+                    cancel = ui.button("Cancel").clicked();
+                });
+            });
+
+        if cancel {
+            return;
+        }
+        match discard || (save && self.save_game()) {
+            true => self.proceed(ctx, pending),
+            false => self.pending = Some(pending),
+        }
+    }
+
+    /// Shows the last error, if any, until it is dismissed
+    fn show_error(&mut self, ctx: &egui::Context) {
+        let Some(error) = &self.error else {
+            return;
+        };
+
+        let mut open = true;
+        let mut dismissed = false;
+        egui::Window::new("Error")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(error);
+                dismissed = ui.button("OK").clicked();
+            });
+        if !open || dismissed {
+            self.error = None;
+        }
+    }
+
+    /// Runs the given command
+    fn run(&mut self, ctx: &egui::Context, command: Command) {
+        match command {
+            Command::NewGame => self.new_game.open(self.clock_settings.time_control()),
+            Command::SetUpPosition => self.editor.open(self.game.board()),
+            Command::SaveGame => {
+                self.save_game();
+            }
+            Command::SaveGameAs => {
+                self.save_game_as();
+            }
+            Command::LoadGame => self.discard_then(ctx, Pending::Load),
+            Command::OpenDatabase => self.database.open(),
+            Command::FlipBoard => self.settings.flipped = !self.settings.flipped,
+            Command::HideCoordinates => self.settings.coordinates = Coordinates::Hidden,
+            Command::CoordinatesInside => self.settings.coordinates = Coordinates::Inside,
+            Command::CoordinatesOutside => self.settings.coordinates = Coordinates::Outside,
+            Command::ToggleHoveredSquare => {
+                self.settings.show_hovered_square = !self.settings.show_hovered_square
+            }
+            Command::ClearAnnotations => {
+                let annotations = self.game.annotations_mut(self.game.current());
+                if !annotations.is_empty() {
+                    annotations.clear();
+                    self.dirty = true;
+                }
+            }
+            Command::ToggleAnalysis => {
+                let engine = self.settings.analysis_engine.as_deref();
+                self.analysis.toggle(engine);
+            }
+            Command::AnalyzeGame => self.review.open(),
+            Command::ToggleEditingPast => {
+                self.settings.allow_editing_past = !self.settings.allow_editing_past
+            }
+            Command::EditClockSettings => self.clock_settings.open(),
+            Command::OpenPreferences => self.preferences.open(),
+            Command::GoToStart => self.go_to(NodeId::ROOT),
+            Command::StepBack => {
+                self.game.step_back();
+                self.move_input.clear();
+            }
+            Command::StepForward => {
+                self.game.step_forward();
+                self.move_input.clear();
+            }
+            Command::GoToEnd => {
+                self.game.go_to_end();
+                self.move_input.clear();
+            }
+        }
+    }
+
+    /// Runs the commands whose keyboard shortcuts were pressed
+    fn handle_shortcuts(&mut self, ctx: &egui::Context) {
+        if ctx.input_mut(|i| i.consume_shortcut(&PALETTE_SHORTCUT)) {
+            self.palette.open();
+        }
+        if ctx.wants_keyboard_input() || self.palette.is_open() {
+            return;
+        }
+
+        // egui matches Ctrl+S on Ctrl+Shift+S, so shortcuts with shift are checked first
+        let mut shortcuts: Vec<_> = Command::ALL
+            .into_iter()
+            .filter_map(|command| command.shortcut().map(|shortcut| (command, shortcut)))
+            .collect();
+        shortcuts.sort_by_key(|(_, shortcut)| !shortcut.modifiers.shift);
+        for (command, shortcut) in shortcuts {
+            if ctx.input_mut(|i| i.consume_shortcut(&shortcut)) {
+                self.run(ctx, command);
+            }
+        }
+
+        // the up and down arrows also jump to the start and end
+        if ctx.input(|i| i.key_pressed(Key::ArrowUp)) {
+            self.run(ctx, Command::GoToStart);
+        } else if ctx.input(|i| i.key_pressed(Key::ArrowDown)) {
+            self.run(ctx, Command::GoToEnd);
+        }
     }
 }
 
+/// Returns true if the given file holds a single position in Forsyth-Edwards Notation
+fn is_fen(path: &std::path::Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("fen"))
+}
+
 impl eframe::App for App {
-    #[allow(clippy::needless_if)]
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, SETTINGS_KEY, &self.settings);
+        let session = self.settings.restore_session.then(|| Session {
+            pgn: pgn::write(&self.game),
+            path: self.path.clone(),
+            dirty: self.dirty,
+        });
+        eframe::set_value(storage, SESSION_KEY, &session);
+    }
+
+    #[allow(clippy::needless_ifs)]
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        ctx.set_pixels_per_point(self.ui_scale);
+        // the scale may also have been changed with egui's zoom shortcuts
+        self.settings.ui_scale = ctx.zoom_factor();
+        if ctx.input(|i| i.viewport().close_requested()) && self.dirty {
+            ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+            self.pending = Some(Pending::Quit);
+        }
+        self.handle_shortcuts(ctx);
+        self.update_clock(ctx);
         egui::TopBottomPanel::top("MenuBar").show(ctx, |ui| {
             ui.horizontal(|ui| {
                 ui.menu_button("Game", |ui| {
-                    if ui.button("New").clicked() {}
+                    if ui.button("New").clicked() {
+                        self.run(ctx, Command::NewGame);
+                        ui.close_menu();
+                    }
+                    if ui.button("Set up position").clicked() {
+                        self.run(ctx, Command::SetUpPosition);
+                        ui.close_menu();
+                    }
 
                     ui.separator();
 
-                    if ui.button("Save").clicked() {}
-                    if ui.button("Load").clicked() {}
+                    if ui.button("Save").clicked() {
+                        self.run(ctx, Command::SaveGame);
+                        ui.close_menu();
+                    }
+                    if ui.button("Save as").clicked() {
+                        self.run(ctx, Command::SaveGameAs);
+                        ui.close_menu();
+                    }
+                    if ui.button("Load").clicked() {
+                        self.run(ctx, Command::LoadGame);
+                        ui.close_menu();
+                    }
+                    if ui.button("Game database").clicked() {
+                        self.run(ctx, Command::OpenDatabase);
+                        ui.close_menu();
+                    }
+
+                    ui.separator();
+
+                    if ui.button("Preferences").clicked() {
+                        self.run(ctx, Command::OpenPreferences);
+                        ui.close_menu();
+                    }
                 });
                 ui.menu_button("View", |ui| {
-                    if ui.button("Flip board").clicked() {}
+                    if ui.button("Flip board").clicked() {
+                        self.run(ctx, Command::FlipBoard);
+                    }
                     if ui.button("Automatically flip board").clicked() {}
-                    if ui.button("Show coordinates").clicked() {}
+                    ui.menu_button("Show coordinates", |ui| {
+                        ui.radio_value(
+                            &mut self.settings.coordinates,
+                            Coordinates::Hidden,
+                            "Hidden",
+                        );
+                        ui.radio_value(
+                            &mut self.settings.coordinates,
+                            Coordinates::Inside,
+                            "Inside",
+                        );
+                        ui.radio_value(
+                            &mut self.settings.coordinates,
+                            Coordinates::Outside,
+                            "Outside",
+                        );
+                    });
+                    ui.checkbox(
+                        &mut self.settings.show_hovered_square,
+                        "Show square under mouse",
+                    );
+                    if ui.button("Clear arrows and marked squares").clicked() {
+                        self.run(ctx, Command::ClearAnnotations);
+                        ui.close_menu();
+                    }
+                    let analyzing = self.analysis.is_running();
+                    if ui
+                        .add(egui::Button::new("Analyze").selected(analyzing))
+                        .clicked()
+                    {
+                        self.run(ctx, Command::ToggleAnalysis);
+                        ui.close_menu();
+                    }
+                    if ui.button("Analyze game").clicked() {
+                        self.run(ctx, Command::AnalyzeGame);
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    ui.menu_button("Panels", |ui| self.layout.menu_ui(ui));
                 });
                 ui.menu_button("Rules", |ui| {
-                    if ui.button("Allow editing the past").clicked() {}
-                    if ui.button("Edit clock settings").clicked() {}
+                    ui.checkbox(
+                        &mut self.settings.allow_editing_past,
+                        "Allow editing the past",
+                    );
+                    ui.add_enabled_ui(self.settings.allow_editing_past, |ui| {
+                        ui.menu_button("Moves played in the past", |ui| {
+                            ui.radio_value(
+                                &mut self.settings.edit_past,
+                                EditPast::Variation,
+                                "Start a variation",
+                            );
+                            ui.radio_value(
+                                &mut self.settings.edit_past,
+                                EditPast::Truncate,
+                                "Replace the rest of the game",
+                            );
+                        });
+                    });
+                    if ui.button("Edit clock settings").clicked() {
+                        self.run(ctx, Command::EditClockSettings);
+                        ui.close_menu();
+                    }
                     // todo
                 });
 
-                // TODO: add fuzzy finder
+                let search = format!("Search ({})", ctx.format_shortcut(&PALETTE_SHORTCUT));
+                if ui.button(search).clicked() {
+                    self.palette.open();
+                }
             });
-            ui.add(BoardView::new());
         });
-        egui::CentralPanel::default().show(ctx, |_ui| {});
+        self.update_book();
+        self.update_tablebase();
+        self.explorer.update(self.settings.database.as_deref());
+        if self.explorer.is_indexing() {
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
+        let mut action = None;
+        let mut entered = None;
+        let mut suggested = None;
+        // the user may not move for an engine
+        let can_move = self.can_move() && !self.is_engine_turn();
+        let thinking = self.thinking.as_ref().map(|_| self.game.board().to_move());
+        self.layout.show(ctx, |ui, kind| match kind {
+            PanelKind::Players => PlayersView::new(&self.game)
+                .flipped(self.settings.flipped)
+                .theme(&self.settings.board_theme)
+                .thinking(thinking)
+                .show(ui),
+            PanelKind::MoveList => {
+                action = MoveList::new(&self.game).show(ui);
+            }
+            PanelKind::MoveEntry => {
+                ui.add_enabled_ui(can_move, |ui| {
+                    entered = self.move_entry.show(ui, self.game.board());
+                });
+            }
+            PanelKind::Clock => match &self.clock {
+                Some(clock) => ClockView::new(clock)
+                    .flipped(self.settings.flipped)
+                    .show(ui),
+                None => {
+                    ui.weak("Untimed");
+                }
+            },
+            PanelKind::Analysis => self.analysis.show(ui, &mut self.settings),
+            PanelKind::Book => {
+                let book = self.book.as_ref().map(|(_, book)| book);
+                suggested = BookMoves::new(book, self.game.board())
+                    .enabled(can_move)
+                    .show(ui);
+            }
+            PanelKind::Explorer => {
+                let ply = self.game.node(self.game.current()).ply;
+                suggested = self.explorer.show(ui, self.game.board(), ply, can_move);
+            }
+        });
+        match action {
+            Some(MoveListAction::GoTo(id)) => self.go_to(id),
+            Some(MoveListAction::Promote(id)) => {
+                self.game.promote_to_main_line(id);
+                self.dirty = true;
+            }
+            Some(MoveListAction::Remove(id)) => {
+                self.game.remove(id);
+                self.move_input.clear();
+                self.dirty = true;
+            }
+            None => {}
+        }
+        if let Some(r#move) = entered {
+            // the entry box only offers legal moves, and is disabled where moves may not be played
+            self.play_move(r#move).expect("entered moves are playable");
+        }
+        if let Some(r#move) = suggested {
+            // likewise, book and explorer moves are legal and disabled where moves may not be played
+            self.play_move(r#move)
+                .expect("suggested moves are playable");
+        }
+
+        if self.palette.is_open() {
+            self.database.load(&self.settings.game_database);
+        }
+        let games = self
+            .database
+            .database()
+            .map(|database| (database, self.database.results()));
+        let files = &self.settings.recent_files;
+        match self.palette.show(ctx, self.game.board(), files, games) {
+            Some(PaletteItem::Command(command)) => self.run(ctx, command),
+            Some(PaletteItem::Move(r#move)) if self.can_move() && !self.is_engine_turn() => {
+                let san = self.game.board().san(r#move);
+                if let Err(error) = self.play_move(r#move) {
+                    self.error = Some(format!("Unable to play {san}: {error}"));
+                }
+            }
+            Some(PaletteItem::File(path)) => self.discard_then(ctx, Pending::LoadFile(path)),
+            Some(PaletteItem::Game(id)) => {
+                match self.database.database().map(|database| database.game(id)) {
+                    Some(Ok(game)) => self.discard_then(ctx, Pending::Open(Box::new(game))),
+                    Some(Err(error)) => {
+                        self.error = Some(format!("Unable to open the game: {error}"));
+                    }
+                    None => {}
+                }
+            }
+            Some(PaletteItem::Move(_)) | None => {}
+        }
+
+        if let Some(new_game) = self
+            .new_game
+            .show(ctx, &self.settings.engines, self.book.is_some())
+        {
+            self.discard_then(ctx, Pending::NewGame(Box::new(new_game)));
+        }
+        let theme = &self.settings.board_theme;
+        if let Some(board) = self.editor.show(ctx, theme, self.settings.flipped) {
+            let new_game = NewGame {
+                chess960: board.is_chess960(),
+                board,
+                white: String::from("White"),
+                black: String::from("Black"),
+                white_player: Player::Human,
+                black_player: Player::Human,
+                time_control: self.clock_settings.time_control().cloned(),
+                book: false,
+            };
+            self.discard_then(ctx, Pending::NewGame(Box::new(new_game)));
+        }
+        let database = &mut self.settings.game_database;
+        if let Some(game) = self.database.show(ctx, database, self.game.board()) {
+            self.discard_then(ctx, Pending::Open(Box::new(game)));
+        }
+        let engine = (self.settings.analysis_engine.as_deref())
+            .map_or(String::from("Built-in engine"), engine_name);
+        if self.review.show(ctx, &engine) {
+            self.start_review();
+        }
+        self.show_pending(ctx);
+        self.show_error(ctx);
+
+        let (ui_scale, theme) = (self.settings.ui_scale, self.settings.theme);
+        if self.preferences.show(ctx, &mut self.settings) {
+            self.clock_settings.open();
+        }
+        if self.settings.ui_scale != ui_scale {
+            ctx.set_zoom_factor(self.settings.ui_scale);
+        }
+        if self.settings.theme != theme {
+            ctx.set_theme(self.settings.theme);
+        }
+        let time_control = self.clock_settings.time_control().map(ToString::to_string);
+        if self.settings.time_control != time_control {
+            let time_control = self.settings.time_control.as_deref();
+            self.clock_settings
+                .set_time_control(time_control.and_then(|text| text.parse().ok()));
+        }
+
+        if self.clock_settings.show(ctx) {
+            // restart the clock with the new time control, counting from the next move
+            self.clock = self.clock_settings.time_control().cloned().map(Clock::new);
+        }
+        self.settings.time_control = self.clock_settings.time_control().map(ToString::to_string);
+
+        // against an engine, the user may queue premoves during its turn
+        let can_move = self.can_move() && (!self.is_engine_turn() || self.human().is_some());
+        let human = self.human();
+        self.analysis
+            .update(&self.game, self.settings.analysis_lines);
+        if self.analysis.is_running() {
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
+        if self.review.update(&mut self.game) {
+            self.dirty = true;
+        }
+        if self.review.is_running() {
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
+        let opening = eco::classify_game(&self.game, self.game.current());
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let eval_bar = self.analysis.is_running();
+            let gap = ui.spacing().item_spacing.x;
+            let mut rect = ui.available_rect_before_wrap();
+            if eval_bar {
+                // room is left on both sides so the board stays centered
+                rect = rect.shrink2(egui::vec2(EVAL_BAR_WIDTH + gap, 0.0));
+            }
+            // room is always left for the opening's name, so the board does not jump when it
+            // first appears
+            let opening_height = ui.text_style_height(&egui::TextStyle::Body);
+            rect.max.y -= opening_height + gap;
+
+            // a line previewed from the analysis is shown in place of the game
+            let preview = self.analysis.preview();
+            let (board, last_move) = preview.unwrap_or((self.game.board(), self.game.last_move()));
+            let mut board_view = BoardView::new(board)
+                .last_move(last_move)
+                .flipped(self.settings.flipped)
+                .coordinates(self.settings.coordinates)
+                .show_hovered_square(self.settings.show_hovered_square)
+                .theme(&self.settings.board_theme)
+                .animation(&mut self.animation, self.settings.animation_speed);
+            if preview.is_none() {
+                board_view = board_view.annotations(
+                    &mut self.painting,
+                    &self.game.node(self.game.current()).annotations,
+                );
+                if can_move {
+                    board_view = board_view.input(&mut self.move_input).player(human);
+                }
+            }
+            let board_rect = ui
+                .allocate_new_ui(egui::UiBuilder::new().max_rect(rect), |ui| {
+                    ui.add(board_view)
+                })
+                .inner
+                .rect;
+
+            if eval_bar {
+                let bar = egui::Rect::from_min_max(
+                    egui::pos2(board_rect.left() - gap - EVAL_BAR_WIDTH, board_rect.top()),
+                    egui::pos2(board_rect.left() - gap, board_rect.bottom()),
+                );
+                self.analysis.paint_eval_bar(ui, bar, self.settings.flipped);
+            }
+            if let Some(opening) = opening {
+                let below = egui::Rect::from_min_size(
+                    egui::pos2(board_rect.left(), board_rect.bottom() + gap),
+                    egui::vec2(board_rect.width(), opening_height),
+                );
+                ui.put(below, egui::Label::new(opening.to_string()).truncate());
+            }
+        });
+
+        if self
+            .painting
+            .apply(self.game.annotations_mut(self.game.current()))
+        {
+            self.dirty = true;
+        }
+
+        if let Some(r#move) = self.move_input.take_move() {
+            // the board only offers legal moves, from positions where moves may be played
+            self.play_move(r#move).expect("board input is playable");
+        } else if self.can_move() {
+            // moves queued during the opponent's turn are played as soon as the user's turn comes
+            if let Some(r#move) = self.move_input.play_premove(self.game.board()) {
+                self.play_move(r#move)
+                    .expect("premoves are only played when legal");
+            }
+        }
+        self.update_engine(ctx);
     }
 }
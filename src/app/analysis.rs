@@ -0,0 +1,408 @@
+use std::{path::Path, sync::Arc};
+
+use eframe::{
+    egui::{Button, ComboBox, DragValue, RichText, Ui, Vec2},
+    epaint::{Color32, Rect, Rounding},
+};
+
+use crate::{
+    board::Board,
+    engine::{strength::Strength, uci::UciEngine, Engine, Event, Info, Limits, Score, Thinking},
+    game::Game,
+    piece::PieceColor,
+    r#move::Move,
+    tablebase::{Probe, Tablebase, Wdl},
+};
+
+use super::{new_game::engine_name, preferences::Settings};
+
+/// The most lines that can be shown at once
+const MAX_LINES: u32 = 5;
+
+/// The width of the evaluation bar beside the board, in points
+pub const EVAL_BAR_WIDTH: f32 = 16.0;
+
+/// How quickly the evaluation bar fills as the advantage grows, per centipawn
+const EVAL_BAR_SCALE: f32 = 0.004;
+
+/// The color of white's share of the evaluation bar
+const EVAL_BAR_WHITE: Color32 = Color32::from_gray(235);
+
+/// The color of black's share of the evaluation bar
+const EVAL_BAR_BLACK: Color32 = Color32::from_gray(40);
+
+/// An engine analyzing the position being viewed, and the best lines it has found
+///
+/// The analysis restarts whenever the position being viewed changes, and runs until it is
+/// turned off.
+pub struct Analysis {
+    engine: Option<Engine>,
+    thinking: Option<Thinking>,
+
+    /// The position being analyzed, in Forsyth-Edwards Notation, to notice when it changes
+    position: Option<String>,
+    board: Board,
+    multi_pv: u32,
+    lines: Vec<Info>,
+
+    /// The moves leading to the position previewed in place of the game, and that position
+    preview: Option<(Vec<Move>, Board)>,
+    error: Option<&'static str>,
+    tablebase: Option<Arc<Tablebase>>,
+
+    /// The position last looked up in the tablebase, in Forsyth-Edwards Notation, and what the
+    /// tablebase knows about it, kept whether or not an engine is analyzing
+    probed: Option<String>,
+    probe: Option<(Board, Probe)>,
+}
+
+impl Analysis {
+    /// Constructs a new, stopped analysis
+    pub fn new() -> Self {
+        Self {
+            engine: None,
+            thinking: None,
+            position: None,
+            board: Board::new_init(),
+            multi_pv: 1,
+            lines: Vec::new(),
+            preview: None,
+            error: None,
+            tablebase: None,
+            probed: None,
+            probe: None,
+        }
+    }
+
+    /// Sets the endgame tablebase looked up in the position being viewed and given to the
+    /// built-in engine when it next starts
+    pub fn set_tablebase(&mut self, tablebase: Option<Arc<Tablebase>>) {
+        self.tablebase = tablebase;
+        self.probed = None;
+        self.probe = None;
+    }
+
+    /// Returns true if an engine is analyzing
+    pub fn is_running(&self) -> bool {
+        self.engine.is_some()
+    }
+
+    /// Starts analyzing with the engine at the given path, or the built-in engine if `None`
+    pub fn start(&mut self, path: Option<&Path>) {
+        self.stop();
+        self.engine = match path {
+            None => Some(Engine::BuiltIn(Strength::FULL, self.tablebase.clone())),
+            Some(path) => match UciEngine::spawn(path) {
+                Ok(engine) => Some(Engine::External(engine)),
+                Err(error) => {
+                    self.error = Some(error);
+                    return;
+                }
+            },
+        };
+    }
+
+    /// Stops analyzing, forgetting the lines found
+    pub fn stop(&mut self) {
+        self.thinking = None;
+        self.engine = None;
+        self.position = None;
+        self.lines.clear();
+        self.preview = None;
+        self.error = None;
+    }
+
+    /// Starts analyzing if stopped, otherwise stops
+    pub fn toggle(&mut self, path: Option<&Path>) {
+        match self.is_running() {
+            true => self.stop(),
+            false => self.start(path),
+        }
+    }
+
+    /// Takes the lines found since the last update, restarting the analysis if the position
+    /// being viewed or the number of lines to find changed
+    pub fn update(&mut self, game: &Game, multi_pv: u32) {
+        self.update_probe(game.board());
+        let Some(engine) = &self.engine else {
+            return;
+        };
+
+        let fen = game.board().fen();
+        if self.position.as_ref() != Some(&fen) || self.multi_pv != multi_pv {
+            let limits = Limits {
+                multi_pv: Some(multi_pv),
+                ..Limits::default()
+            };
+            // the old search is stopped before the new one starts
+            self.thinking = None;
+            let moves = game.moves_to(game.current());
+            self.thinking = Some(engine.think(game.start(), &moves, limits));
+            self.position = Some(fen);
+            self.board = game.board().clone();
+            self.multi_pv = multi_pv;
+            self.lines.clear();
+            self.preview = None;
+        }
+
+        let Some(thinking) = &self.thinking else {
+            return;
+        };
+        for event in thinking.events() {
+            match event {
+                Event::Info(info) => {
+                    let index = info.multi_pv.saturating_sub(1) as usize;
+                    match self.lines.get_mut(index) {
+                        Some(line) => *line = info,
+                        None => self.lines.push(info),
+                    }
+                }
+                // the search only ends early once it has found a forced mate
+                Event::BestMove(_) => {}
+                Event::Failed(error) => {
+                    self.stop();
+                    self.error = Some(error);
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Looks up the given position in the tablebase, if it changed since it was last looked up
+    fn update_probe(&mut self, board: &Board) {
+        let Some(tablebase) = &self.tablebase else {
+            return;
+        };
+        let fen = board.fen();
+        if self.probed.as_ref() == Some(&fen) {
+            return;
+        }
+        self.probe = tablebase.probe(board).map(|probe| (board.clone(), probe));
+        self.probed = Some(fen);
+        // a tablebase move previewed in the old position no longer follows from the game
+        if !self.is_running() {
+            self.preview = None;
+        }
+    }
+
+    /// Returns the position of the line being previewed in place of the game, with the move
+    /// that reached it
+    pub fn preview(&self) -> Option<(&Board, Option<Move>)> {
+        self.preview
+            .as_ref()
+            .map(|(moves, board)| (board, moves.last().copied()))
+    }
+
+    /// Shows the controls and the best lines, previewing the position in a line when one of its
+    /// moves is clicked
+    pub fn show(&mut self, ui: &mut Ui, settings: &mut Settings) {
+        ui.horizontal(|ui| {
+            let running = self.is_running();
+            if ui.add(Button::new("Analyze").selected(running)).clicked() {
+                self.toggle(settings.analysis_engine.as_deref());
+            }
+
+            let previous = settings.analysis_engine.clone();
+            let name = (settings.analysis_engine.as_deref())
+                .map_or(String::from("Built-in engine"), engine_name);
+            ComboBox::from_id_salt("AnalysisEngine")
+                .selected_text(name)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut settings.analysis_engine, None, "Built-in engine");
+                    for engine in &settings.engines {
+                        let name = engine_name(engine);
+                        ui.selectable_value(
+                            &mut settings.analysis_engine,
+                            Some(engine.clone()),
+                            name,
+                        );
+                    }
+                });
+            if running && settings.analysis_engine != previous {
+                self.start(settings.analysis_engine.as_deref());
+            }
+
+            let lines = DragValue::new(&mut settings.analysis_lines)
+                .range(1..=MAX_LINES)
+                .suffix(" lines");
+            ui.add(lines);
+        });
+
+        if let Some(error) = self.error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+
+        let mut previewed = None;
+        if let Some((board, probe)) = &self.probe {
+            ui.separator();
+            previewed = self.tablebase_ui(ui, board, probe);
+        }
+        if self.is_running() {
+            previewed = self.lines_ui(ui).or(previewed);
+        }
+
+        if self.preview.is_some() {
+            ui.separator();
+            if ui.button("Back to game").clicked() {
+                previewed = Some(None);
+            }
+        }
+        if let Some(preview) = previewed {
+            self.preview = preview;
+        }
+    }
+
+    /// Shows the best lines found, returning the preview to show if one of their moves was
+    /// clicked
+    fn lines_ui(&self, ui: &mut Ui) -> Option<Option<(Vec<Move>, Board)>> {
+        if self.board.legal_moves().is_empty() {
+            ui.weak("No moves to analyze");
+            return None;
+        }
+        if self.lines.is_empty() {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.weak("thinking...");
+            });
+        }
+
+        let mut previewed = None;
+        for info in &self.lines {
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label(RichText::new(self.white_score(info.score).to_string()).strong());
+                let seconds = info.time.as_secs_f64().max(0.001);
+                ui.weak(format!(
+                    "depth {}, {} nodes, {} nodes/s",
+                    info.depth,
+                    format_count(info.nodes),
+                    format_count((info.nodes as f64 / seconds) as u64),
+                ));
+            });
+            ui.horizontal_wrapped(|ui| {
+                ui.spacing_mut().item_spacing.x = 2.0;
+                let mut board = self.board.clone();
+                for (ply, &r#move) in info.pv.iter().enumerate() {
+                    let (number, color) = (board.fullmove_number(), board.to_move());
+                    if color == PieceColor::White {
+                        ui.weak(format!("{number}."));
+                    } else if ply == 0 {
+                        ui.weak(format!("{number}..."));
+                    }
+
+                    let moves = &info.pv[..=ply];
+                    let selected = self
+                        .preview
+                        .as_ref()
+                        .is_some_and(|(previewed, _)| previewed == moves);
+                    let clicked = ui.selectable_label(selected, board.san(r#move)).clicked();
+                    board.play_move(r#move);
+                    if clicked {
+                        previewed = Some(match selected {
+                            true => None,
+                            false => Some((moves.to_vec(), board.clone())),
+                        });
+                    }
+                }
+            });
+        }
+
+        previewed
+    }
+
+    /// Shows what the tablebase knows about the given position and its best moves, returning the
+    /// preview to show if one of the moves was clicked
+    fn tablebase_ui(
+        &self,
+        ui: &mut Ui,
+        board: &Board,
+        probe: &Probe,
+    ) -> Option<Option<(Vec<Move>, Board)>> {
+        // the distance counts plies to the next capture or pawn move, not to mate
+        let plies = probe.dtz.unsigned_abs();
+        let plies = match plies > 100 {
+            true => plies - 100,
+            false => plies,
+        };
+        let text = match probe.wdl {
+            Wdl::Win => format!("Tablebase win in {plies}"),
+            Wdl::CursedWin => format!("Tablebase win in {plies}, but drawn by the fifty-move rule"),
+            Wdl::Draw => String::from("Tablebase draw"),
+            Wdl::BlessedLoss => {
+                format!("Tablebase loss in {plies}, but drawn by the fifty-move rule")
+            }
+            Wdl::Loss => format!("Tablebase loss in {plies}"),
+        };
+        ui.label(RichText::new(text).strong()).on_hover_text(
+            "The number of plies to the next capture or pawn move with perfect play, \
+             for the player to move",
+        );
+
+        let mut previewed = None;
+        ui.horizontal_wrapped(|ui| {
+            ui.spacing_mut().item_spacing.x = 2.0;
+            for &r#move in &probe.best_moves {
+                let moves = [r#move];
+                let selected = self
+                    .preview
+                    .as_ref()
+                    .is_some_and(|(previewed, _)| previewed == &moves);
+                if ui.selectable_label(selected, board.san(r#move)).clicked() {
+                    let mut board = board.clone();
+                    board.play_move(r#move);
+                    previewed = Some(match selected {
+                        true => None,
+                        false => Some((moves.to_vec(), board)),
+                    });
+                }
+            }
+        });
+        previewed
+    }
+
+    /// Paints a bar in the given rectangle, filled with white and black in proportion to how
+    /// good the position is for each
+    pub fn paint_eval_bar(&self, ui: &Ui, rect: Rect, flipped: bool) {
+        let white_share = match self.lines.first().map(|info| self.white_score(info.score)) {
+            Some(Score::Centipawns(centipawns)) => {
+                1.0 / (1.0 + (-EVAL_BAR_SCALE * centipawns as f32).exp())
+            }
+            Some(Score::Mate(moves)) if moves > 0 => 1.0,
+            Some(Score::Mate(_)) => 0.0,
+            None => 0.5,
+        };
+
+        // white's share grows from white's side of the board
+        let painter = ui.painter();
+        painter.rect_filled(rect, Rounding::same(2.0), EVAL_BAR_BLACK);
+        let height = rect.height() * white_share;
+        let white = match flipped {
+            false => Rect::from_min_max(rect.left_bottom() - Vec2::new(0.0, height), rect.max),
+            true => Rect::from_min_max(rect.min, rect.right_top() + Vec2::new(0.0, height)),
+        };
+        painter.rect_filled(white, Rounding::same(2.0), EVAL_BAR_WHITE);
+    }
+
+    /// Returns the given score for the player to move as a score for white
+    fn white_score(&self, score: Score) -> Score {
+        match self.board.to_move() {
+            PieceColor::White => score,
+            PieceColor::Black => score.negate(),
+        }
+    }
+}
+
+impl Default for Analysis {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Formats a large count briefly, i.e. "950", "12.3k", or "4.5M"
+fn format_count(count: u64) -> String {
+    match count {
+        0..1_000 => count.to_string(),
+        1_000..1_000_000 => format!("{:.1}k", count as f64 / 1e3),
+        _ => format!("{:.1}M", count as f64 / 1e6),
+    }
+}
@@ -0,0 +1,162 @@
+use eframe::emath::easing;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    board::{Board, Square},
+    piece::Piece,
+};
+
+/// How quickly pieces move between squares
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AnimationSpeed {
+    /// Pieces jump straight to their new squares
+    Off,
+
+    /// Pieces take half a second to move
+    Slow,
+
+    /// Pieces take a fifth of a second to move
+    #[default]
+    Normal,
+
+    /// Pieces take a tenth of a second to move
+    Fast,
+}
+
+impl AnimationSpeed {
+    /// Returns how long a move takes to animate, in seconds
+    pub const fn duration(&self) -> f64 {
+        match self {
+            Self::Off => 0.0,
+            Self::Slow => 0.5,
+            Self::Normal => 0.2,
+            Self::Fast => 0.1,
+        }
+    }
+}
+
+/// How a piece is drawn partway through an animation
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PieceMotion {
+    /// The piece slides from the first square towards the second, the given fraction of the way
+    Sliding(Square, Square, f32),
+
+    /// The piece is drawn on its square with the given opacity
+    Fading(Square, f32),
+}
+
+/// The pieces moving between the last position shown and the current one, kept between frames
+///
+/// Rather than animating moves themselves, the position shown is compared with the one shown
+/// before it, so moves played, moves taken back and jumps through the game all animate the same
+/// way. Pieces that have gone slide to where pieces like them have appeared, and pieces with
+/// nowhere to go fade out.
+#[derive(Debug, Default)]
+pub struct Animation {
+    shown: Vec<(Square, Piece)>,
+    slides: Vec<(Square, Square)>,
+    appearing: Vec<Square>,
+    vanishing: Vec<(Square, Piece)>,
+    start: f64,
+    skip_next: bool,
+}
+
+impl Animation {
+    /// Constructs a new animation state, with nothing moving
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Shows the next change of position without animating it, as when the user has dropped a
+    /// piece where it is going
+    pub fn skip_next(&mut self) {
+        self.skip_next = true;
+    }
+
+    /// Starts animating the change from the last position shown, if the given one is different
+    pub fn update(&mut self, board: &Board, time: f64) {
+        let pieces: Vec<_> = board.pieces().collect();
+        if pieces == self.shown {
+            return;
+        }
+        let previous = std::mem::replace(&mut self.shown, pieces);
+        self.slides.clear();
+        self.appearing.clear();
+        self.vanishing.clear();
+        if std::mem::take(&mut self.skip_next) || previous.is_empty() {
+            return;
+        }
+
+        let mut gone: Vec<_> = previous
+            .iter()
+            .filter(|&&(square, piece)| board.get_piece(square) != Some(piece))
+            .copied()
+            .collect();
+        let arrived: Vec<_> = self
+            .shown
+            .iter()
+            .filter(|piece| !previous.contains(piece))
+            .copied()
+            .collect();
+
+        for (to, piece) in arrived {
+            // a pawn that promoted slides as the piece it became
+            let nearest = [true, false].into_iter().find_map(|same_kind| {
+                gone.iter()
+                    .enumerate()
+                    .filter(|(_, (_, other))| match same_kind {
+                        true => *other == piece,
+                        false => other.color == piece.color,
+                    })
+                    .min_by_key(|(_, (from, _))| distance(*from, to))
+                    .map(|(index, _)| index)
+            });
+            match nearest {
+                Some(index) => {
+                    let (from, _) = gone.remove(index);
+                    self.slides.push((from, to));
+                }
+                None => self.appearing.push(to),
+            }
+        }
+        self.vanishing = gone;
+        self.start = time;
+    }
+
+    /// Returns how far through the animation it is at the given time, from 0 to 1, or `None` if
+    /// nothing is moving
+    pub fn progress(&self, time: f64, speed: AnimationSpeed) -> Option<f32> {
+        let moving =
+            !(self.slides.is_empty() && self.appearing.is_empty() && self.vanishing.is_empty());
+        let elapsed = (time - self.start) / speed.duration();
+        (moving && elapsed < 1.0).then_some(elapsed as f32)
+    }
+
+    /// Returns how the piece on the given square is drawn, given how far through the animation
+    /// it is, or `None` if it is drawn normally
+    pub fn motion(&self, square: Square, progress: f32) -> Option<PieceMotion> {
+        let eased = easing::cubic_out(progress);
+        if let Some(&(from, to)) = self.slides.iter().find(|(_, to)| *to == square) {
+            return Some(PieceMotion::Sliding(from, to, eased));
+        }
+        self.appearing
+            .contains(&square)
+            .then_some(PieceMotion::Fading(square, eased))
+    }
+
+    /// Returns the pieces that are no longer on the board but are still fading out, with how
+    /// they are drawn given how far through the animation it is
+    pub fn vanishing(&self, progress: f32) -> impl Iterator<Item = (Piece, PieceMotion)> + '_ {
+        let opacity = 1.0 - easing::cubic_out(progress);
+        self.vanishing
+            .iter()
+            .map(move |&(square, piece)| (piece, PieceMotion::Fading(square, opacity)))
+    }
+}
+
+/// Returns the number of king moves between two squares
+fn distance(a: Square, b: Square) -> u8 {
+    let (a_file, a_rank) = a.indices();
+    let (b_file, b_rank) = b.indices();
+    a_file.abs_diff(b_file).max(a_rank.abs_diff(b_rank))
+}
@@ -1,64 +1,690 @@
 use eframe::{
-    egui::{Sense, Widget},
-    epaint::{Color32, Rect, Rounding, Vec2},
+    egui::{include_image, Align2, FontId, Image, ImageSource, PointerButton, Sense, Ui, Widget},
+    epaint::{Color32, Pos2, Rect, Rounding, Stroke, Vec2},
 };
+use serde::{Deserialize, Serialize};
 
-use crate::board::{File, Rank, Square};
+use crate::{
+    annotation::Annotations,
+    board::{movegen::PROMOTION_KINDS, Board, Square},
+    piece::{Piece, PieceColor, PieceKind},
+    r#move::Move,
+};
+
+use super::{
+    animation::{Animation, AnimationSpeed, PieceMotion},
+    painting::Painting,
+    theme::{BoardTheme, DEFAULT_THEME},
+};
+
+/// The smallest size the board is drawn at, in points
+const MIN_SIZE: f32 = 64.0;
+
+/// The tint over the squares of the last move played
+const LAST_MOVE_TINT: Color32 = Color32::from_rgba_premultiplied(120, 120, 0, 90);
+
+/// The tint over the selected square
+const SELECTED_TINT: Color32 = Color32::from_rgba_premultiplied(20, 100, 20, 110);
+
+/// The tint over the squares of queued premoves
+const PREMOVE_TINT: Color32 = Color32::from_rgba_premultiplied(20, 60, 120, 110);
+
+/// The tint over a king in check
+const CHECK_TINT: Color32 = Color32::from_rgba_premultiplied(170, 0, 0, 150);
+
+/// Where board coordinates are drawn, if at all
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Coordinates {
+    /// No coordinates are drawn
+    #[default]
+    Hidden,
+
+    /// Coordinates are drawn inside the edge squares
+    Inside,
+
+    /// Coordinates are drawn in a margin outside the board
+    Outside,
+}
+
+/// A move queued during the opponent's turn, to be played when the user's turn comes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Premove {
+    piece: Piece,
+    from: Square,
+    to: Square,
+    promotion: Option<PieceKind>,
+}
+
+/// The state of the user entering a move on the board, kept between frames
+#[derive(Debug, Default)]
+pub struct MoveInput {
+    selected: Option<Square>,
+    promotion: Option<(Square, Square)>,
+    played: Option<Move>,
+    premoves: Vec<Premove>,
+}
+
+impl MoveInput {
+    /// Takes the move the user has entered, if any
+    pub fn take_move(&mut self) -> Option<Move> {
+        self.played.take()
+    }
+
+    /// Clears the selected piece, any pending promotion, and any queued premoves
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Clears the selected piece and any pending promotion, keeping queued premoves
+    pub fn deselect(&mut self) {
+        self.selected = None;
+        self.promotion = None;
+    }
+
+    /// Takes the first queued premove if it is its turn, returning it if it is legal, or clearing
+    /// every premove if it is not
+    pub fn play_premove(&mut self, board: &Board) -> Option<Move> {
+        let premove = *self.premoves.first()?;
+        if premove.piece.color != board.to_move() {
+            return None;
+        }
+        self.premoves.remove(0);
+
+        let r#move = board
+            .legal_moves_from(premove.from)
+            .into_iter()
+            .find(|r#move| {
+                r#move.to() == premove.to
+                    && r#move.promotion().map(|piece| piece.kind) == premove.promotion
+            });
+        if r#move.is_none() {
+            self.premoves.clear();
+            self.selected = None;
+        }
+        r#move
+    }
+
+    /// Returns the board as it will be after the queued premoves, whether or not they are legal
+    fn premove_board(&self, board: &Board) -> Board {
+        let mut board = board.clone();
+        for premove in &self.premoves {
+            let color = premove.piece.color;
+            let files = board.castling_files();
+            let castle = [
+                Move::kingside_castle(color, files),
+                Move::queenside_castle(color, files),
+            ]
+            .into_iter()
+            .find(|castle| castle.from() == premove.from && castle.to() == premove.to);
+            let rook = castle
+                .filter(|_| premove.piece.kind == PieceKind::King)
+                .and_then(|castle| castle.castling_rook())
+                .and_then(|(from, to)| Some((board.take_piece(from)?, to)));
+
+            board.remove_piece(premove.from);
+            let kind = premove.promotion.unwrap_or(premove.piece.kind);
+            board.set_piece(premove.to, Piece::new(kind, color));
+            if let Some((rook, to)) = rook {
+                board.set_piece(to, rook);
+            }
+        }
+        board
+    }
+
+    /// Handles the user choosing to move the selected piece to the given square, queueing it as
+    /// a premove if it is not the user's turn
+    fn try_move(&mut self, board: &Board, to: Square, premoving: bool) {
+        let Some(from) = self.selected else {
+            return;
+        };
+
+        if premoving {
+            // the selected piece may have been captured or moved since it was selected
+            let Some(piece) = board.get_piece(from) else {
+                self.selected = None;
+                return;
+            };
+            if !board.premove_targets(from).contains(&to) {
+                return self.select(board, to, piece.color);
+            }
+            // premoved pawns always promote to a queen, as there is no time to choose
+            let last_rank = match piece.color {
+                PieceColor::White => 7,
+                PieceColor::Black => 0,
+            };
+            let promotion = (piece.kind == PieceKind::Pawn && to.rank.index() == last_rank)
+                .then_some(PieceKind::Queen);
+            self.premoves.push(Premove {
+                piece,
+                from,
+                to,
+                promotion,
+            });
+            self.selected = None;
+            return;
+        }
+
+        let moves: Vec<_> = board
+            .legal_moves_from(from)
+            .into_iter()
+            .filter(|r#move| r#move.to() == to)
+            .collect();
+
+        match moves.first() {
+            Some(r#move) if r#move.promotion().is_some() => self.promotion = Some((from, to)),
+            Some(r#move) => {
+                self.played = Some(*r#move);
+                self.selected = None;
+            }
+            None => self.select(board, to, board.to_move()),
+        }
+    }
+
+    /// Selects the piece on the given square if it belongs to the given player, otherwise clears
+    /// the selection
+    fn select(&mut self, board: &Board, square: Square, player: PieceColor) {
+        self.selected = board
+            .get_piece(square)
+            .filter(|piece| piece.color == player)
+            .map(|_| square);
+    }
+}
 
 /// A view of the chess board
-pub struct BoardView;
+pub struct BoardView<'a> {
+    board: &'a Board,
+    input: Option<&'a mut MoveInput>,
+    painting: Option<(&'a mut Painting, &'a Annotations)>,
+    animation: Option<(&'a mut Animation, AnimationSpeed)>,
+    player: Option<PieceColor>,
+    last_move: Option<Move>,
+    flipped: bool,
+    coordinates: Coordinates,
+    show_hovered_square: bool,
+    theme: &'a BoardTheme,
+}
+
+impl<'a> BoardView<'a> {
+    /// Constructs a new `BoardView` of the given board
+    pub fn new(board: &'a Board) -> Self {
+        Self {
+            board,
+            input: None,
+            painting: None,
+            animation: None,
+            player: None,
+            last_move: None,
+            flipped: false,
+            coordinates: Coordinates::Hidden,
+            show_hovered_square: false,
+            theme: &DEFAULT_THEME,
+        }
+    }
+
+    /// Lets the user enter moves for the player to move, storing them in the given input state
+    #[must_use]
+    pub fn input(mut self, input: &'a mut MoveInput) -> Self {
+        self.input = Some(input);
+        self
+    }
+
+    /// Draws the given arrows and marked squares, and lets the user edit them, storing their
+    /// changes in the given painting state
+    #[must_use]
+    pub fn annotations(mut self, painting: &'a mut Painting, annotations: &'a Annotations) -> Self {
+        self.painting = Some((painting, annotations));
+        self
+    }
+
+    /// Animates pieces moving between the positions shown at the given speed, storing their
+    /// progress in the given animation state
+    #[must_use]
+    pub fn animation(mut self, animation: &'a mut Animation, speed: AnimationSpeed) -> Self {
+        self.animation = Some((animation, speed));
+        self
+    }
+
+    /// Sets the side the user plays, so moves entered during the other side's turn are queued as
+    /// premoves, or `None` if the user plays both sides
+    #[must_use]
+    pub fn player(mut self, player: Option<PieceColor>) -> Self {
+        self.player = player;
+        self
+    }
+
+    /// Sets the move to highlight as the last one played
+    #[must_use]
+    pub fn last_move(mut self, last_move: Option<Move>) -> Self {
+        self.last_move = last_move;
+        self
+    }
+
+    /// Sets whether the board is viewed from black's side
+    #[must_use]
+    pub fn flipped(mut self, flipped: bool) -> Self {
+        self.flipped = flipped;
+        self
+    }
+
+    /// Sets where board coordinates are drawn
+    #[must_use]
+    pub fn coordinates(mut self, coordinates: Coordinates) -> Self {
+        self.coordinates = coordinates;
+        self
+    }
+
+    /// Sets whether the name of the square under the mouse is shown
+    #[must_use]
+    pub fn show_hovered_square(mut self, show: bool) -> Self {
+        self.show_hovered_square = show;
+        self
+    }
 
-impl BoardView {
-    /// Constructs a new `BoardView`
-    pub fn new() -> Self {
-        Self {}
+    /// Sets how the board and pieces look
+    #[must_use]
+    pub fn theme(mut self, theme: &'a BoardTheme) -> Self {
+        self.theme = theme;
+        self
     }
 }
 
-impl Default for BoardView {
-    fn default() -> Self {
-        Self::new()
+/// Where the board and its squares are on screen
+struct Geometry {
+    board_rect: Rect,
+    square_size: f32,
+    flipped: bool,
+}
+
+impl Geometry {
+    /// Returns the square drawn at the given column and row, from the top left
+    fn square_at(&self, col: u8, row: u8) -> Square {
+        match self.flipped {
+            false => Square::from_indices(col, 7 - row),
+            true => Square::from_indices(7 - col, row),
+        }
+    }
+
+    /// Returns the column and row at which the given square is drawn, from the top left
+    fn screen_indices(&self, square: Square) -> (u8, u8) {
+        let (f, r) = square.indices();
+        match self.flipped {
+            false => (f, 7 - r),
+            true => (7 - f, r),
+        }
+    }
+
+    /// Returns the rect of the square drawn at the given column and row
+    fn cell_rect(&self, col: u8, row: u8) -> Rect {
+        Rect::from_min_size(
+            self.board_rect.min + Vec2::new(col as f32, row as f32) * self.square_size,
+            Vec2::splat(self.square_size),
+        )
+    }
+
+    /// Returns the rect of the given square
+    fn square_rect(&self, square: Square) -> Rect {
+        let (col, row) = self.screen_indices(square);
+        self.cell_rect(col, row)
+    }
+
+    /// Returns the column and row under the given position, if it is on the board
+    fn cell_at_pos(&self, pos: Pos2) -> Option<(u8, u8)> {
+        self.board_rect.contains(pos).then(|| {
+            let index = (pos - self.board_rect.min) / self.square_size;
+            ((index.x as u8).min(7), (index.y as u8).min(7))
+        })
+    }
+
+    /// Returns the square under the given position, if it is on the board
+    fn square_at_pos(&self, pos: Pos2) -> Option<Square> {
+        self.cell_at_pos(pos)
+            .map(|(col, row)| self.square_at(col, row))
+    }
+
+    /// Returns the cells of the promotion picker for a pawn promoting on the given square, in the
+    /// order of [`PROMOTION_KINDS`]
+    fn promotion_cells(&self, to: Square) -> [(u8, u8); 4] {
+        let (col, row) = self.screen_indices(to);
+        match row {
+            0 => [0, 1, 2, 3].map(|row| (col, row)),
+            _ => [7, 6, 5, 4].map(|row| (col, row)),
+        }
     }
 }
 
-impl Widget for BoardView {
-    fn ui(self, ui: &mut eframe::egui::Ui) -> eframe::egui::Response {
-        let size = 650.0;
-        let (rect, response) = ui.allocate_at_least(Vec2::new(size, size), Sense::click_and_drag());
+impl Widget for BoardView<'_> {
+    fn ui(self, ui: &mut Ui) -> eframe::egui::Response {
+        // fill the largest square that fits in the available space, centered within it
+        let available = ui.available_rect_before_wrap();
+        let size = available.width().min(available.height()).max(MIN_SIZE);
+        let rect = Rect::from_center_size(available.center(), Vec2::splat(size));
+        let response = ui.allocate_rect(rect, Sense::click_and_drag());
+
+        let margin = match self.coordinates {
+            Coordinates::Outside => size * 0.04,
+            _ => 0.0,
+        };
+        let board_size = size - margin;
+        let square_size = board_size / 8.0;
+        let geometry = Geometry {
+            board_rect: Rect::from_min_size(
+                rect.min + Vec2::new(margin, 0.0),
+                Vec2::splat(board_size),
+            ),
+            square_size,
+            flipped: self.flipped,
+        };
+        let board_rect = geometry.board_rect;
 
+        let BoardView {
+            board,
+            mut input,
+            mut painting,
+            mut animation,
+            theme,
+            ..
+        } = self;
+        let player = self.player.unwrap_or(board.to_move());
+        let premoving = player != board.to_move();
+        let mut cancelled = false;
+        if let Some(input) = input.as_deref_mut() {
+            cancelled = handle_input(ui, &response, &geometry, board, input, player);
+
+            // a dropped piece is already where it is going
+            if response.drag_stopped_by(PointerButton::Primary) && input.played.is_some() {
+                if let Some((animation, _)) = animation.as_mut() {
+                    animation.skip_next();
+                }
+            }
+        }
+        if let Some((painting, _)) = painting.as_mut().filter(|_| !cancelled) {
+            painting.handle_input(ui, &response, |pos| geometry.square_at_pos(pos));
+        }
+        let selected = input.as_ref().and_then(|input| input.selected);
+        let promotion = input.as_ref().and_then(|input| input.promotion);
+        let dragging = selected.filter(|_| response.dragged_by(PointerButton::Primary));
+        let premoves = input
+            .as_ref()
+            .map_or(Vec::new(), |input| input.premoves.clone());
+        let premove_board = input.as_ref().map(|input| input.premove_board(board));
+
+        let painter = ui.painter();
+
+        for col in 0..8 {
+            for row in 0..8 {
+                let color = theme.square_color(geometry.square_at(col, row));
+                painter.rect_filled(geometry.cell_rect(col, row), Rounding::ZERO, color);
+            }
+        }
+        if let Some(texture) = theme.texture_image() {
+            Image::new(texture).paint_at(ui, board_rect);
+        }
         let painter = ui.painter();
 
-        for file in 'a'..'h' {
-            for rank in 1..8 {
-                let square = Square::new(File::new(file), Rank::new(rank));
-                let (f, r) = square.indices();
+        if let Some(last_move) = self.last_move {
+            for square in [last_move.from(), last_move.to()] {
+                painter.rect_filled(geometry.square_rect(square), Rounding::ZERO, LAST_MOVE_TINT);
+            }
+        }
+        for premove in &premoves {
+            for square in [premove.from, premove.to] {
+                painter.rect_filled(geometry.square_rect(square), Rounding::ZERO, PREMOVE_TINT);
+            }
+        }
+        if let Some(square) = selected {
+            painter.rect_filled(geometry.square_rect(square), Rounding::ZERO, SELECTED_TINT);
+        }
+        if let Some(king) = board
+            .king_square(board.to_move())
+            .filter(|_| board.is_in_check(board.to_move()))
+        {
+            painter.rect_filled(geometry.square_rect(king), Rounding::ZERO, CHECK_TINT);
+        }
+
+        match self.coordinates {
+            Coordinates::Hidden => {}
+            Coordinates::Inside => {
+                let font = FontId::proportional(square_size * 0.2);
+                let pad = square_size * 0.05;
+                for i in 0..8 {
+                    // files along the bottom row, ranks along the leftmost column
+                    let file_square = geometry.square_at(i, 7);
+                    let rank_square = geometry.square_at(0, i);
+
+                    let pos = board_rect.min + Vec2::new(i as f32 + 1.0, 8.0) * square_size
+                        - Vec2::splat(pad);
+                    let color = theme.contrasting_color(file_square);
+                    let text = file_square.file.to_string();
+                    painter.text(pos, Align2::RIGHT_BOTTOM, text, font.clone(), color);
+
+                    let pos =
+                        board_rect.min + Vec2::new(0.0, i as f32) * square_size + Vec2::splat(pad);
+                    let color = theme.contrasting_color(rank_square);
+                    let text = rank_square.rank.to_string();
+                    painter.text(pos, Align2::LEFT_TOP, text, font.clone(), color);
+                }
+            }
+            Coordinates::Outside => {
+                let font = FontId::proportional(margin * 0.8);
+                let color = ui.visuals().text_color();
+                for i in 0..8 {
+                    let offset = (i as f32 + 0.5) * square_size;
+
+                    let pos = Pos2::new(board_rect.min.x + offset, board_rect.max.y + margin * 0.5);
+                    let text = geometry.square_at(i, 7).file.to_string();
+                    painter.text(pos, Align2::CENTER_CENTER, text, font.clone(), color);
+
+                    let pos = Pos2::new(rect.min.x + margin * 0.5, board_rect.min.y + offset);
+                    let text = geometry.square_at(0, i).rank.to_string();
+                    painter.text(pos, Align2::CENTER_CENTER, text, font.clone(), color);
+                }
+            }
+        }
+
+        let time = ui.input(|i| i.time);
+        let progress = animation.as_mut().and_then(|(animation, speed)| {
+            animation.update(board, time);
+            animation.progress(time, *speed)
+        });
+        let animation = animation.map(|(animation, _)| &*animation);
+        if progress.is_some() {
+            ui.ctx().request_repaint();
+        }
+
+        if let (Some(animation), Some(progress)) = (animation, progress) {
+            for (piece, motion) in animation.vanishing(progress) {
+                paint_piece(ui, &geometry, theme.piece_image(piece), motion);
+            }
+        }
+        for (square, piece) in board.pieces() {
+            if dragging == Some(square) {
+                continue;
+            }
+            let image = theme.piece_image(piece);
+            let motion = animation
+                .zip(progress)
+                .and_then(|(a, p)| a.motion(square, p));
+            match motion {
+                Some(motion) => paint_piece(ui, &geometry, image, motion),
+                None => Image::new(image).paint_at(ui, geometry.square_rect(square)),
+            }
+        }
+
+        if let Some(from) = selected {
+            let targets: Vec<_> = match (premoving, &premove_board) {
+                (true, Some(premove_board)) => premove_board
+                    .premove_targets(from)
+                    .into_iter()
+                    .map(|to| (to, false))
+                    .collect(),
+                _ => board
+                    .legal_moves_from(from)
+                    .into_iter()
+                    .map(|r#move| (r#move.to(), r#move.captured().is_some()))
+                    .collect(),
+            };
+            for (to, capture) in targets {
+                let rect = geometry.square_rect(to);
+                match capture {
+                    true => {
+                        let stroke = Stroke::new(square_size * 0.06, Color32::from_black_alpha(90));
+                        painter.circle_stroke(rect.center(), square_size * 0.45, stroke);
+                    }
+                    false => Image::new(include_image!("../../assets/move_dot.png"))
+                        .paint_at(ui, rect.shrink(square_size * 0.35)),
+                }
+            }
+        }
+
+        if let Some((painting, annotations)) = painting {
+            let hovered = response
+                .hover_pos()
+                .and_then(|pos| geometry.square_at_pos(pos));
+            painting.paint(painter, annotations, hovered, |square| {
+                geometry.square_rect(square)
+            });
+        }
 
-                let color = match (f + r) % 2 {
-                    0 => Color32::from_rgb(195, 163, 113),
-                    1 => Color32::from_rgb(113, 78, 47),
-                    _ => unreachable!(),
-                };
+        if let (Some(square), Some(pos)) = (dragging, response.interact_pointer_pos()) {
+            let piece = premove_board.as_ref().unwrap_or(board).get_piece(square);
+            if let Some(piece) = piece {
+                let rect = Rect::from_center_size(pos, Vec2::splat(square_size));
+                Image::new(theme.piece_image(piece)).paint_at(ui, rect);
+            }
+        }
 
-                let (f, r) = (f as f32 / 8.0, r as f32 / 8.0);
+        if let Some((_, to)) = promotion {
+            painter.rect_filled(board_rect, Rounding::ZERO, Color32::from_black_alpha(120));
+            let color = board.to_move();
+            for (kind, (col, row)) in PROMOTION_KINDS.iter().zip(geometry.promotion_cells(to)) {
+                let rect = geometry.cell_rect(col, row);
+                painter.rect_filled(rect, Rounding::same(square_size * 0.1), Color32::LIGHT_GRAY);
+                Image::new(theme.piece_image(Piece::new(*kind, color))).paint_at(ui, rect);
+            }
+        }
 
-                let rect = Rect {
-                    min: rect.min
-                        + Vec2 {
-                            x: f * size,
-                            y: r * size,
-                        },
-                    max: rect.min
-                        + Vec2 {
-                            x: (f + 0.125) * size,
-                            y: (r + 0.125) * size,
-                        },
-                };
+        if self.show_hovered_square {
+            if let Some(pos) = response.hover_pos().filter(|pos| board_rect.contains(*pos)) {
+                let square = geometry
+                    .square_at_pos(pos)
+                    .expect("position is on the board");
 
-                painter.rect_filled(rect, Rounding::ZERO, color);
-                // draw piece images
+                let font = FontId::proportional(square_size * 0.25);
+                let galley = painter.layout_no_wrap(square.to_string(), font, Color32::WHITE);
+                let label =
+                    Rect::from_min_size(pos + Vec2::new(12.0, 12.0), galley.size()).expand(2.0);
+                painter.rect_filled(label, Rounding::same(2.0), Color32::from_black_alpha(180));
+                painter.galley(label.min + Vec2::splat(2.0), galley, Color32::WHITE);
             }
         }
 
         response
     }
 }
+
+/// Draws a piece partway through an animation
+fn paint_piece(ui: &Ui, geometry: &Geometry, image: ImageSource, motion: PieceMotion) {
+    let (rect, opacity) = match motion {
+        PieceMotion::Sliding(from, to, t) => {
+            let from = geometry.square_rect(from);
+            (from.lerp_towards(&geometry.square_rect(to), t), 1.0)
+        }
+        PieceMotion::Fading(square, opacity) => (geometry.square_rect(square), opacity),
+    };
+    let tint = Color32::from_white_alpha((opacity.clamp(0.0, 1.0) * 255.0) as u8);
+    Image::new(image).tint(tint).paint_at(ui, rect);
+}
+
+/// Updates the move input from clicks and drags on the board by the given player, returning true
+/// if a right click cancelled the queued premoves
+fn handle_input(
+    ui: &Ui,
+    response: &eframe::egui::Response,
+    geometry: &Geometry,
+    board: &Board,
+    input: &mut MoveInput,
+    player: PieceColor,
+) -> bool {
+    if response.secondary_clicked() && !input.premoves.is_empty() {
+        input.premoves.clear();
+        input.selected = None;
+        return true;
+    }
+
+    if let Some((from, to)) = input.promotion {
+        if response.clicked() {
+            let cell = response
+                .interact_pointer_pos()
+                .and_then(|pos| geometry.cell_at_pos(pos));
+            let kind = PROMOTION_KINDS
+                .iter()
+                .zip(geometry.promotion_cells(to))
+                .find_map(|(kind, c)| (Some(c) == cell).then_some(*kind));
+
+            input.played = kind.and_then(|kind| {
+                board.legal_moves_from(from).into_iter().find(|r#move| {
+                    r#move.to() == to && r#move.promotion().map(|piece| piece.kind) == Some(kind)
+                })
+            });
+            input.promotion = None;
+            input.selected = None;
+        }
+        return false;
+    }
+
+    // during the opponent's turn, moves are queued from where the earlier premoves leave pieces
+    let premoving = player != board.to_move();
+    let premove_board;
+    let board = match premoving {
+        true => {
+            premove_board = input.premove_board(board);
+            &premove_board
+        }
+        false => board,
+    };
+
+    let pointer_square = response
+        .interact_pointer_pos()
+        .and_then(|pos| geometry.square_at_pos(pos));
+
+    if response.drag_started_by(PointerButton::Primary) {
+        let origin = ui.input(|i| i.pointer.press_origin());
+        if let Some(square) = origin.and_then(|pos| geometry.square_at_pos(pos)) {
+            if input.selected != Some(square) {
+                input.select(board, square, player);
+            }
+        }
+    } else if response.drag_stopped_by(PointerButton::Primary) {
+        match pointer_square {
+            Some(to) if input.selected != Some(to) => input.try_move(board, to, premoving),
+            Some(_) => {}
+            None => input.selected = None,
+        }
+    } else if response.clicked() {
+        match pointer_square {
+            Some(to) if input.selected == Some(to) => input.selected = None,
+            Some(to) if input.selected.is_some() => input.try_move(board, to, premoving),
+            Some(to) => input.select(board, to, player),
+            None => input.selected = None,
+        }
+    }
+    false
+}
+
+/// Returns the image of the given piece
+pub fn piece_image(piece: Piece) -> ImageSource<'static> {
+    match (piece.color, piece.kind) {
+        (PieceColor::White, PieceKind::Pawn) => include_image!("../../assets/white_pawn.png"),
+        (PieceColor::White, PieceKind::Knight) => include_image!("../../assets/white_knight.png"),
+        (PieceColor::White, PieceKind::Bishop) => include_image!("../../assets/white_bishop.png"),
+        (PieceColor::White, PieceKind::Rook) => include_image!("../../assets/white_rook.png"),
+        (PieceColor::White, PieceKind::Queen) => include_image!("../../assets/white_queen.png"),
+        (PieceColor::White, PieceKind::King) => include_image!("../../assets/white_king.png"),
+        (PieceColor::Black, PieceKind::Pawn) => include_image!("../../assets/black_pawn.png"),
+        (PieceColor::Black, PieceKind::Knight) => include_image!("../../assets/black_knight.png"),
+        (PieceColor::Black, PieceKind::Bishop) => include_image!("../../assets/black_bishop.png"),
+        (PieceColor::Black, PieceKind::Rook) => include_image!("../../assets/black_rook.png"),
+        (PieceColor::Black, PieceKind::Queen) => include_image!("../../assets/black_queen.png"),
+        (PieceColor::Black, PieceKind::King) => include_image!("../../assets/black_king.png"),
+    }
+}
@@ -0,0 +1,66 @@
+use eframe::egui::{Button, Grid, Ui};
+
+use crate::{board::Board, book::Book, r#move::Move};
+
+/// A list of the moves an opening book suggests in the position being viewed, with how often
+/// each is played
+pub struct BookMoves<'a> {
+    book: Option<&'a Book>,
+    board: &'a Board,
+    enabled: bool,
+}
+
+impl<'a> BookMoves<'a> {
+    /// Constructs a new `BookMoves` of the moves the given book suggests in the given position
+    pub fn new(book: Option<&'a Book>, board: &'a Board) -> Self {
+        Self {
+            book,
+            board,
+            enabled: true,
+        }
+    }
+
+    /// Sets whether the moves may be clicked to play them
+    #[must_use]
+    pub fn enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    /// Shows the book moves, returning the move the user clicked, if any
+    pub fn show(self, ui: &mut Ui) -> Option<Move> {
+        let Some(book) = self.book else {
+            ui.weak("Choose an opening book in the preferences");
+            return None;
+        };
+        let moves = book.moves(self.board);
+        if moves.is_empty() {
+            ui.weak("Out of book");
+            return None;
+        }
+
+        let total: u32 = moves.iter().map(|book_move| book_move.weight as u32).sum();
+        let mut clicked = None;
+        Grid::new("BookMoves").num_columns(2).show(ui, |ui| {
+            for book_move in moves {
+                let san = self.board.san(book_move.r#move);
+                if ui
+                    .add_enabled(self.enabled, Button::new(san).frame(false))
+                    .clicked()
+                {
+                    clicked = Some(book_move.r#move);
+                }
+                // books made without counting games may weigh every move zero
+                match total {
+                    0 => ui.weak("–"),
+                    _ => ui.weak(format!(
+                        "{:.1}%",
+                        book_move.weight as f32 / total as f32 * 100.0
+                    )),
+                };
+                ui.end_row();
+            }
+        });
+        clicked
+    }
+}
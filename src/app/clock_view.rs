@@ -0,0 +1,221 @@
+use std::time::Duration;
+
+use eframe::{
+    egui::{Align, Context, Frame, Layout, Margin, RichText, Ui, Window},
+    epaint::Color32,
+};
+
+use crate::{
+    clock::{Clock, TimeControl},
+    piece::PieceColor,
+};
+
+/// Time controls offered in the clock settings, by name
+const PRESETS: [(&str, &str); 8] = [
+    ("Bullet", "1+0"),
+    ("Bullet", "2+1"),
+    ("Blitz", "3+2"),
+    ("Blitz", "5+0"),
+    ("Rapid", "10+5"),
+    ("Rapid", "15+10"),
+    ("Classical", "90+30"),
+    ("Classical", "40/90+30, 30+30"),
+];
+
+/// A view of both players' remaining time
+pub struct ClockView<'a> {
+    clock: &'a Clock,
+    flipped: bool,
+}
+
+impl<'a> ClockView<'a> {
+    /// Constructs a new `ClockView` of the given clock
+    pub fn new(clock: &'a Clock) -> Self {
+        Self {
+            clock,
+            flipped: false,
+        }
+    }
+
+    /// Sets whether black's time is shown at the bottom, matching a flipped board
+    #[must_use]
+    pub fn flipped(mut self, flipped: bool) -> Self {
+        self.flipped = flipped;
+        self
+    }
+
+    /// Shows the clock
+    pub fn show(self, ui: &mut Ui) {
+        let order = match self.flipped {
+            false => [PieceColor::Black, PieceColor::White],
+            true => [PieceColor::White, PieceColor::Black],
+        };
+
+        for color in order {
+            let remaining = self.clock.remaining(color);
+            let running = self.clock.running() == Some(color);
+
+            let (fill, text) = if remaining.is_zero() {
+                (Color32::DARK_RED, Color32::WHITE)
+            } else if running {
+                (
+                    ui.visuals().selection.bg_fill,
+                    ui.visuals().strong_text_color(),
+                )
+            } else {
+                (ui.visuals().faint_bg_color, ui.visuals().weak_text_color())
+            };
+
+            Frame::none()
+                .fill(fill)
+                .rounding(4.0)
+                .inner_margin(Margin::symmetric(8.0, 2.0))
+                .show(ui, |ui| {
+                    ui.set_width(ui.available_width());
+                    ui.horizontal(|ui| {
+                        let name = match color {
+                            PieceColor::White => "White",
+                            PieceColor::Black => "Black",
+                        };
+                        ui.label(RichText::new(name).color(text));
+                        ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                            ui.label(
+                                RichText::new(format_time(remaining))
+                                    .monospace()
+                                    .size(18.0)
+                                    .color(text),
+                            );
+                            if let Some(delay) = self.clock.delay_remaining(color) {
+                                ui.label(
+                                    RichText::new(format!("+{:.1}", delay.as_secs_f32()))
+                                        .small()
+                                        .color(text),
+                                );
+                            }
+                        });
+                    });
+                });
+        }
+
+        ui.weak(self.clock.control().to_string());
+    }
+}
+
+/// Formats a remaining time as "h:mm:ss", "m:ss", or "s.t" under ten seconds
+pub fn format_time(time: Duration) -> String {
+    let seconds = time.as_secs();
+    if seconds >= 3600 {
+        format!(
+            "{}:{:02}:{:02}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    } else if seconds >= 10 {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    } else {
+        format!("{:.1}", time.as_secs_f32())
+    }
+}
+
+/// The window for choosing the time control of new clocks
+#[derive(Debug)]
+pub struct ClockSettings {
+    open: bool,
+    time_control: Option<TimeControl>,
+    text: String,
+    error: Option<&'static str>,
+}
+
+impl ClockSettings {
+    /// Constructs new clock settings, with no time control
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            time_control: None,
+            text: String::from("5+3"),
+            error: None,
+        }
+    }
+
+    /// Opens the settings window
+    pub fn open(&mut self) {
+        self.open = true;
+    }
+
+    /// Returns the chosen time control, or `None` for untimed games
+    pub fn time_control(&self) -> Option<&TimeControl> {
+        self.time_control.as_ref()
+    }
+
+    /// Sets the chosen time control, or `None` for untimed games
+    pub fn set_time_control(&mut self, time_control: Option<TimeControl>) {
+        if let Some(time_control) = &time_control {
+            self.text = time_control.to_string();
+        }
+        self.time_control = time_control;
+    }
+
+    /// Shows the settings window if it is open, returning true if new settings were applied
+    pub fn show(&mut self, ctx: &Context) -> bool {
+        let mut open = self.open;
+        let mut applied = false;
+
+        Window::new("Clock settings")
+            .open(&mut open)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.horizontal_wrapped(|ui| {
+                    for (name, preset) in PRESETS {
+                        if ui.button(format!("{name} {preset}")).clicked() {
+                            self.text = preset.to_string();
+                            self.error = None;
+                        }
+                    }
+                });
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label("Time control");
+                    if ui.text_edit_singleline(&mut self.text).changed() {
+                        self.error = self.text.parse::<TimeControl>().err();
+                    }
+                });
+                ui.weak(
+                    "Stages are separated by commas, as [moves/]minutes then +increment, \
+                     d for a simple delay or b for a Bronstein delay in seconds, \
+                     i.e. \"40/90+30, 30+30\" or \"25d5\"",
+                );
+                if let Some(error) = self.error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("Apply").clicked() {
+                        match self.text.parse() {
+                            Ok(time_control) => {
+                                self.time_control = Some(time_control);
+                                applied = true;
+                            }
+                            Err(error) => self.error = Some(error),
+                        }
+                    }
+                    if ui.button("Untimed").clicked() {
+                        self.time_control = None;
+                        applied = true;
+                    }
+                });
+            });
+
+        self.open = open && !applied;
+        applied
+    }
+}
+
+impl Default for ClockSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
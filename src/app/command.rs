@@ -0,0 +1,142 @@
+use eframe::egui::{Key, KeyboardShortcut, Modifiers};
+
+/// An action the user can take from the menus or the command palette
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    /// Start a new game
+    NewGame,
+
+    /// Set up a position by hand to start a game from
+    SetUpPosition,
+
+    /// Save the game to its file
+    SaveGame,
+
+    /// Save the game to a new file
+    SaveGameAs,
+
+    /// Load a game from a file
+    LoadGame,
+
+    /// Browse and search the game database
+    OpenDatabase,
+
+    /// View the board from the other side
+    FlipBoard,
+
+    /// Hide the board coordinates
+    HideCoordinates,
+
+    /// Draw the board coordinates inside the edge squares
+    CoordinatesInside,
+
+    /// Draw the board coordinates in a margin outside the board
+    CoordinatesOutside,
+
+    /// Show or hide the name of the square under the mouse
+    ToggleHoveredSquare,
+
+    /// Erase the arrows and marked squares drawn on the position being viewed
+    ClearAnnotations,
+
+    /// Start or stop analyzing the position being viewed with an engine
+    ToggleAnalysis,
+
+    /// Judge every move of the game with an engine
+    AnalyzeGame,
+
+    /// Allow or forbid playing moves from earlier positions
+    ToggleEditingPast,
+
+    /// Open the clock settings
+    EditClockSettings,
+
+    /// Open the preferences window
+    OpenPreferences,
+
+    /// View the starting position
+    GoToStart,
+
+    /// View the previous position
+    StepBack,
+
+    /// View the next position
+    StepForward,
+
+    /// View the last position of the current line
+    GoToEnd,
+}
+
+impl Command {
+    /// Every command, in the order they are listed in the command palette
+    pub const ALL: [Self; 21] = [
+        Self::NewGame,
+        Self::SetUpPosition,
+        Self::SaveGame,
+        Self::SaveGameAs,
+        Self::LoadGame,
+        Self::OpenDatabase,
+        Self::FlipBoard,
+        Self::HideCoordinates,
+        Self::CoordinatesInside,
+        Self::CoordinatesOutside,
+        Self::ToggleHoveredSquare,
+        Self::ClearAnnotations,
+        Self::ToggleAnalysis,
+        Self::AnalyzeGame,
+        Self::ToggleEditingPast,
+        Self::EditClockSettings,
+        Self::OpenPreferences,
+        Self::GoToStart,
+        Self::StepBack,
+        Self::StepForward,
+        Self::GoToEnd,
+    ];
+
+    /// Returns the name of the command, as shown in the command palette
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::NewGame => "New game",
+            Self::SetUpPosition => "Set up position",
+            Self::SaveGame => "Save game",
+            Self::SaveGameAs => "Save game as",
+            Self::LoadGame => "Load game",
+            Self::OpenDatabase => "Open game database",
+            Self::FlipBoard => "Flip board",
+            Self::HideCoordinates => "Hide coordinates",
+            Self::CoordinatesInside => "Show coordinates inside the board",
+            Self::CoordinatesOutside => "Show coordinates outside the board",
+            Self::ToggleHoveredSquare => "Toggle showing the square under the mouse",
+            Self::ClearAnnotations => "Clear arrows and marked squares",
+            Self::ToggleAnalysis => "Toggle analysis",
+            Self::AnalyzeGame => "Analyze game",
+            Self::ToggleEditingPast => "Toggle allowing editing the past",
+            Self::EditClockSettings => "Edit clock settings",
+            Self::OpenPreferences => "Preferences",
+            Self::GoToStart => "Go to start",
+            Self::StepBack => "Previous move",
+            Self::StepForward => "Next move",
+            Self::GoToEnd => "Go to end",
+        }
+    }
+
+    /// Returns the keyboard shortcut that runs this command, if any
+    pub const fn shortcut(&self) -> Option<KeyboardShortcut> {
+        match self {
+            Self::NewGame => Some(KeyboardShortcut::new(Modifiers::COMMAND, Key::N)),
+            Self::SaveGame => Some(KeyboardShortcut::new(Modifiers::COMMAND, Key::S)),
+            Self::SaveGameAs => Some(KeyboardShortcut::new(
+                Modifiers::COMMAND.plus(Modifiers::SHIFT),
+                Key::S,
+            )),
+            Self::LoadGame => Some(KeyboardShortcut::new(Modifiers::COMMAND, Key::O)),
+            Self::OpenPreferences => Some(KeyboardShortcut::new(Modifiers::COMMAND, Key::Comma)),
+            Self::FlipBoard => Some(KeyboardShortcut::new(Modifiers::COMMAND, Key::F)),
+            Self::GoToStart => Some(KeyboardShortcut::new(Modifiers::NONE, Key::Home)),
+            Self::StepBack => Some(KeyboardShortcut::new(Modifiers::NONE, Key::ArrowLeft)),
+            Self::StepForward => Some(KeyboardShortcut::new(Modifiers::NONE, Key::ArrowRight)),
+            Self::GoToEnd => Some(KeyboardShortcut::new(Modifiers::NONE, Key::End)),
+            _ => None,
+        }
+    }
+}
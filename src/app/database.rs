@@ -0,0 +1,343 @@
+use std::{fs, path::PathBuf};
+
+use eframe::egui::{Button, ComboBox, Context, Grid, Key, ScrollArea, TextEdit, Ui, Window};
+
+use crate::{
+    board::Board,
+    database::{Database, Query},
+    game::Game,
+};
+
+/// The results a search can be narrowed to, with their labels
+const RESULTS: [(&str, &str); 5] = [
+    ("", "Any"),
+    ("1-0", "White won"),
+    ("0-1", "Black won"),
+    ("1/2-1/2", "Drawn"),
+    ("*", "Unfinished"),
+];
+
+/// The window for importing games into the game database, searching it, and opening its games
+pub struct DatabaseBrowser {
+    open: bool,
+    database: Option<Database>,
+
+    /// The file the database was last opened from, so it is only opened again when it changes
+    opened: Option<PathBuf>,
+    query: Query,
+    results: Vec<usize>,
+    status: String,
+    error: Option<String>,
+}
+
+impl DatabaseBrowser {
+    /// Constructs a new, closed database browser
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            database: None,
+            opened: None,
+            query: Query::default(),
+            results: Vec::new(),
+            status: String::new(),
+            error: None,
+        }
+    }
+
+    /// Opens the window
+    pub fn open(&mut self) {
+        self.open = true;
+    }
+
+    /// Returns the open database, if any
+    pub fn database(&self) -> Option<&Database> {
+        self.database.as_ref()
+    }
+
+    /// Returns the indices of the games the last search found
+    pub fn results(&self) -> &[usize] {
+        &self.results
+    }
+
+    /// Opens the database in the given file, unless it is the one already open
+    pub fn load(&mut self, path: &Option<PathBuf>) {
+        if *path != self.opened {
+            self.opened = path.clone();
+            self.reload();
+        }
+    }
+
+    /// Shows the window if it is open, returning the game the user opened, if any
+    ///
+    /// The database is kept in the given path, which is changed when the user creates or opens
+    /// another, and searches for a position may start from the given one.
+    pub fn show(
+        &mut self,
+        ctx: &Context,
+        path: &mut Option<PathBuf>,
+        board: &Board,
+    ) -> Option<Game> {
+        if !self.open {
+            return None;
+        }
+        self.load(path);
+
+        let mut open = self.open;
+        let mut opened = None;
+        Window::new("Game database")
+            .open(&mut open)
+            .default_size([640.0, 480.0])
+            .show(ctx, |ui| {
+                self.file_ui(ui, path);
+                ui.separator();
+                if self.database.is_some() {
+                    self.query_ui(ui, board);
+                    ui.separator();
+                    opened = self.results_ui(ui);
+                }
+            });
+        self.open = open;
+        opened
+    }
+
+    /// Opens the database in the chosen file, if any
+    fn reload(&mut self) {
+        self.database = None;
+        self.results.clear();
+        self.status.clear();
+        self.error = None;
+        let Some(path) = &self.opened else {
+            return;
+        };
+        match Database::open(path) {
+            Ok(database) => {
+                self.database = Some(database);
+                self.search();
+            }
+            Err(error) => {
+                self.error = Some(format!("Unable to open {}: {error}", path.display()));
+            }
+        }
+    }
+
+    /// Shows the database's file, with buttons for creating and opening another and importing
+    /// games into it
+    fn file_ui(&mut self, ui: &mut Ui, path: &mut Option<PathBuf>) {
+        ui.horizontal(|ui| {
+            match &self.database {
+                Some(database) => ui.label(database.path().display().to_string()),
+                None => ui.weak("No database"),
+            };
+
+            if ui.button("New").clicked() {
+                let picked = rfd::FileDialog::new()
+                    .add_filter("Game database", &["chessdb"])
+                    .set_file_name("games.chessdb")
+                    .save_file();
+                if let Some(picked) = picked {
+                    match Database::create(&picked) {
+                        Ok(_) => {
+                            *path = Some(picked);
+                            // a database replaced in the same file is read again
+                            self.opened = None;
+                        }
+                        Err(error) => {
+                            self.error =
+                                Some(format!("Unable to create {}: {error}", picked.display()));
+                        }
+                    }
+                }
+            }
+            if ui.button("Open").clicked() {
+                let picked = rfd::FileDialog::new()
+                    .add_filter("Game database", &["chessdb"])
+                    .pick_file();
+                if picked.is_some() {
+                    *path = picked;
+                }
+            }
+            if ui
+                .add_enabled(self.database.is_some(), Button::new("Import PGN"))
+                .clicked()
+            {
+                self.import();
+            }
+        });
+
+        if let Some(error) = &self.error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+    }
+
+    /// Asks for PGN files and adds their games to the database
+    fn import(&mut self) {
+        let Some(database) = &mut self.database else {
+            return;
+        };
+        let Some(picked) = rfd::FileDialog::new()
+            .add_filter("PGN games", &["pgn"])
+            .pick_files()
+        else {
+            return;
+        };
+
+        self.error = None;
+        let (mut added, mut skipped) = (0, 0);
+        for path in picked {
+            let imported = fs::read(&path)
+                .map_err(|error| error.to_string())
+                // older collections are often not UTF-8
+                .and_then(|bytes| {
+                    database
+                        .import(&String::from_utf8_lossy(&bytes))
+                        .map_err(String::from)
+                });
+            match imported {
+                Ok((file_added, file_skipped)) => {
+                    added += file_added;
+                    skipped += file_skipped;
+                }
+                Err(error) => {
+                    self.error = Some(format!("Unable to import {}: {error}", path.display()));
+                    break;
+                }
+            }
+        }
+        self.search();
+        self.status = match skipped {
+            0 => format!("Imported {added} games"),
+            _ => format!("Imported {added} games, skipping {skipped} that could not be read"),
+        };
+    }
+
+    /// Shows the fields of the search, running it when asked
+    fn query_ui(&mut self, ui: &mut Ui, board: &Board) {
+        let mut submitted = false;
+        let mut field = |ui: &mut Ui, text: &mut String, hint: &str| {
+            let response = ui.add(TextEdit::singleline(text).hint_text(hint));
+            submitted |= response.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter));
+        };
+
+        Grid::new("DatabaseQuery").num_columns(2).show(ui, |ui| {
+            ui.label("Player");
+            field(ui, &mut self.query.player, "Either side");
+            ui.end_row();
+
+            ui.label("Event");
+            field(ui, &mut self.query.event, "Any");
+            ui.end_row();
+
+            ui.label("Dates");
+            ui.horizontal(|ui| {
+                field(ui, &mut self.query.from, "From, i.e. 2019");
+                field(ui, &mut self.query.to, "To, i.e. 2020.06");
+            });
+            ui.end_row();
+
+            ui.label("Result");
+            let selected = RESULTS
+                .iter()
+                .find(|(result, _)| *result == self.query.result)
+                .map_or("Any", |(_, label)| label);
+            ComboBox::from_id_salt("DatabaseResult")
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    for (result, label) in RESULTS {
+                        ui.selectable_value(&mut self.query.result, result.to_string(), label);
+                    }
+                });
+            ui.end_row();
+
+            ui.label("ECO");
+            field(ui, &mut self.query.eco, "i.e. B or C65");
+            ui.end_row();
+
+            ui.label("Position");
+            ui.horizontal(|ui| {
+                field(ui, &mut self.query.fen, "FEN");
+                if ui.button("Current").clicked() {
+                    self.query.fen = board.fen();
+                }
+            });
+            ui.end_row();
+
+            ui.label("Material");
+            field(ui, &mut self.query.material, "i.e. Q v RR");
+            ui.end_row();
+        });
+
+        ui.horizontal(|ui| {
+            submitted |= ui.button("Search").clicked();
+            if ui.button("Clear").clicked() {
+                self.query = Query::default();
+                submitted = true;
+            }
+        });
+        if submitted {
+            self.search();
+        }
+    }
+
+    /// Finds the games matching the query
+    fn search(&mut self) {
+        let Some(database) = &self.database else {
+            return;
+        };
+        match database.search(&self.query) {
+            Ok(results) => {
+                self.status = format!("{} of {} games", results.len(), database.len());
+                self.results = results;
+            }
+            Err(error) => self.status = format!("Unable to search: {error}"),
+        }
+    }
+
+    /// Shows the games found, returning the one the user opened, if any
+    fn results_ui(&mut self, ui: &mut Ui) -> Option<Game> {
+        let database = self.database.as_ref()?;
+        ui.label(&self.status);
+
+        let mut clicked = None;
+        let row_height = ui.spacing().interact_size.y;
+        ScrollArea::both().auto_shrink(false).show_rows(
+            ui,
+            row_height,
+            self.results.len(),
+            |ui, rows| {
+                Grid::new("DatabaseGames")
+                    .num_columns(5)
+                    .striped(true)
+                    .min_row_height(row_height)
+                    .show(ui, |ui| {
+                        for &id in &self.results[rows] {
+                            let headers = database.headers(id);
+                            let tag = |name| headers.get(name).unwrap_or_default();
+                            let players = format!("{} – {}", tag("White"), tag("Black"));
+                            if ui.add(Button::new(players).frame(false)).clicked() {
+                                clicked = Some(id);
+                            }
+                            ui.label(tag("Result"));
+                            ui.label(tag("Date"));
+                            ui.label(tag("Event"));
+                            ui.label(tag("ECO"));
+                            ui.end_row();
+                        }
+                    });
+            },
+        );
+
+        match database.game(clicked?) {
+            Ok(game) => Some(game),
+            Err(error) => {
+                self.error = Some(format!("Unable to open the game: {error}"));
+                None
+            }
+        }
+    }
+}
+
+impl Default for DatabaseBrowser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,269 @@
+use eframe::{
+    egui::{
+        Button, ComboBox, Context, Image, ImageButton, PointerButton, Sense, TextEdit, Ui, Vec2,
+        Window,
+    },
+    epaint::{Rect, Rounding},
+};
+
+use crate::{
+    board::{Board, Square},
+    piece::{Piece, PieceColor, PieceKind},
+};
+
+use super::theme::BoardTheme;
+
+/// The size of the board drawn in the editor, in points
+const BOARD_SIZE: f32 = 320.0;
+
+/// The size of the piece buttons in the palette, in points
+const PALETTE_PIECE_SIZE: f32 = 32.0;
+
+/// The pieces of each color, in the order they are shown in the palette
+const PALETTE_KINDS: [PieceKind; 6] = [
+    PieceKind::King,
+    PieceKind::Queen,
+    PieceKind::Rook,
+    PieceKind::Bishop,
+    PieceKind::Knight,
+    PieceKind::Pawn,
+];
+
+/// The window for setting up a position by hand to start a game from
+///
+/// Left-clicking or dragging over squares places the piece chosen in the palette, and
+/// right-clicking or dragging removes pieces.
+pub struct BoardEditor {
+    open: bool,
+    board: Board,
+    piece: Option<Piece>,
+    fen: String,
+    error: Option<&'static str>,
+}
+
+impl BoardEditor {
+    /// Constructs a new, closed board editor
+    pub fn new() -> Self {
+        let board = Board::new_init();
+        Self {
+            open: false,
+            fen: board.fen(),
+            board,
+            piece: Some(Piece::new(PieceKind::Pawn, PieceColor::White)),
+            error: None,
+        }
+    }
+
+    /// Opens the editor, starting from the given position
+    pub fn open(&mut self, board: &Board) {
+        self.open = true;
+        self.set_board(board.clone());
+    }
+
+    /// Shows the editor if it is open, returning the position if the user started a game from it
+    pub fn show(&mut self, ctx: &Context, theme: &BoardTheme, flipped: bool) -> Option<Board> {
+        let mut open = self.open;
+        let mut started = None;
+
+        Window::new("Set up position")
+            .open(&mut open)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    self.board_ui(ui, theme, flipped);
+                    ui.vertical(|ui| {
+                        self.palette_ui(ui, theme);
+                        ui.separator();
+                        self.rules_ui(ui);
+                    });
+                });
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label("FEN");
+                    let edit =
+                        ui.add(TextEdit::singleline(&mut self.fen).desired_width(f32::INFINITY));
+                    if edit.changed() {
+                        match Board::from_fen(&self.fen) {
+                            Ok(board) => {
+                                self.board = board;
+                                self.error = None;
+                            }
+                            Err(error) => self.error = Some(error),
+                        }
+                    }
+                    if edit.lost_focus() {
+                        self.fen = self.board.fen();
+                    }
+                });
+
+                if let Some(error) = self.error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("Clear").clicked() {
+                        self.set_board(Board::new_empty());
+                    }
+                    if ui.button("Starting position").clicked() {
+                        self.set_board(Board::new_init());
+                    }
+                    if ui.button("Start game").clicked() {
+                        match self.board.validate() {
+                            Ok(()) => started = Some(self.board.clone()),
+                            Err(error) => self.error = Some(error),
+                        }
+                    }
+                });
+            });
+
+        self.open = open && started.is_none();
+        started
+    }
+
+    /// Replaces the position being edited
+    fn set_board(&mut self, board: Board) {
+        self.fen = board.fen();
+        self.board = board;
+        self.error = None;
+    }
+
+    /// Records that the position was changed from the editor's controls
+    fn edited(&mut self) {
+        self.fen = self.board.fen();
+        self.error = None;
+    }
+
+    /// Shows the board, placing and removing pieces where the user clicks and drags
+    fn board_ui(&mut self, ui: &mut Ui, theme: &BoardTheme, flipped: bool) {
+        let (rect, response) = ui.allocate_exact_size(Vec2::splat(BOARD_SIZE), Sense::drag());
+        let square_size = BOARD_SIZE / 8.0;
+        let square_rect = |square: Square| {
+            let (file, rank) = square.indices();
+            let (col, row) = match flipped {
+                false => (file, 7 - rank),
+                true => (7 - file, rank),
+            };
+            Rect::from_min_size(
+                rect.min + Vec2::new(col as f32, row as f32) * square_size,
+                Vec2::splat(square_size),
+            )
+        };
+
+        let pointer = ui.input(|i| i.pointer.clone());
+        let hovered = pointer
+            .interact_pos()
+            .filter(|_| response.contains_pointer())
+            .filter(|_| pointer.press_origin().is_some_and(|pos| rect.contains(pos)))
+            .and_then(|pos| Square::all().find(|&square| square_rect(square).contains(pos)));
+        if let Some(square) = hovered {
+            // pieces are painted while the button is held, so dragging fills every square passed
+            let placed = match (
+                pointer.button_down(PointerButton::Primary),
+                pointer.button_down(PointerButton::Secondary),
+            ) {
+                (true, _) => Some(self.piece),
+                (false, true) => Some(None),
+                (false, false) => None,
+            };
+            if let Some(piece) = placed.filter(|&piece| self.board.get_piece(square) != piece) {
+                match piece {
+                    Some(piece) => self.board.set_piece(square, piece),
+                    None => self.board.remove_piece(square),
+                }
+                self.edited();
+            }
+        }
+
+        let painter = ui.painter();
+        for square in Square::all() {
+            let rect = square_rect(square);
+            painter.rect_filled(rect, Rounding::ZERO, theme.square_color(square));
+        }
+        if let Some(texture) = theme.texture_image() {
+            Image::new(texture).paint_at(ui, rect);
+        }
+        for (square, piece) in self.board.pieces() {
+            Image::new(theme.piece_image(piece)).paint_at(ui, square_rect(square));
+        }
+    }
+
+    /// Shows the twelve pieces to place, and an eraser
+    fn palette_ui(&mut self, ui: &mut Ui, theme: &BoardTheme) {
+        for color in [PieceColor::White, PieceColor::Black] {
+            ui.horizontal(|ui| {
+                for kind in PALETTE_KINDS {
+                    let piece = Piece::new(kind, color);
+                    let image = Image::new(theme.piece_image(piece))
+                        .fit_to_exact_size(Vec2::splat(PALETTE_PIECE_SIZE));
+                    let button = ImageButton::new(image).selected(self.piece == Some(piece));
+                    if ui.add(button).clicked() {
+                        self.piece = Some(piece);
+                    }
+                }
+            });
+        }
+        let eraser = Button::new("Remove pieces").selected(self.piece.is_none());
+        if ui.add(eraser).clicked() {
+            self.piece = None;
+        }
+    }
+
+    /// Shows the side to move, castling rights and en passant square
+    fn rules_ui(&mut self, ui: &mut Ui) {
+        let mut to_move = self.board.to_move();
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut to_move, PieceColor::White, "White to move");
+            ui.radio_value(&mut to_move, PieceColor::Black, "Black to move");
+        });
+        if to_move != self.board.to_move() {
+            self.board.set_to_move(to_move);
+            self.board.set_en_passant(None);
+            self.edited();
+        }
+
+        let mut castling = self.board.castling_rights();
+        ui.label("Castling");
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut castling.white_kingside, "White O-O");
+            ui.checkbox(&mut castling.white_queenside, "White O-O-O");
+        });
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut castling.black_kingside, "Black O-O");
+            ui.checkbox(&mut castling.black_queenside, "Black O-O-O");
+        });
+        if castling != self.board.castling_rights() {
+            self.board.set_castling_rights(castling);
+            self.edited();
+        }
+
+        // en passant squares are behind the pawns of the side that just moved
+        let rank = match to_move {
+            PieceColor::White => 5,
+            PieceColor::Black => 2,
+        };
+        let mut en_passant = self.board.en_passant();
+        let name = en_passant.map_or(String::from("None"), |square| square.to_string());
+        ComboBox::from_label("En passant")
+            .selected_text(name)
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut en_passant, None, "None");
+                for file in 0..8 {
+                    let square = Square::from_indices(file, rank);
+                    ui.selectable_value(&mut en_passant, Some(square), square.to_string());
+                }
+            });
+        if en_passant != self.board.en_passant() {
+            self.board.set_en_passant(en_passant);
+            self.edited();
+        }
+    }
+}
+
+impl Default for BoardEditor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
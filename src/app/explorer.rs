@@ -0,0 +1,265 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver},
+        Arc,
+    },
+    thread,
+};
+
+use eframe::{
+    egui::{pos2, Align2, Button, FontId, Grid, ProgressBar, Sense, Ui, Vec2},
+    epaint::{Color32, Rect},
+};
+
+use crate::{
+    board::Board,
+    explorer::{indexing, Index, MoveStats},
+    r#move::Move,
+};
+
+/// The size of the bar showing how the games after a move ended, in points
+const RESULTS_BAR_SIZE: Vec2 = Vec2::new(140.0, 16.0);
+
+/// The colors of the white wins, draws, and black wins in the results bar, and of their text
+const RESULTS_COLORS: [(Color32, Color32); 3] = [
+    (Color32::from_gray(235), Color32::from_gray(40)),
+    (Color32::from_gray(140), Color32::from_gray(250)),
+    (Color32::from_gray(40), Color32::from_gray(235)),
+];
+
+/// Something the indexing thread reports
+enum IndexingEvent {
+    /// How much of the database has been read, from 0 to 1
+    Progress(f32),
+
+    /// Indexing finished, or failed
+    Done(Result<(), &'static str>),
+}
+
+/// Whether the database can be explored yet
+enum State {
+    /// No database is chosen
+    Closed,
+
+    /// The database is being indexed on a background thread
+    Indexing {
+        events: Receiver<IndexingEvent>,
+        stop: Arc<AtomicBool>,
+        progress: f32,
+    },
+
+    /// The database is indexed
+    Ready(Index),
+
+    /// The database could not be indexed
+    Failed(&'static str),
+}
+
+/// The moves played in the position being viewed in a database of games, with how the games
+/// went on
+///
+/// The database is indexed the first time it is chosen, and again whenever it changes, so later
+/// lookups are quick however many games it holds.
+pub struct Explorer {
+    database: Option<PathBuf>,
+    state: State,
+}
+
+impl Explorer {
+    /// Constructs a new explorer with no database
+    pub fn new() -> Self {
+        Self {
+            database: None,
+            state: State::Closed,
+        }
+    }
+
+    /// Returns true if the database is being indexed
+    pub fn is_indexing(&self) -> bool {
+        matches!(self.state, State::Indexing { .. })
+    }
+
+    /// Opens the given database if it changed since it was last opened, indexing it if needed,
+    /// and takes the progress of any indexing since the last update
+    pub fn update(&mut self, database: Option<&Path>) {
+        if database != self.database.as_deref() {
+            self.database = database.map(Path::to_path_buf);
+            self.open();
+        }
+
+        let State::Indexing {
+            events, progress, ..
+        } = &mut self.state
+        else {
+            return;
+        };
+        for event in events.try_iter() {
+            match event {
+                IndexingEvent::Progress(read) => *progress = read,
+                IndexingEvent::Done(Ok(())) => {
+                    self.open();
+                    return;
+                }
+                IndexingEvent::Done(Err(error)) => {
+                    self.state = State::Failed(error);
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Opens the index of the database, indexing it first if it has no index or it is out of date
+    fn open(&mut self) {
+        // indexing a database that is no longer chosen is stopped, as the cancel button does
+        if let State::Indexing { stop, .. } = &self.state {
+            stop.store(true, Ordering::Relaxed);
+        }
+        let Some(database) = self.database.clone() else {
+            self.state = State::Closed;
+            return;
+        };
+        if let Ok(index) = Index::open(&database) {
+            self.state = State::Ready(index);
+            return;
+        }
+
+        let (sender, events) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&stop);
+        thread::spawn(move || {
+            let progress = |read| {
+                // the receiver is gone if the database was changed while indexing
+                let _ = sender.send(IndexingEvent::Progress(read));
+            };
+            let result = indexing::build(&database, progress, &flag);
+            let _ = sender.send(IndexingEvent::Done(result));
+        });
+        self.state = State::Indexing {
+            events,
+            stop,
+            progress: 0.0,
+        };
+    }
+
+    /// Shows the moves played in the given position, reached after the given number of plies,
+    /// returning the move the user clicked, if any
+    pub fn show(&mut self, ui: &mut Ui, board: &Board, ply: usize, enabled: bool) -> Option<Move> {
+        let index = match &self.state {
+            State::Closed => {
+                ui.weak("Choose the explorer's games in the preferences");
+                return None;
+            }
+            State::Indexing { stop, progress, .. } => {
+                ui.horizontal(|ui| {
+                    let bar = ProgressBar::new(*progress)
+                        .text(format!("Indexing games... {:.0}%", progress * 100.0));
+                    ui.add(bar.desired_width(RESULTS_BAR_SIZE.x * 1.5));
+                    if ui.button("Cancel").clicked() {
+                        stop.store(true, Ordering::Relaxed);
+                    }
+                });
+                return None;
+            }
+            State::Failed(error) => {
+                let error = *error;
+                ui.horizontal(|ui| {
+                    ui.colored_label(
+                        ui.visuals().error_fg_color,
+                        format!("Unable to index: {error}"),
+                    );
+                    if ui.button("Retry").clicked() {
+                        self.open();
+                    }
+                });
+                return None;
+            }
+            State::Ready(index) => index,
+        };
+
+        // only the openings of the games are indexed, so later positions are mostly missing
+        let beyond = ply >= indexing::MAX_PLY;
+        let moves = index.moves(board);
+        if moves.is_empty() {
+            match beyond {
+                true => ui.weak(format!(
+                    "This position is beyond the indexed depth ({} plies)",
+                    indexing::MAX_PLY
+                )),
+                false => ui.weak("No games reached this position"),
+            };
+            return None;
+        }
+
+        let mut clicked = None;
+        Grid::new("ExplorerMoves")
+            .num_columns(4)
+            .striped(true)
+            .show(ui, |ui| {
+                for stats in moves {
+                    let san = board.san(stats.r#move);
+                    if ui
+                        .add_enabled(enabled, Button::new(san).frame(false))
+                        .clicked()
+                    {
+                        clicked = Some(stats.r#move);
+                    }
+                    ui.label(stats.games().to_string());
+                    results_bar(ui, &stats);
+                    match stats.average_rating {
+                        Some(rating) => ui.weak(rating.to_string()),
+                        None => ui.weak("–"),
+                    };
+                    ui.end_row();
+                }
+            });
+        if beyond {
+            ui.weak(format!(
+                "Only games reaching this position within the indexed depth ({} plies) are counted",
+                indexing::MAX_PLY
+            ));
+        }
+        clicked
+    }
+}
+
+impl Default for Explorer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Shows a bar split in proportion to the games after a move that white won, drew, and black
+/// won, with their percentages written in the parts wide enough to hold them
+fn results_bar(ui: &mut Ui, stats: &MoveStats) {
+    let (rect, response) = ui.allocate_exact_size(RESULTS_BAR_SIZE, Sense::hover());
+    let games = stats.games().max(1) as f32;
+    let shares = [stats.white, stats.draws, stats.black].map(|count| count as f32 / games);
+
+    let painter = ui.painter();
+    let mut left = rect.left();
+    for (share, (fill, text)) in shares.into_iter().zip(RESULTS_COLORS) {
+        let width = rect.width() * share;
+        let part = Rect::from_min_max(pos2(left, rect.top()), pos2(left + width, rect.bottom()));
+        painter.rect_filled(part, 0.0, fill);
+        if width >= 28.0 {
+            let percent = format!("{:.0}%", share * 100.0);
+            painter.text(
+                part.center(),
+                Align2::CENTER_CENTER,
+                percent,
+                FontId::proportional(10.0),
+                text,
+            );
+        }
+        left += width;
+    }
+
+    response.on_hover_text(format!(
+        "White wins {:.1}%, draws {:.1}%, black wins {:.1}%",
+        shares[0] * 100.0,
+        shares[1] * 100.0,
+        shares[2] * 100.0,
+    ));
+}
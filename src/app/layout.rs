@@ -0,0 +1,165 @@
+use eframe::egui::{self, Context, ScrollArea, Ui};
+
+/// Window widths below this many points stack the side panels underneath the board
+const NARROW_WIDTH: f32 = 480.0;
+
+/// A panel that can be docked beside the board
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PanelKind {
+    /// The players, with the pieces they have captured
+    Players,
+
+    /// The list of moves played
+    MoveList,
+
+    /// The box for typing moves
+    MoveEntry,
+
+    /// The game clock
+    Clock,
+
+    /// Engine analysis
+    Analysis,
+
+    /// Moves from the opening book
+    Book,
+
+    /// Moves played in the games database
+    Explorer,
+}
+
+impl PanelKind {
+    /// Every panel kind, in display order
+    pub const ALL: [Self; 7] = [
+        Self::Players,
+        Self::Clock,
+        Self::MoveList,
+        Self::MoveEntry,
+        Self::Analysis,
+        Self::Book,
+        Self::Explorer,
+    ];
+
+    /// Returns the title shown above the panel
+    pub const fn title(&self) -> &'static str {
+        match self {
+            Self::Players => "Players",
+            Self::MoveList => "Moves",
+            Self::MoveEntry => "Enter move",
+            Self::Clock => "Clock",
+            Self::Analysis => "Analysis",
+            Self::Book => "Book",
+            Self::Explorer => "Explorer",
+        }
+    }
+}
+
+/// Where a panel is docked
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dock {
+    /// Docked to the left of the board
+    Left,
+
+    /// Docked to the right of the board
+    Right,
+
+    /// Not shown
+    Hidden,
+}
+
+/// The arrangement of side panels around the board
+pub struct Layout {
+    docks: [(PanelKind, Dock); PanelKind::ALL.len()],
+}
+
+impl Layout {
+    /// Constructs the default layout, with every panel docked to the right
+    pub fn new() -> Self {
+        Self {
+            docks: PanelKind::ALL.map(|kind| (kind, Dock::Right)),
+        }
+    }
+
+    /// Returns a mutable reference to the dock of the given panel
+    pub fn dock_mut(&mut self, kind: PanelKind) -> &mut Dock {
+        self.docks
+            .iter_mut()
+            .find_map(|(k, dock)| (*k == kind).then_some(dock))
+            .expect("every panel kind has a dock")
+    }
+
+    /// Draws a menu for choosing where each panel is docked
+    pub fn menu_ui(&mut self, ui: &mut Ui) {
+        for kind in PanelKind::ALL {
+            ui.menu_button(kind.title(), |ui| {
+                let dock = self.dock_mut(kind);
+                ui.radio_value(dock, Dock::Left, "Left");
+                ui.radio_value(dock, Dock::Right, "Right");
+                ui.radio_value(dock, Dock::Hidden, "Hidden");
+            });
+        }
+    }
+
+    /// Shows every visible panel, calling `add_contents` to fill each one
+    ///
+    /// This must be called before the central panel is shown. On narrow windows the panels are
+    /// stacked in a resizable panel below the board instead of beside it.
+    pub fn show(&self, ctx: &Context, mut add_contents: impl FnMut(&mut Ui, PanelKind)) {
+        let panels = |dock: Dock| {
+            self.docks
+                .iter()
+                .filter(move |(_, d)| *d == dock)
+                .map(|(kind, _)| *kind)
+        };
+
+        if ctx.screen_rect().width() < NARROW_WIDTH {
+            let visible: Vec<_> = panels(Dock::Left).chain(panels(Dock::Right)).collect();
+            if !visible.is_empty() {
+                egui::TopBottomPanel::bottom("StackedPanels")
+                    .resizable(true)
+                    .show(ctx, |ui| {
+                        ScrollArea::vertical().show(ui, |ui| {
+                            show_panels(ui, visible, &mut add_contents);
+                        });
+                    });
+            }
+            return;
+        }
+
+        for (id, dock) in [("LeftPanels", Dock::Left), ("RightPanels", Dock::Right)] {
+            let visible: Vec<_> = panels(dock).collect();
+            if visible.is_empty() {
+                continue;
+            }
+
+            let panel = match dock {
+                Dock::Left => egui::SidePanel::left(id),
+                _ => egui::SidePanel::right(id),
+            };
+            panel
+                .resizable(true)
+                .default_width(160.0)
+                .width_range(100.0..=ctx.screen_rect().width() * 0.4)
+                .show(ctx, |ui| show_panels(ui, visible, &mut add_contents));
+        }
+    }
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Shows the given panels one above another, each under a collapsible header
+fn show_panels(
+    ui: &mut Ui,
+    panels: Vec<PanelKind>,
+    add_contents: &mut impl FnMut(&mut Ui, PanelKind),
+) {
+    for kind in panels {
+        egui::CollapsingHeader::new(kind.title())
+            .default_open(true)
+            .show(ui, |ui| add_contents(ui, kind));
+    }
+}
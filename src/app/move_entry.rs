@@ -0,0 +1,81 @@
+use eframe::egui::{Key, TextEdit, Ui};
+
+use crate::{board::Board, r#move::Move};
+
+/// A text box for entering moves from the keyboard, in standard algebraic or UCI notation
+#[derive(Debug, Default)]
+pub struct MoveEntry {
+    text: String,
+    error: Option<&'static str>,
+}
+
+impl MoveEntry {
+    /// Constructs a new, empty move entry box
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Clears the entered text and any error
+    pub fn clear(&mut self) {
+        self.text.clear();
+        self.error = None;
+    }
+
+    /// Shows the entry box and the legal moves matching what has been typed so far, returning
+    /// the move the user entered, if any
+    pub fn show(&mut self, ui: &mut Ui, board: &Board) -> Option<Move> {
+        let edit = ui.add(
+            TextEdit::singleline(&mut self.text)
+                .hint_text("Nf3, e4, O-O or e2e4")
+                .desired_width(f32::INFINITY),
+        );
+        if edit.changed() {
+            self.error = None;
+        }
+
+        let matches = board.moves_matching(&self.text);
+        let mut chosen = None;
+        if edit.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter)) {
+            // keep typing moves without clicking back into the box
+            edit.request_focus();
+            if !self.text.trim().is_empty() {
+                match self.parse(board, &matches) {
+                    Ok(r#move) => chosen = Some(r#move),
+                    Err(error) => self.error = Some(error),
+                }
+            }
+        }
+
+        if let Some(error) = self.error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+        if !matches.is_empty() && chosen.is_none() {
+            ui.horizontal_wrapped(|ui| {
+                for &r#move in &matches {
+                    if ui.small_button(board.san(r#move)).clicked() {
+                        chosen = Some(r#move);
+                    }
+                }
+            });
+        }
+
+        if chosen.is_some() {
+            self.clear();
+        }
+        chosen
+    }
+
+    /// Parses the entered text as a complete move, falling back to the only matching move if
+    /// the text is a prefix of exactly one
+    fn parse(&self, board: &Board, matches: &[Move]) -> Result<Move, &'static str> {
+        let parsed = board
+            .parse_uci(&self.text)
+            .or_else(|_| board.parse_san(&self.text));
+        match (parsed, matches) {
+            (Ok(r#move), _) => Ok(r#move),
+            (Err(_), &[r#move]) => Ok(r#move),
+            (Err(_), [_, _, ..]) => Err("ambiguous move, choose one of the matches below"),
+            (Err(error), []) => Err(error),
+        }
+    }
+}
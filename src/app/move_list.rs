@@ -0,0 +1,220 @@
+use eframe::egui::{Button, Label, Response, RichText, ScrollArea, SelectableLabel, Ui, Vec2};
+
+use crate::{
+    game::{Game, NodeId},
+    piece::PieceColor,
+};
+
+/// The width of the move number column of the main line
+const NUMBER_WIDTH: f32 = 32.0;
+
+/// The width of each move column of the main line
+const MOVE_WIDTH: f32 = 56.0;
+
+/// Something the user asked for in the move list
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MoveListAction {
+    /// View the position at the given node
+    GoTo(NodeId),
+
+    /// Make the line through the given node the main line
+    Promote(NodeId),
+
+    /// Delete the given node and every move after it
+    Remove(NodeId),
+}
+
+/// A list of the moves played in a game, as numbered pairs in standard algebraic notation, with
+/// variations shown inline
+pub struct MoveList<'a> {
+    game: &'a Game,
+}
+
+impl<'a> MoveList<'a> {
+    /// Constructs a new `MoveList` of the given game
+    pub fn new(game: &'a Game) -> Self {
+        Self { game }
+    }
+
+    /// Shows the move list, returning what the user asked for, if anything
+    pub fn show(self, ui: &mut Ui) -> Option<MoveListAction> {
+        let game = self.game;
+        if game.is_empty() {
+            ui.weak("No moves played");
+            return None;
+        }
+
+        // the node the list was last scrolled to, so that only changes scroll the list
+        let id = ui.id().with("MoveListScrolledTo");
+        let scrolled_to = ui.data(|data| data.get_temp::<NodeId>(id));
+        let scroll = scrolled_to != Some(game.current());
+        ui.data_mut(|data| data.insert_temp(id, game.current()));
+
+        let mut list = ListState {
+            game,
+            scroll,
+            action: None,
+        };
+        ScrollArea::vertical()
+            .auto_shrink([false, true])
+            .max_height(ui.available_height().max(120.0))
+            .show(ui, |ui| {
+                list.main_line_ui(ui);
+
+                if let Some(outcome) = game.outcome() {
+                    ui.separator();
+                    ui.strong(outcome.result());
+                    ui.label(outcome.to_string());
+                }
+            });
+
+        list.action
+    }
+}
+
+/// The state of the move list while it is being drawn
+struct ListState<'a> {
+    game: &'a Game,
+    scroll: bool,
+    action: Option<MoveListAction>,
+}
+
+impl ListState<'_> {
+    /// Shows the main line as rows of numbered move pairs, with variations below the move they
+    /// branch from
+    fn main_line_ui(&mut self, ui: &mut Ui) {
+        let game = self.game;
+        let line = game.main_line();
+        let mut index = 0;
+        while index < line.len() {
+            let node = line[index];
+            let (number, color) = game.move_number(game.node(node).ply - 1);
+
+            // a white move and black's reply share a row unless the white move has variations
+            let mut row = vec![node];
+            if color == PieceColor::White && !self.has_variations(node) {
+                row.extend(line.get(index + 1));
+            }
+
+            ui.horizontal(|ui| {
+                let number = RichText::new(format!("{number}.")).weak();
+                ui.add_sized(Vec2::new(NUMBER_WIDTH, 0.0), Label::new(number));
+                if color == PieceColor::Black {
+                    ui.add_sized(Vec2::new(MOVE_WIDTH, 0.0), Label::new("..."));
+                }
+                for &node in &row {
+                    self.move_ui(ui, node, Some(MOVE_WIDTH));
+                }
+            });
+
+            for &node in &row {
+                self.variations_ui(ui, node);
+            }
+            index += row.len();
+        }
+    }
+
+    /// Returns true if the position before the given node has other continuations
+    fn has_variations(&self, id: NodeId) -> bool {
+        self.game
+            .node(id)
+            .parent
+            .is_some_and(|parent| self.game.node(parent).children.len() > 1)
+    }
+
+    /// Shows the alternatives to the given main line node, indented below it
+    fn variations_ui(&mut self, ui: &mut Ui, id: NodeId) {
+        let Some(parent) = self.game.node(id).parent else {
+            return;
+        };
+        for &variation in &self.game.node(parent).children[1..] {
+            ui.indent(variation, |ui| {
+                ui.horizontal_wrapped(|ui| {
+                    ui.spacing_mut().item_spacing.x = 2.0;
+                    self.line_ui(ui, variation);
+                });
+            });
+        }
+    }
+
+    /// Shows the line starting at the given node inline, with nested variations in parentheses
+    fn line_ui(&mut self, ui: &mut Ui, start: NodeId) {
+        let game = self.game;
+        let mut node = start;
+        let mut numbered = false;
+        loop {
+            let (number, color) = game.move_number(game.node(node).ply - 1);
+            if color == PieceColor::White {
+                ui.weak(format!("{number}."));
+            } else if !numbered {
+                ui.weak(format!("{number}..."));
+            }
+            numbered = true;
+
+            self.move_ui(ui, node, None);
+
+            // the alternatives to the first move are shown by whoever showed this line
+            if node != start {
+                let parent = game.node(node).parent.expect("moves have a parent");
+                for &variation in &game.node(parent).children[1..] {
+                    ui.weak("(");
+                    self.line_ui(ui, variation);
+                    ui.weak(")");
+                    numbered = false;
+                }
+            }
+
+            match game.node(node).children.first() {
+                Some(&child) => node = child,
+                None => break,
+            }
+        }
+    }
+
+    /// Shows a single move, recording a click on it and offering a menu to manage its line
+    fn move_ui(&mut self, ui: &mut Ui, id: NodeId, width: Option<f32>) {
+        let game = self.game;
+        let node = game.node(id);
+        let san = node
+            .played
+            .as_ref()
+            .map(|played| played.san.as_str())
+            .unwrap_or_default();
+        let glyph = node.annotations.assessment().unwrap_or_default();
+        let selected = game.current() == id;
+
+        let label = SelectableLabel::new(selected, format!("{san}{glyph}"));
+        let response = match width {
+            Some(width) => ui.add_sized(Vec2::new(width, 0.0), label),
+            None => ui.add(label),
+        };
+
+        let response = match node.annotations.eval {
+            Some(eval) => response.on_hover_text(eval.to_string()),
+            None => response,
+        };
+        if response.clicked() {
+            self.action = Some(MoveListAction::GoTo(id));
+        }
+        if self.scroll && selected {
+            response.scroll_to_me(None);
+        }
+        self.context_menu(&response, id);
+    }
+
+    /// Adds a context menu for managing the line through the given node
+    fn context_menu(&mut self, response: &Response, id: NodeId) {
+        let main_line = self.game.is_main_line(id);
+        response.context_menu(|ui| {
+            let promote = ui.add_enabled(!main_line, Button::new("Promote to main line"));
+            if promote.clicked() {
+                self.action = Some(MoveListAction::Promote(id));
+                ui.close_menu();
+            }
+            if ui.button("Delete from here").clicked() {
+                self.action = Some(MoveListAction::Remove(id));
+                ui.close_menu();
+            }
+        });
+    }
+}
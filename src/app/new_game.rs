@@ -0,0 +1,323 @@
+use std::path::{Path, PathBuf};
+
+use eframe::egui::{Checkbox, ComboBox, Context, DragValue, Grid, Slider, Ui, Window};
+
+use crate::{
+    board::Board,
+    clock::TimeControl,
+    engine::{strength::level_elo, MAX_LEVEL},
+    piece::PieceColor,
+};
+
+/// The number of the standard starting position among the Chess960 positions
+const STANDARD_CHESS960_NUMBER: u16 = 518;
+
+/// Where a new game starts from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StartingPosition {
+    /// The standard starting position
+    Standard,
+
+    /// One of the 960 starting positions of Chess960
+    Chess960,
+
+    /// A position given in Forsyth-Edwards Notation
+    Fen,
+}
+
+/// Who plays one side of a game
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Player {
+    /// A person, moving on the board
+    Human,
+
+    /// The built-in engine
+    BuiltIn {
+        /// How strongly the engine plays, from 1 to [`MAX_LEVEL`]
+        level: u8,
+
+        /// Whether the engine makes its mistakes by missing tactics, as people do
+        human_like: bool,
+    },
+
+    /// An external engine, talking over the Universal Chess Interface
+    External(PathBuf),
+}
+
+impl Player {
+    /// Returns the name of the engine, or `None` for a person
+    pub fn engine_name(&self) -> Option<String> {
+        match self {
+            Self::Human => None,
+            Self::BuiltIn { level, .. } => Some(format!("Built-in engine (level {level})")),
+            Self::External(path) => Some(engine_name(path)),
+        }
+    }
+}
+
+/// A new game, as chosen in the new game dialog
+#[derive(Clone)]
+pub struct NewGame {
+    /// The starting position
+    pub board: Board,
+
+    /// Whether the game is played under Chess960 rules
+    pub chess960: bool,
+
+    /// The name of the white player
+    pub white: String,
+
+    /// The name of the black player
+    pub black: String,
+
+    /// Who plays white
+    pub white_player: Player,
+
+    /// Who plays black
+    pub black_player: Player,
+
+    /// The time control, or `None` for an untimed game
+    pub time_control: Option<TimeControl>,
+
+    /// Whether engines play from the opening book while they can
+    pub book: bool,
+}
+
+/// The dialog for choosing the starting position, players, and time control of a new game
+#[derive(Debug)]
+pub struct NewGameDialog {
+    open: bool,
+    start: StartingPosition,
+    chess960: u16,
+    fen: String,
+    white: String,
+    black: String,
+    white_player: Player,
+    black_player: Player,
+    timed: bool,
+    time_control: String,
+    book: bool,
+    error: Option<&'static str>,
+}
+
+impl NewGameDialog {
+    /// Constructs a new, closed new game dialog
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            start: StartingPosition::Standard,
+            chess960: STANDARD_CHESS960_NUMBER,
+            fen: String::from(Board::STARTING_FEN),
+            white: String::from("White"),
+            black: String::from("Black"),
+            white_player: Player::Human,
+            black_player: Player::Human,
+            timed: false,
+            time_control: String::from("5+3"),
+            book: true,
+            error: None,
+        }
+    }
+
+    /// Opens the dialog, starting from the given time control
+    pub fn open(&mut self, time_control: Option<&TimeControl>) {
+        self.open = true;
+        self.timed = time_control.is_some();
+        if let Some(time_control) = time_control {
+            self.time_control = time_control.to_string();
+        }
+        self.chess960 = fastrand::u16(0..960);
+        self.error = None;
+    }
+
+    /// Shows the dialog if it is open, returning the new game if the user started one
+    ///
+    /// Either side may be played by one of the given external engines, and engines may play from
+    /// the opening book if there is one.
+    pub fn show(&mut self, ctx: &Context, engines: &[PathBuf], has_book: bool) -> Option<NewGame> {
+        let mut open = self.open;
+        let mut started = None;
+
+        Window::new("New game")
+            .open(&mut open)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut self.start, StartingPosition::Standard, "Standard");
+                    ui.radio_value(&mut self.start, StartingPosition::Chess960, "Chess960");
+                    ui.radio_value(&mut self.start, StartingPosition::Fen, "From FEN");
+                });
+                match self.start {
+                    StartingPosition::Standard => {}
+                    StartingPosition::Chess960 => {
+                        ui.horizontal(|ui| {
+                            ui.label("Position");
+                            ui.add(DragValue::new(&mut self.chess960).range(0..=959));
+                            if ui.button("Random").clicked() {
+                                self.chess960 = fastrand::u16(0..960);
+                            }
+                        });
+                    }
+                    StartingPosition::Fen => {
+                        if ui.text_edit_singleline(&mut self.fen).changed() {
+                            self.error = None;
+                        }
+                    }
+                }
+
+                ui.separator();
+                Grid::new("NewGamePlayers").num_columns(2).show(ui, |ui| {
+                    ui.label("White");
+                    ui.vertical(|ui| {
+                        player_ui(ui, PieceColor::White, &mut self.white_player, engines);
+                        if self.white_player == Player::Human {
+                            ui.text_edit_singleline(&mut self.white);
+                        }
+                    });
+                    ui.end_row();
+                    ui.label("Black");
+                    ui.vertical(|ui| {
+                        player_ui(ui, PieceColor::Black, &mut self.black_player, engines);
+                        if self.black_player == Player::Human {
+                            ui.text_edit_singleline(&mut self.black);
+                        }
+                    });
+                    ui.end_row();
+                });
+                let engine_playing =
+                    self.white_player != Player::Human || self.black_player != Player::Human;
+                ui.add_enabled(
+                    engine_playing && has_book,
+                    Checkbox::new(&mut self.book, "Engines play from the opening book"),
+                )
+                .on_disabled_hover_text("Choose an opening book in the preferences");
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.timed, "Time control");
+                    ui.add_enabled_ui(self.timed, |ui| {
+                        if ui.text_edit_singleline(&mut self.time_control).changed() {
+                            self.error = None;
+                        }
+                    });
+                });
+
+                if let Some(error) = self.error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+
+                ui.separator();
+                if ui.button("Start").clicked() {
+                    match self.new_game() {
+                        Ok(new_game) => started = Some(new_game),
+                        Err(error) => self.error = Some(error),
+                    }
+                }
+            });
+
+        self.open = open && started.is_none();
+        started
+    }
+
+    /// Returns the game described by the dialog, or an error if any of it is invalid
+    fn new_game(&self) -> Result<NewGame, &'static str> {
+        let board = match self.start {
+            StartingPosition::Standard => Board::new_init(),
+            StartingPosition::Chess960 => Board::chess960(self.chess960),
+            StartingPosition::Fen => {
+                let board = Board::from_fen(&self.fen)?;
+                board.validate()?;
+                board
+            }
+        };
+        let time_control = match self.timed {
+            true => Some(self.time_control.parse()?),
+            false => None,
+        };
+
+        Ok(NewGame {
+            chess960: board.is_chess960() || self.start == StartingPosition::Chess960,
+            board,
+            white: self
+                .white_player
+                .engine_name()
+                .unwrap_or_else(|| self.white.clone()),
+            black: self
+                .black_player
+                .engine_name()
+                .unwrap_or_else(|| self.black.clone()),
+            white_player: self.white_player.clone(),
+            black_player: self.black_player.clone(),
+            time_control,
+            book: self.book,
+        })
+    }
+}
+
+/// Shows the choice of who plays the given side, and the level or engine if it is an engine
+fn player_ui(ui: &mut Ui, color: PieceColor, player: &mut Player, engines: &[PathBuf]) {
+    ui.horizontal(|ui| {
+        if ui.radio(*player == Player::Human, "Human").clicked() {
+            *player = Player::Human;
+        }
+        let built_in = matches!(player, Player::BuiltIn { .. });
+        if ui.radio(built_in, "Built-in engine").clicked() && !built_in {
+            *player = Player::BuiltIn {
+                level: 1,
+                human_like: true,
+            };
+        }
+        let external = matches!(player, Player::External(_));
+        let button = ui
+            .add_enabled_ui(!engines.is_empty(), |ui| {
+                ui.radio(external, "External engine")
+            })
+            .inner
+            .on_disabled_hover_text("Add engines in the preferences to play against them");
+        if button.clicked() && !external {
+            *player = Player::External(engines[0].clone());
+        }
+    });
+
+    match player {
+        Player::Human => {}
+        Player::BuiltIn { level, human_like } => {
+            ui.horizontal(|ui| {
+                ui.add(Slider::new(level, 1..=MAX_LEVEL).text("Level"));
+                match *level < MAX_LEVEL {
+                    true => ui.weak(format!("about {} Elo", level_elo(*level))),
+                    false => ui.weak("full strength"),
+                };
+            });
+            ui.add_enabled(
+                *level < MAX_LEVEL,
+                Checkbox::new(human_like, "Human-like mistakes"),
+            )
+            .on_hover_text("Miss tactics now and then, rather than choosing worse moves");
+        }
+        Player::External(path) => {
+            ComboBox::from_id_salt(("NewGameEngine", color))
+                .selected_text(engine_name(path))
+                .show_ui(ui, |ui| {
+                    for engine in engines {
+                        ui.selectable_value(path, engine.clone(), engine_name(engine));
+                    }
+                });
+        }
+    }
+}
+
+/// Returns the name of the engine at the given path, from its file name
+pub fn engine_name(path: &Path) -> String {
+    path.file_stem()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy()
+        .into_owned()
+}
+
+impl Default for NewGameDialog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
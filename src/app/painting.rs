@@ -1 +1,153 @@
-pub struct Painting {}
+use eframe::{
+    egui::{Modifiers, Painter, PointerButton, Response, Shape, Ui},
+    epaint::{Color32, Pos2, Rect, Stroke},
+};
+
+use crate::{
+    annotation::{AnnotationColor, Annotations, Arrow},
+    board::Square,
+};
+
+/// A change the user has made to the annotations of the position being viewed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Edit {
+    /// An arrow was drawn or erased
+    Arrow(Arrow),
+
+    /// A square was marked or unmarked
+    Square(Square, AnnotationColor),
+}
+
+/// The state of the user drawing arrows and marking squares on the board, kept between frames
+///
+/// Right-clicking a square marks it, and dragging with the right button draws an arrow. Drawing
+/// the same mark again erases it. The color is green, or red with shift, blue with alt, and
+/// yellow with ctrl or with both shift and alt.
+#[derive(Debug, Default)]
+pub struct Painting {
+    drawing: Option<(Square, AnnotationColor)>,
+    edit: Option<Edit>,
+}
+
+impl Painting {
+    /// Constructs a new painting state, with nothing being drawn
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Applies the change the user has made, if any, to the given annotations, returning true if
+    /// there was one
+    pub fn apply(&mut self, annotations: &mut Annotations) -> bool {
+        match self.edit.take() {
+            Some(Edit::Arrow(arrow)) => annotations.toggle_arrow(arrow),
+            Some(Edit::Square(square, color)) => annotations.toggle_square(square, color),
+            None => return false,
+        }
+        true
+    }
+
+    /// Updates the drawing from right clicks and drags on the board, given the square under each
+    /// position
+    pub fn handle_input(
+        &mut self,
+        ui: &Ui,
+        response: &Response,
+        square_at_pos: impl Fn(Pos2) -> Option<Square>,
+    ) {
+        let color = ui.input(|i| color_for(i.modifiers));
+        let pointer_square = response.interact_pointer_pos().and_then(&square_at_pos);
+
+        if response.drag_started_by(PointerButton::Secondary) {
+            let origin = ui.input(|i| i.pointer.press_origin());
+            self.drawing = origin.and_then(square_at_pos).map(|from| (from, color));
+        } else if response.drag_stopped_by(PointerButton::Secondary) {
+            if let (Some((from, color)), Some(to)) = (self.drawing.take(), pointer_square) {
+                self.edit = Some(match from == to {
+                    true => Edit::Square(from, color),
+                    false => Edit::Arrow(Arrow { from, to, color }),
+                });
+            }
+        } else if response.secondary_clicked() {
+            self.edit = pointer_square.map(|square| Edit::Square(square, color));
+        }
+    }
+
+    /// Draws the given annotations, and any arrow being drawn to the given square
+    pub fn paint(
+        &self,
+        painter: &Painter,
+        annotations: &Annotations,
+        hovered: Option<Square>,
+        square_rect: impl Fn(Square) -> Rect,
+    ) {
+        for &(square, color) in &annotations.squares {
+            let rect = square_rect(square);
+            let stroke = Stroke::new(rect.width() * 0.07, fill(color));
+            painter.circle_stroke(rect.center(), rect.width() * 0.45, stroke);
+        }
+
+        let mut arrows = annotations.arrows.clone();
+        if let Some((from, color)) = self.drawing {
+            if let Some(to) = hovered.filter(|&to| to != from) {
+                arrows.push(Arrow { from, to, color });
+            }
+        }
+        for arrow in arrows {
+            let from = square_rect(arrow.from);
+            let to = square_rect(arrow.to);
+            paint_arrow(
+                painter,
+                from.center(),
+                to.center(),
+                from.width(),
+                fill(arrow.color),
+            );
+        }
+    }
+}
+
+/// Returns the color chosen by the given modifier keys
+fn color_for(modifiers: Modifiers) -> AnnotationColor {
+    match (
+        modifiers.shift,
+        modifiers.alt,
+        modifiers.command || modifiers.ctrl,
+    ) {
+        (true, true, _) | (_, _, true) => AnnotationColor::Yellow,
+        (true, false, false) => AnnotationColor::Red,
+        (false, true, false) => AnnotationColor::Blue,
+        (false, false, false) => AnnotationColor::Green,
+    }
+}
+
+/// Returns the translucent color annotations of the given color are drawn in
+fn fill(color: AnnotationColor) -> Color32 {
+    match color {
+        AnnotationColor::Green => Color32::from_rgba_premultiplied(14, 102, 28, 170),
+        AnnotationColor::Red => Color32::from_rgba_premultiplied(150, 18, 18, 170),
+        AnnotationColor::Yellow => Color32::from_rgba_premultiplied(165, 125, 0, 170),
+        AnnotationColor::Blue => Color32::from_rgba_premultiplied(0, 60, 140, 170),
+    }
+}
+
+/// Draws an arrow between the centers of two squares of the given size
+fn paint_arrow(painter: &Painter, from: Pos2, to: Pos2, square_size: f32, color: Color32) {
+    let direction = (to - from).normalized();
+    let normal = direction.rot90();
+    let head_length = square_size * 0.4;
+    let head_width = square_size * 0.4;
+
+    // start at the edge of the starting square, and stop the shaft where the head begins
+    let start = from + direction * square_size * 0.25;
+    let base = to - direction * head_length;
+    painter.line_segment([start, base], Stroke::new(square_size * 0.16, color));
+    painter.add(Shape::convex_polygon(
+        vec![
+            to,
+            base + normal * head_width * 0.5,
+            base - normal * head_width * 0.5,
+        ],
+        color,
+        Stroke::NONE,
+    ));
+}
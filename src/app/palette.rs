@@ -0,0 +1,354 @@
+use std::path::{Path, PathBuf};
+
+use eframe::{
+    egui::{
+        text::LayoutJob, Align, Align2, Context, Key, Layout, Modifiers, RichText, ScrollArea,
+        SelectableLabel, TextEdit, TextFormat, Window,
+    },
+    epaint::{Color32, FontId},
+};
+
+use crate::{board::Board, database::Database, r#move::Move};
+
+use super::command::Command;
+
+/// The most results shown at once
+const MAX_RESULTS: usize = 50;
+
+/// The most games of the game database's search results offered
+const MAX_GAMES: usize = 1000;
+
+/// The most recently used entries remembered for ranking
+const MAX_RECENT: usize = 20;
+
+/// Something that can be chosen from the command palette
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PaletteItem {
+    /// A menu action
+    Command(Command),
+
+    /// A legal move in the position being viewed
+    Move(Move),
+
+    /// A recently opened or saved PGN file
+    File(PathBuf),
+
+    /// A game in the game database, by its index
+    Game(usize),
+}
+
+/// An entry in the command palette
+struct Entry {
+    item: PaletteItem,
+    label: String,
+    detail: String,
+}
+
+/// A Ctrl+P style palette that fuzzy-finds commands, moves, recent files, and database games
+#[derive(Debug, Default)]
+pub struct Palette {
+    open: bool,
+    query: String,
+    selected: usize,
+
+    /// Labels of recently chosen entries, most recent first
+    recent: Vec<String>,
+}
+
+impl Palette {
+    /// Constructs a new, closed command palette
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Opens the palette with an empty query
+    pub fn open(&mut self) {
+        self.open = true;
+        self.query.clear();
+        self.selected = 0;
+    }
+
+    /// Returns true if the palette is open
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Shows the palette if it is open, returning the item the user chose, if any
+    ///
+    /// Besides commands and the moves on the given board, the given recent files are offered, as
+    /// are the games of the given database its last search found.
+    pub fn show(
+        &mut self,
+        ctx: &Context,
+        board: &Board,
+        files: &[PathBuf],
+        games: Option<(&Database, &[usize])>,
+    ) -> Option<PaletteItem> {
+        if !self.open {
+            return None;
+        }
+
+        let entries = self.ranked_entries(ctx, board, files, games);
+
+        // handle list navigation before the text edit can consume the keys
+        let (up, down, enter, escape) = ctx.input_mut(|i| {
+            (
+                i.consume_key(Modifiers::NONE, Key::ArrowUp),
+                i.consume_key(Modifiers::NONE, Key::ArrowDown),
+                i.consume_key(Modifiers::NONE, Key::Enter),
+                i.consume_key(Modifiers::NONE, Key::Escape),
+            )
+        });
+        if up {
+            self.selected = self.selected.saturating_sub(1);
+        }
+        if down {
+            self.selected += 1;
+        }
+        self.selected = self.selected.min(entries.len().saturating_sub(1));
+
+        let mut chosen = enter
+            .then(|| entries.get(self.selected))
+            .flatten()
+            .map(|(entry, _)| entry);
+
+        let window = Window::new("Command palette")
+            .title_bar(false)
+            .resizable(false)
+            .anchor(Align2::CENTER_TOP, [0.0, 24.0])
+            .fixed_size([320.0, 0.0])
+            .show(ctx, |ui| {
+                let edit = ui.add(
+                    TextEdit::singleline(&mut self.query)
+                        .hint_text("Search commands, moves, files, and games")
+                        .desired_width(f32::INFINITY),
+                );
+                edit.request_focus();
+                if edit.changed() {
+                    self.selected = 0;
+                }
+
+                ui.separator();
+                if entries.is_empty() {
+                    ui.weak("No matches");
+                }
+                ScrollArea::vertical().max_height(240.0).show(ui, |ui| {
+                    for (index, (entry, matched)) in entries.iter().enumerate() {
+                        let selected = index == self.selected;
+                        let job = highlight(&entry.label, matched, ui.visuals().text_color());
+                        let response = ui.horizontal(|ui| {
+                            let response = ui.add(SelectableLabel::new(selected, job));
+                            ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                                ui.label(RichText::new(&entry.detail).weak().small())
+                            });
+                            response
+                        });
+                        if selected && (up || down) {
+                            response.inner.scroll_to_me(None);
+                        }
+                        if response.inner.clicked() {
+                            chosen = Some(entry);
+                        }
+                    }
+                });
+            });
+
+        let clicked_elsewhere = window.is_some_and(|window| window.response.clicked_elsewhere());
+        if escape || clicked_elsewhere {
+            self.open = false;
+        }
+
+        let chosen = chosen?;
+        self.open = false;
+        self.recent.retain(|label| *label != chosen.label);
+        self.recent.insert(0, chosen.label.clone());
+        self.recent.truncate(MAX_RECENT);
+        Some(chosen.item.clone())
+    }
+
+    /// Returns every entry matching the query, best first, with the indices of matched characters
+    fn ranked_entries(
+        &self,
+        ctx: &Context,
+        board: &Board,
+        files: &[PathBuf],
+        games: Option<(&Database, &[usize])>,
+    ) -> Vec<(Entry, Vec<usize>)> {
+        let commands = Command::ALL.into_iter().map(|command| Entry {
+            item: PaletteItem::Command(command),
+            label: command.name().to_string(),
+            detail: command
+                .shortcut()
+                .map(|shortcut| ctx.format_shortcut(&shortcut))
+                .unwrap_or_default(),
+        });
+        let moves = board.legal_moves().into_iter().map(|r#move| Entry {
+            item: PaletteItem::Move(r#move),
+            label: board.san(r#move),
+            detail: String::from("move"),
+        });
+        let files = files.iter().map(|path| Entry {
+            item: PaletteItem::File(path.clone()),
+            label: file_name(path),
+            detail: path.parent().map(file_name).unwrap_or_default(),
+        });
+        let games = games.into_iter().flat_map(|(database, results)| {
+            results.iter().take(MAX_GAMES).map(|&id| {
+                let headers = database.headers(id);
+                let tag = |name| headers.get(name).unwrap_or_default();
+                Entry {
+                    item: PaletteItem::Game(id),
+                    label: format!(
+                        "{} – {}, {} {}",
+                        tag("White"),
+                        tag("Black"),
+                        tag("Event"),
+                        tag("Date")
+                    ),
+                    detail: String::from("game"),
+                }
+            })
+        });
+
+        let mut ranked: Vec<_> = commands
+            .chain(moves)
+            .chain(files)
+            .chain(games)
+            .filter_map(|entry| {
+                let (score, matched) = fuzzy_match(&self.query, &entry.label)?;
+                let recency = self
+                    .recent
+                    .iter()
+                    .position(|label| *label == entry.label)
+                    .map_or(0, |index| (MAX_RECENT - index) as i32 * 2);
+                Some((score + recency, entry, matched))
+            })
+            .collect();
+
+        // sort by score, keeping the listed order for ties
+        ranked.sort_by_key(|(score, ..)| -score);
+        ranked
+            .into_iter()
+            .take(MAX_RESULTS)
+            .map(|(_, entry, matched)| (entry, matched))
+            .collect()
+    }
+}
+
+/// Returns the last component of the given path, or the whole path if it has none
+fn file_name(path: &Path) -> String {
+    path.file_name().map_or_else(
+        || path.display().to_string(),
+        |name| name.to_string_lossy().into_owned(),
+    )
+}
+
+/// Scores how well the query matches the text as a case-insensitive subsequence, returning the
+/// score and the indices of the matched characters, or `None` if it does not match
+///
+/// Matches of consecutive characters, at the start of words, and with the same case score
+/// higher. An empty query matches everything with a score of zero.
+pub fn fuzzy_match(query: &str, text: &str) -> Option<(i32, Vec<usize>)> {
+    let text: Vec<char> = text.chars().collect();
+    let mut matched = Vec::new();
+    let mut score = 0;
+    let mut start = 0;
+
+    for q in query.chars().filter(|c| !c.is_whitespace()) {
+        let index = (start..text.len()).find(|&i| text[i].eq_ignore_ascii_case(&q))?;
+
+        score += 1;
+        if matched.last().is_some_and(|&last| last + 1 == index) {
+            score += 5;
+        }
+        if index == 0 || !text[index - 1].is_alphanumeric() {
+            score += 8;
+        }
+        if text[index] == q {
+            score += 1;
+        }
+        if matched.is_empty() {
+            // prefer matches that start early in the text
+            score -= index.min(5) as i32;
+        }
+
+        matched.push(index);
+        start = index + 1;
+    }
+
+    Some((score, matched))
+}
+
+/// Lays out the given text with the characters at the given indices emphasized
+fn highlight(text: &str, matched: &[usize], color: Color32) -> LayoutJob {
+    let mut job = LayoutJob::default();
+    for (index, c) in text.chars().enumerate() {
+        let format = match matched.contains(&index) {
+            true => TextFormat {
+                font_id: FontId::proportional(14.0),
+                color: Color32::from_rgb(230, 180, 60),
+                ..Default::default()
+            },
+            false => TextFormat {
+                font_id: FontId::proportional(14.0),
+                color,
+                ..Default::default()
+            },
+        };
+        job.append(&c.to_string(), 0.0, format);
+    }
+    job
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the labels of the entries the palette offers for the given query, best first
+    fn labels(palette: &Palette, query: &str, files: &[PathBuf]) -> Vec<String> {
+        let palette = Palette {
+            query: query.to_string(),
+            recent: palette.recent.clone(),
+            ..Palette::default()
+        };
+        palette
+            .ranked_entries(&Context::default(), &Board::new_init(), files, None)
+            .into_iter()
+            .map(|(entry, _)| entry.label)
+            .collect()
+    }
+
+    #[test]
+    fn matching() {
+        // each character scores one, and more at the start of a word or in the same case
+        assert_eq!(fuzzy_match("Ng", "New game"), Some((20, vec![0, 4])));
+        assert_eq!(fuzzy_match("ng", "New game"), Some((19, vec![0, 4])));
+        assert_eq!(fuzzy_match("ne", "New game"), Some((16, vec![0, 1])));
+        assert_eq!(fuzzy_match("", "New game"), Some((0, Vec::new())));
+        assert_eq!(fuzzy_match("gn", "New game"), None);
+        assert_eq!(fuzzy_match("x", "New game"), None);
+
+        // later and scattered matches score less
+        let score = |query, text| fuzzy_match(query, text).unwrap().0;
+        assert!(score("game", "Load game") < score("game", "Game review"));
+        assert!(score("sg", "Save game") > score("sg", "Toggle showing the square"));
+        assert!(score("nf3", "Nf3") > score("nf3", "Nxf3"));
+    }
+
+    #[test]
+    fn ranking() {
+        let mut palette = Palette::new();
+        assert_eq!(labels(&palette, "save", &[]), ["Save game", "Save game as"]);
+        assert_eq!(labels(&palette, "Nf", &[])[0], "Nf3");
+        assert!(labels(&palette, "", &[]).len() <= MAX_RESULTS);
+
+        // recently chosen entries come first among matches of similar scores
+        palette.recent = vec![String::from("Save game as")];
+        assert_eq!(labels(&palette, "save", &[]), ["Save game as", "Save game"]);
+        palette.recent = vec![String::from("Nh3")];
+        assert_eq!(labels(&palette, "n", &[])[0], "Nh3");
+
+        let files = [PathBuf::from("games/world championship.pgn")];
+        assert_eq!(labels(&palette, "wch", &files)[0], "world championship.pgn");
+    }
+}
@@ -0,0 +1,108 @@
+use eframe::egui::{Frame, Image, Margin, RichText, Spinner, Ui, Vec2};
+
+use crate::{game::Game, piece::PieceColor};
+
+use super::theme::{BoardTheme, DEFAULT_THEME};
+
+/// The size of the captured piece sprites, in points
+const CAPTURED_SIZE: f32 = 18.0;
+
+/// How far each captured piece overlaps the one before it, in points
+const CAPTURED_OVERLAP: f32 = 6.0;
+
+/// A view of both players, with the pieces each has captured and their material advantage
+pub struct PlayersView<'a> {
+    game: &'a Game,
+    flipped: bool,
+    theme: &'a BoardTheme,
+    thinking: Option<PieceColor>,
+}
+
+impl<'a> PlayersView<'a> {
+    /// Constructs a new `PlayersView` of the players of the given game
+    pub fn new(game: &'a Game) -> Self {
+        Self {
+            game,
+            flipped: false,
+            theme: &DEFAULT_THEME,
+            thinking: None,
+        }
+    }
+
+    /// Sets whether black is shown at the bottom, matching a flipped board
+    #[must_use]
+    pub fn flipped(mut self, flipped: bool) -> Self {
+        self.flipped = flipped;
+        self
+    }
+
+    /// Sets how the captured pieces look
+    #[must_use]
+    pub fn theme(mut self, theme: &'a BoardTheme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    /// Sets which player, if any, is an engine that is thinking about its move
+    #[must_use]
+    pub fn thinking(mut self, thinking: Option<PieceColor>) -> Self {
+        self.thinking = thinking;
+        self
+    }
+
+    /// Shows the players, as of the position being viewed
+    pub fn show(self, ui: &mut Ui) {
+        let order = match self.flipped {
+            false => [PieceColor::Black, PieceColor::White],
+            true => [PieceColor::White, PieceColor::Black],
+        };
+        let board = self.game.board();
+        let captured = self.game.captured_pieces();
+
+        for color in order {
+            let name = match color {
+                PieceColor::White => "White",
+                PieceColor::Black => "Black",
+            };
+            let name = self.game.headers().get(name).unwrap_or(name);
+
+            // the pieces this player took are the opponent's, most valuable first
+            let mut taken: Vec<_> = captured
+                .iter()
+                .filter(|piece| piece.color != color)
+                .copied()
+                .collect();
+            taken.sort_by_key(|piece| std::cmp::Reverse(piece.kind.value()));
+
+            // promotions count too, so the advantage comes from the pieces left on the board
+            let advantage = board.material(color) as i64 - board.material(color.opposite()) as i64;
+
+            Frame::none()
+                .fill(ui.visuals().faint_bg_color)
+                .rounding(4.0)
+                .inner_margin(Margin::symmetric(8.0, 2.0))
+                .show(ui, |ui| {
+                    ui.set_width(ui.available_width());
+                    ui.horizontal(|ui| {
+                        ui.label(RichText::new(name).strong());
+                        if self.thinking == Some(color) {
+                            ui.add(Spinner::new());
+                            ui.weak("thinking...");
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.spacing_mut().item_spacing.x = -CAPTURED_OVERLAP;
+                        for piece in taken {
+                            let image = Image::new(self.theme.piece_image(piece))
+                                .fit_to_exact_size(Vec2::splat(CAPTURED_SIZE));
+                            ui.add(image);
+                        }
+                        ui.add_space(CAPTURED_OVERLAP + 4.0);
+                        if advantage > 0 {
+                            ui.weak(format!("+{advantage}"));
+                        }
+                    });
+                });
+        }
+    }
+}
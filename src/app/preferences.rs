@@ -0,0 +1,303 @@
+use std::path::PathBuf;
+
+use eframe::egui::{Button, Context, Grid, Slider, ThemePreference, Ui, Window};
+use serde::{Deserialize, Serialize};
+
+use crate::game::EditPast;
+
+use super::{animation::AnimationSpeed, board_view::Coordinates, theme::BoardTheme};
+
+/// The key the settings are stored under
+pub const SETTINGS_KEY: &str = "settings";
+
+/// The key the game in progress is stored under
+pub const SESSION_KEY: &str = "session";
+
+/// Everything about the app the user can change that is remembered between launches
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// How much larger than normal the interface is drawn
+    pub ui_scale: f32,
+
+    /// Whether the interface is dark, light, or follows the system
+    pub theme: ThemePreference,
+
+    /// How the board and pieces look
+    pub board_theme: BoardTheme,
+
+    /// Whether black is at the bottom of the board
+    pub flipped: bool,
+
+    /// Where board coordinates are drawn
+    pub coordinates: Coordinates,
+
+    /// Whether the name of the square under the mouse is shown
+    pub show_hovered_square: bool,
+
+    /// How quickly pieces move between squares
+    pub animation_speed: AnimationSpeed,
+
+    /// Whether moves may be played from earlier positions
+    pub allow_editing_past: bool,
+
+    /// What happens when moves are played from earlier positions
+    pub edit_past: EditPast,
+
+    /// The time control of new games, or `None` for untimed games
+    pub time_control: Option<String>,
+
+    /// Paths to UCI chess engines
+    pub engines: Vec<PathBuf>,
+
+    /// The engine that analyzes positions, or `None` for the built-in engine
+    pub analysis_engine: Option<PathBuf>,
+
+    /// The number of best lines shown while analyzing
+    pub analysis_lines: u32,
+
+    /// The Polyglot opening book suggesting moves, if any
+    pub book: Option<PathBuf>,
+
+    /// The directory of Syzygy endgame tablebases, if any
+    pub tablebase: Option<PathBuf>,
+
+    /// The PGN file of games the explorer looks positions up in, if any
+    pub database: Option<PathBuf>,
+
+    /// The game database last opened in the database browser, if any
+    pub game_database: Option<PathBuf>,
+
+    /// PGN files recently opened or saved, most recent first
+    pub recent_files: Vec<PathBuf>,
+
+    /// Whether the game in progress is restored when the app is launched
+    pub restore_session: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            ui_scale: 1.0,
+            theme: ThemePreference::System,
+            board_theme: BoardTheme::default(),
+            flipped: false,
+            coordinates: Coordinates::Hidden,
+            show_hovered_square: false,
+            animation_speed: AnimationSpeed::Normal,
+            allow_editing_past: false,
+            edit_past: EditPast::Variation,
+            time_control: None,
+            engines: Vec::new(),
+            analysis_engine: None,
+            analysis_lines: 3,
+            book: None,
+            tablebase: None,
+            database: None,
+            game_database: None,
+            recent_files: Vec::new(),
+            restore_session: true,
+        }
+    }
+}
+
+/// The game in progress when the app was closed
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Session {
+    /// The game, in Portable Game Notation
+    pub pgn: String,
+
+    /// The file the game was loaded from or last saved to
+    pub path: Option<PathBuf>,
+
+    /// Whether the game had changed since it was last saved
+    pub dirty: bool,
+}
+
+/// The window for editing the settings
+#[derive(Debug, Default)]
+pub struct Preferences {
+    open: bool,
+    theme_error: Option<String>,
+}
+
+impl Preferences {
+    /// Constructs a new, closed preferences window
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Opens the preferences window
+    pub fn open(&mut self) {
+        self.open = true;
+    }
+
+    /// Shows the preferences window if it is open, returning true if the user asked to edit the
+    /// default time control
+    pub fn show(&mut self, ctx: &Context, settings: &mut Settings) -> bool {
+        let mut edit_clock = false;
+
+        Window::new("Preferences")
+            .open(&mut self.open)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                Grid::new("Preferences")
+                    .num_columns(2)
+                    .spacing([16.0, 6.0])
+                    .show(ui, |ui| {
+                        ui.label("UI scale");
+                        ui.add(Slider::new(&mut settings.ui_scale, 0.5..=3.0).step_by(0.25));
+                        ui.end_row();
+
+                        ui.label("Theme");
+                        ui.horizontal(|ui| {
+                            ui.radio_value(&mut settings.theme, ThemePreference::System, "System");
+                            ui.radio_value(&mut settings.theme, ThemePreference::Dark, "Dark");
+                            ui.radio_value(&mut settings.theme, ThemePreference::Light, "Light");
+                        });
+                        ui.end_row();
+
+                        ui.label("Board theme");
+                        ui.vertical(|ui| settings.board_theme.ui(ui, &mut self.theme_error));
+                        ui.end_row();
+
+                        ui.label("Board");
+                        ui.vertical(|ui| {
+                            ui.checkbox(&mut settings.flipped, "Black at the bottom");
+                            ui.checkbox(
+                                &mut settings.show_hovered_square,
+                                "Show square under mouse",
+                            );
+                        });
+                        ui.end_row();
+
+                        ui.label("Coordinates");
+                        ui.horizontal(|ui| {
+                            let coordinates = &mut settings.coordinates;
+                            ui.radio_value(coordinates, Coordinates::Hidden, "Hidden");
+                            ui.radio_value(coordinates, Coordinates::Inside, "Inside");
+                            ui.radio_value(coordinates, Coordinates::Outside, "Outside");
+                        });
+                        ui.end_row();
+
+                        ui.label("Animations");
+                        ui.horizontal(|ui| {
+                            let speed = &mut settings.animation_speed;
+                            ui.radio_value(speed, AnimationSpeed::Off, "Off");
+                            ui.radio_value(speed, AnimationSpeed::Slow, "Slow");
+                            ui.radio_value(speed, AnimationSpeed::Normal, "Normal");
+                            ui.radio_value(speed, AnimationSpeed::Fast, "Fast");
+                        });
+                        ui.end_row();
+
+                        ui.label("Editing the past");
+                        ui.vertical(|ui| {
+                            ui.checkbox(&mut settings.allow_editing_past, "Allowed");
+                            ui.add_enabled_ui(settings.allow_editing_past, |ui| {
+                                let edit_past = &mut settings.edit_past;
+                                ui.radio_value(edit_past, EditPast::Variation, "Start a variation");
+                                ui.radio_value(
+                                    edit_past,
+                                    EditPast::Truncate,
+                                    "Replace the rest of the game",
+                                );
+                            });
+                        });
+                        ui.end_row();
+
+                        ui.label("Time control");
+                        ui.horizontal(|ui| {
+                            ui.label(settings.time_control.as_deref().unwrap_or("Untimed"));
+                            edit_clock = ui.button("Edit").clicked();
+                        });
+                        ui.end_row();
+
+                        ui.label("Engines");
+                        ui.vertical(|ui| engines_ui(ui, &mut settings.engines));
+                        ui.end_row();
+
+                        ui.label("Opening book");
+                        ui.horizontal(|ui| {
+                            path_ui(ui, &mut settings.book, || {
+                                rfd::FileDialog::new()
+                                    .add_filter("Polyglot book", &["bin"])
+                                    .pick_file()
+                            })
+                        });
+                        ui.end_row();
+
+                        ui.label("Tablebases");
+                        ui.horizontal(|ui| {
+                            path_ui(ui, &mut settings.tablebase, || {
+                                rfd::FileDialog::new().pick_folder()
+                            })
+                        });
+                        ui.end_row();
+
+                        ui.label("Explorer games");
+                        ui.horizontal(|ui| {
+                            path_ui(ui, &mut settings.database, || {
+                                rfd::FileDialog::new()
+                                    .add_filter("PGN games", &["pgn"])
+                                    .pick_file()
+                            })
+                        });
+                        ui.end_row();
+
+                        ui.label("Session");
+                        ui.checkbox(
+                            &mut settings.restore_session,
+                            "Restore the game in progress on launch",
+                        );
+                        ui.end_row();
+                    });
+
+                ui.separator();
+                if ui.button("Reset to defaults").clicked() {
+                    *settings = Settings::default();
+                }
+            });
+
+        edit_clock
+    }
+}
+
+/// Lists the engine paths, with buttons to add and remove them
+fn engines_ui(ui: &mut Ui, engines: &mut Vec<PathBuf>) {
+    let mut removed = None;
+    for (index, path) in engines.iter().enumerate() {
+        ui.horizontal(|ui| {
+            if ui.add(Button::new("✖").small()).clicked() {
+                removed = Some(index);
+            }
+            ui.label(path.display().to_string());
+        });
+    }
+    if let Some(index) = removed {
+        engines.remove(index);
+    }
+
+    if ui.button("Add engine").clicked() {
+        if let Some(path) = rfd::FileDialog::new().pick_file() {
+            engines.push(path);
+        }
+    }
+}
+
+/// Shows a chosen path, with buttons to choose another with the given dialog and to remove it
+fn path_ui(ui: &mut Ui, path: &mut Option<PathBuf>, choose: impl FnOnce() -> Option<PathBuf>) {
+    if path.is_some() && ui.add(Button::new("✖").small()).clicked() {
+        *path = None;
+    }
+    match path {
+        Some(path) => ui.label(path.display().to_string()),
+        None => ui.weak("None"),
+    };
+
+    if ui.button("Choose").clicked() {
+        if let Some(chosen) = choose() {
+            *path = Some(chosen);
+        }
+    }
+}
@@ -0,0 +1,307 @@
+use std::time::Duration;
+
+use eframe::egui::{Context, DragValue, Grid, ProgressBar, Ui, Window};
+
+use crate::{
+    board::Board,
+    engine::{Engine, Event, Info, Limits, Score, Thinking},
+    game::{Game, NodeId},
+    r#move::Move,
+    review::{self, Evaluation, PlayerReview},
+};
+
+/// An engine evaluating the positions of a game's main line one at a time
+struct Run {
+    engine: Engine,
+    thinking: Option<Thinking>,
+    move_time: Duration,
+
+    /// The starting position, the nodes of the main line after it, and their moves
+    start: Board,
+    line: Vec<NodeId>,
+    moves: Vec<Move>,
+
+    /// The evaluations of the positions so far, from the starting position on
+    evaluations: Vec<Evaluation>,
+
+    /// The engine's latest report on the position it is thinking about
+    latest: Option<Info>,
+}
+
+impl Run {
+    /// Starts the engine on the next position, evaluating positions with no legal moves without
+    /// it, and returns false once every position has been evaluated
+    fn think_next(&mut self) -> bool {
+        while self.evaluations.len() < self.line.len() {
+            let moves = &self.moves[..self.evaluations.len()];
+            let mut board = self.start.clone();
+            for &r#move in moves {
+                board.play_move(r#move);
+            }
+            if board.legal_moves().is_empty() {
+                let score = match board.is_in_check(board.to_move()) {
+                    true => Score::Mate(0),
+                    false => Score::Centipawns(0),
+                };
+                self.evaluations.push(Evaluation {
+                    score,
+                    pv: Vec::new(),
+                });
+                continue;
+            }
+
+            let limits = Limits {
+                move_time: Some(self.move_time),
+                ..Limits::default()
+            };
+            self.thinking = Some(self.engine.think(&self.start, moves, limits));
+            self.latest = None;
+            return true;
+        }
+        self.thinking = None;
+        false
+    }
+
+    /// Returns true if the given game's main line is still the one being evaluated
+    fn matches(&self, game: &Game) -> bool {
+        game.start().fen() == self.start.fen()
+            && game.main_line() == self.line[1..]
+            && game.moves_to(*self.line.last().expect("the line has its start")) == self.moves
+    }
+}
+
+/// Whether the game is being reviewed
+enum State {
+    /// No review has been started
+    Idle,
+
+    /// The engine is evaluating the positions of the game
+    Running(Box<Run>),
+
+    /// The game was annotated, and white and black played as reviewed
+    Done([PlayerReview; 2]),
+
+    /// The review failed
+    Failed(String),
+}
+
+/// The window for annotating the moves of the game with an engine's judgement of them, and
+/// summing up how accurately each player played
+///
+/// The engine runs in the background, so the window can be closed and opened again to check on
+/// its progress.
+pub struct GameReview {
+    open: bool,
+    seconds: f32,
+    state: State,
+}
+
+impl GameReview {
+    /// Constructs a new, closed game review window
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            seconds: 1.0,
+            state: State::Idle,
+        }
+    }
+
+    /// Opens the window
+    pub fn open(&mut self) {
+        self.open = true;
+    }
+
+    /// Returns true if the engine is reviewing the game
+    pub fn is_running(&self) -> bool {
+        matches!(self.state, State::Running(_))
+    }
+
+    /// Starts reviewing the main line of the given game with the given engine
+    pub fn start(&mut self, game: &Game, engine: Engine) {
+        let line = game.main_line();
+        let moves = game.moves_to(line.last().copied().unwrap_or(NodeId::ROOT));
+        let mut run = Run {
+            engine,
+            thinking: None,
+            move_time: Duration::from_secs_f32(self.seconds),
+            start: game.start().clone(),
+            line: [NodeId::ROOT].into_iter().chain(line).collect(),
+            moves,
+            evaluations: Vec::new(),
+            latest: None,
+        };
+        run.think_next();
+        self.state = State::Running(Box::new(run));
+        self.open = true;
+    }
+
+    /// Stops reviewing, leaving the game as it is
+    pub fn cancel(&mut self) {
+        if self.is_running() {
+            self.state = State::Idle;
+        }
+    }
+
+    /// Takes the engine's reports since the last update, moving on to the next position when
+    /// it is done with one, and annotates the game once every position has been evaluated,
+    /// returning true if it did
+    pub fn update(&mut self, game: &mut Game) -> bool {
+        let State::Running(run) = &mut self.state else {
+            return false;
+        };
+
+        let events = run.thinking.as_ref().map(Thinking::events);
+        for event in events.unwrap_or_default() {
+            match event {
+                Event::Info(info) if info.multi_pv <= 1 => run.latest = Some(info),
+                Event::Info(_) => {}
+                Event::BestMove(best) => {
+                    let evaluation = run.latest.take().map(|info| Evaluation {
+                        score: info.score,
+                        pv: match info.pv.is_empty() {
+                            true => best.into_iter().collect(),
+                            false => info.pv,
+                        },
+                    });
+                    let Some(evaluation) = evaluation else {
+                        self.state = State::Failed(String::from(
+                            "The engine moved without evaluating the position",
+                        ));
+                        return false;
+                    };
+                    run.evaluations.push(evaluation);
+                    if run.think_next() {
+                        return false;
+                    }
+                    break;
+                }
+                Event::Failed(error) => {
+                    self.state = State::Failed(format!("The engine failed: {error}"));
+                    return false;
+                }
+            }
+        }
+        if run.thinking.is_some() {
+            return false;
+        }
+
+        if !run.matches(game) {
+            self.state = State::Failed(String::from("The game changed before its review finished"));
+            return false;
+        }
+        let reviews = review::annotate(game, &run.line, &run.evaluations);
+        self.state = State::Done(reviews);
+        true
+    }
+
+    /// Shows the window if it is open, returning true if the user asked to start a review with
+    /// the engine of the given name
+    pub fn show(&mut self, ctx: &Context, engine: &str) -> bool {
+        let mut open = self.open;
+        let mut start = false;
+        Window::new("Analyze game")
+            .open(&mut open)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                let mut cancel = false;
+                match &self.state {
+                    State::Idle => {}
+                    State::Running(run) => {
+                        let (done, total) = (run.evaluations.len(), run.line.len());
+                        ui.horizontal(|ui| {
+                            let bar = ProgressBar::new(done as f32 / total as f32)
+                                .text(format!("Position {} of {total}", (done + 1).min(total)));
+                            ui.add(bar.desired_width(200.0));
+                            cancel = ui.button("Cancel").clicked();
+                        });
+                    }
+                    State::Done(reviews) => {
+                        reviews_ui(ui, *reviews);
+                        ui.separator();
+                    }
+                    State::Failed(error) => {
+                        ui.colored_label(ui.visuals().error_fg_color, error);
+                        ui.separator();
+                    }
+                }
+                if cancel {
+                    self.state = State::Idle;
+                }
+                if self.is_running() {
+                    return;
+                }
+
+                Grid::new("ReviewSettings").num_columns(2).show(ui, |ui| {
+                    ui.label("Engine");
+                    ui.label(engine);
+                    ui.end_row();
+
+                    ui.label("Time per position");
+                    let time = DragValue::new(&mut self.seconds)
+                        .range(0.1..=60.0)
+                        .speed(0.1)
+                        .suffix(" s");
+                    ui.add(time);
+                    ui.end_row();
+                });
+                ui.weak("Choose the engine in the analysis panel");
+                start = ui.button("Analyze").clicked();
+            });
+        self.open = open;
+        start
+    }
+}
+
+impl Default for GameReview {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Shows how accurately white and black played, and how many of their moves were inaccuracies,
+/// mistakes, and blunders
+fn reviews_ui(ui: &mut Ui, reviews: [PlayerReview; 2]) {
+    Grid::new("Reviews")
+        .num_columns(3)
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("");
+            ui.strong("White");
+            ui.strong("Black");
+            ui.end_row();
+
+            let row = |ui: &mut Ui, name: &str, values: [String; 2]| {
+                ui.label(name);
+                for value in values {
+                    ui.label(value);
+                }
+                ui.end_row();
+            };
+            row(
+                ui,
+                "Accuracy",
+                reviews.map(|review| format!("{:.1}%", review.accuracy)),
+            );
+            row(
+                ui,
+                "Average centipawn loss",
+                reviews.map(|review| format!("{:.0}", review.average_loss)),
+            );
+            row(
+                ui,
+                "Inaccuracies ?!",
+                reviews.map(|review| review.inaccuracies.to_string()),
+            );
+            row(
+                ui,
+                "Mistakes ?",
+                reviews.map(|review| review.mistakes.to_string()),
+            );
+            row(
+                ui,
+                "Blunders ??",
+                reviews.map(|review| review.blunders.to_string()),
+            );
+        });
+}
@@ -0,0 +1,221 @@
+use std::path::{Path, PathBuf};
+
+use eframe::{
+    egui::{Button, ImageSource, Ui},
+    epaint::Color32,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    board::Square,
+    piece::{Piece, PieceColor, PieceKind},
+};
+
+use super::board_view::piece_image;
+
+/// The built-in board palettes, as their names and light and dark square colors
+pub const PALETTES: [(&str, Color32, Color32); 6] = [
+    (
+        "Wood",
+        Color32::from_rgb(195, 163, 113),
+        Color32::from_rgb(113, 78, 47),
+    ),
+    (
+        "Green",
+        Color32::from_rgb(238, 238, 210),
+        Color32::from_rgb(118, 150, 86),
+    ),
+    (
+        "Blue",
+        Color32::from_rgb(222, 227, 230),
+        Color32::from_rgb(140, 162, 173),
+    ),
+    (
+        "Gray",
+        Color32::from_rgb(200, 200, 200),
+        Color32::from_rgb(120, 120, 120),
+    ),
+    (
+        "Purple",
+        Color32::from_rgb(232, 221, 245),
+        Color32::from_rgb(136, 119, 183),
+    ),
+    (
+        "Coral",
+        Color32::from_rgb(250, 228, 200),
+        Color32::from_rgb(204, 112, 98),
+    ),
+];
+
+/// The theme used when none is given
+pub static DEFAULT_THEME: BoardTheme = BoardTheme::DEFAULT;
+
+/// How the board and pieces look
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BoardTheme {
+    /// The light square color
+    pub light: Color32,
+
+    /// The dark square color
+    pub dark: Color32,
+
+    /// An image stretched over the whole board, drawn instead of the square colors
+    pub texture: Option<PathBuf>,
+
+    /// A directory of piece images named like the built-in ones, i.e. `white_pawn.png`, drawn
+    /// instead of the built-in pieces
+    pub pieces: Option<PathBuf>,
+}
+
+impl BoardTheme {
+    /// The first built-in palette, with the built-in pieces
+    pub const DEFAULT: Self = Self {
+        light: PALETTES[0].1,
+        dark: PALETTES[0].2,
+        texture: None,
+        pieces: None,
+    };
+
+    /// Returns the color of the given square
+    pub fn square_color(&self, square: Square) -> Color32 {
+        match square.is_light() {
+            true => self.light,
+            false => self.dark,
+        }
+    }
+
+    /// Returns the color of the opposite square color, for drawing text over the given square
+    pub fn contrasting_color(&self, square: Square) -> Color32 {
+        match square.is_light() {
+            true => self.dark,
+            false => self.light,
+        }
+    }
+
+    /// Returns the image of the board texture, if there is one
+    pub fn texture_image(&self) -> Option<ImageSource<'static>> {
+        self.texture.as_deref().map(file_image)
+    }
+
+    /// Returns the image of the given piece, from the piece set directory if there is one
+    pub fn piece_image(&self, piece: Piece) -> ImageSource<'static> {
+        match &self.pieces {
+            Some(directory) => file_image(&directory.join(piece_file_name(piece))),
+            None => piece_image(piece),
+        }
+    }
+
+    /// Shows controls for editing the theme, reporting problems with chosen files in `error`
+    pub fn ui(&mut self, ui: &mut Ui, error: &mut Option<String>) {
+        ui.horizontal_wrapped(|ui| {
+            for (name, light, dark) in PALETTES {
+                let selected = self.light == light && self.dark == dark;
+                let button = Button::new(name).fill(dark).selected(selected);
+                if ui.add(button).on_hover_text(name).clicked() {
+                    (self.light, self.dark) = (light, dark);
+                }
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Light");
+            ui.color_edit_button_srgba(&mut self.light);
+            ui.label("Dark");
+            ui.color_edit_button_srgba(&mut self.dark);
+        });
+
+        ui.horizontal(|ui| {
+            let name = file_name(self.texture.as_deref()).unwrap_or("No texture");
+            ui.label(name);
+            if ui.button("Choose texture").clicked() {
+                let picked = rfd::FileDialog::new()
+                    .add_filter("Image", &["png", "jpg", "jpeg"])
+                    .pick_file();
+                if let Some(path) = picked {
+                    self.texture = Some(path);
+                }
+            }
+            if self.texture.is_some() && ui.button("Clear").clicked() {
+                self.texture = None;
+            }
+        });
+
+        ui.horizontal(|ui| {
+            let name = file_name(self.pieces.as_deref()).unwrap_or("Built-in pieces");
+            ui.label(name);
+            if ui.button("Choose piece set").clicked() {
+                if let Some(directory) = rfd::FileDialog::new().pick_folder() {
+                    match missing_pieces(&directory) {
+                        Some(missing) => {
+                            *error = Some(format!("The piece set is missing {missing}"));
+                        }
+                        None => {
+                            self.pieces = Some(directory);
+                            *error = None;
+                        }
+                    }
+                }
+            }
+            if self.pieces.is_some() && ui.button("Clear").clicked() {
+                self.pieces = None;
+            }
+        });
+
+        if let Some(error) = error {
+            ui.colored_label(ui.visuals().error_fg_color, error.as_str());
+        }
+    }
+}
+
+impl Default for BoardTheme {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Returns the name of the file a piece set stores the given piece's image in, i.e.
+/// `white_pawn.png`
+pub fn piece_file_name(piece: Piece) -> String {
+    let color = match piece.color {
+        PieceColor::White => "white",
+        PieceColor::Black => "black",
+    };
+    let kind = match piece.kind {
+        PieceKind::Pawn => "pawn",
+        PieceKind::Knight => "knight",
+        PieceKind::Bishop => "bishop",
+        PieceKind::Rook => "rook",
+        PieceKind::Queen => "queen",
+        PieceKind::King => "king",
+    };
+    format!("{color}_{kind}.png")
+}
+
+/// Returns the name of the first piece image missing from the given directory, if any
+fn missing_pieces(directory: &Path) -> Option<String> {
+    [PieceColor::White, PieceColor::Black]
+        .into_iter()
+        .flat_map(|color| {
+            [
+                PieceKind::Pawn,
+                PieceKind::Knight,
+                PieceKind::Bishop,
+                PieceKind::Rook,
+                PieceKind::Queen,
+                PieceKind::King,
+            ]
+            .map(|kind| piece_file_name(Piece::new(kind, color)))
+        })
+        .find(|name| !directory.join(name).is_file())
+}
+
+/// Returns an image loaded from the given file
+fn file_image(path: &Path) -> ImageSource<'static> {
+    ImageSource::Uri(format!("file://{}", path.display()).into())
+}
+
+/// Returns the last component of the given path, if there is one
+fn file_name(path: Option<&Path>) -> Option<&str> {
+    path?.file_name()?.to_str()
+}
@@ -1,44 +1,219 @@
-use std::fmt::{Debug, Display};
+use std::{
+    fmt::{Debug, Display},
+    str::FromStr,
+};
 
 use crate::{
-    piece::{Piece, PieceKind},
+    piece::{Piece, PieceColor, PieceKind},
     r#move::Move,
 };
 
+/// Forsyth-Edwards Notation and other starting positions
+pub mod fen;
+
+/// Legal move generation
+pub mod movegen;
+
+/// Standard algebraic notation
+pub mod san;
+
+/// Long algebraic notation, as used by the Universal Chess Interface
+pub mod uci;
+
+/// Zobrist keys identifying positions, as used by opening books
+pub mod zobrist;
+
 /// The current state of a chess board
+#[derive(Clone)]
 pub struct Board {
     pieces: [[Option<Piece>; 8]; 8],
+    to_move: PieceColor,
+    castling: CastlingRights,
+    castling_files: CastlingFiles,
+    en_passant: Option<Square>,
+    halfmove_clock: u16,
+    fullmove_number: u16,
+    history: Vec<Irreversible>,
+}
+
+/// The state lost when a move is played, restored when it is undone
+#[derive(Clone, Copy)]
+struct Irreversible {
+    castling: CastlingRights,
+    en_passant: Option<Square>,
+    halfmove_clock: u16,
+}
+
+/// Which players may still castle, and to which side
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CastlingRights {
+    /// White may castle kingside
+    pub white_kingside: bool,
+
+    /// White may castle queenside
+    pub white_queenside: bool,
+
+    /// Black may castle kingside
+    pub black_kingside: bool,
+
+    /// Black may castle queenside
+    pub black_queenside: bool,
+}
+
+impl CastlingRights {
+    /// Every castling right
+    pub const ALL: Self = Self {
+        white_kingside: true,
+        white_queenside: true,
+        black_kingside: true,
+        black_queenside: true,
+    };
+
+    /// No castling rights
+    pub const NONE: Self = Self {
+        white_kingside: false,
+        white_queenside: false,
+        black_kingside: false,
+        black_queenside: false,
+    };
+
+    /// Removes any rights lost by a piece moving from or to the given square, where the king and
+    /// rooks start on the given files
+    fn update(&mut self, square: Square, files: CastlingFiles) {
+        let (file, rank) = square.indices();
+        let (kingside, queenside) = match rank {
+            0 => (&mut self.white_kingside, &mut self.white_queenside),
+            7 => (&mut self.black_kingside, &mut self.black_queenside),
+            _ => return,
+        };
+        if file == files.king.index() {
+            (*kingside, *queenside) = (false, false);
+        } else if file == files.kingside_rook.index() {
+            *kingside = false;
+        } else if file == files.queenside_rook.index() {
+            *queenside = false;
+        }
+    }
+}
+
+/// The files the king and rooks start on for castling, which are only unusual in Chess960
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CastlingFiles {
+    /// The file the king starts on
+    pub king: File,
+
+    /// The file the kingside rook starts on
+    pub kingside_rook: File,
+
+    /// The file the queenside rook starts on
+    pub queenside_rook: File,
+}
+
+impl CastlingFiles {
+    /// The files of standard chess, with the king on e and the rooks on a and h
+    pub const STANDARD: Self = Self {
+        king: File(4),
+        kingside_rook: File(7),
+        queenside_rook: File(0),
+    };
 }
 
 impl Board {
     /// Creates a new chess board with the standard starting position
     pub fn new_init() -> Self {
-        let mut pieces = [[None; 8]; 8];
-
-        pieces[0][0] = Some(Piece::WHITE_ROOK);
-        pieces[0][1] = Some(Piece::WHITE_KNIGHT);
-        pieces[0][2] = Some(Piece::WHITE_BISHOP);
-        pieces[0][3] = Some(Piece::WHITE_QUEEN);
-        pieces[0][4] = Some(Piece::WHITE_KING);
-        pieces[0][5] = Some(Piece::WHITE_BISHOP);
-        pieces[0][6] = Some(Piece::WHITE_KNIGHT);
-        pieces[0][7] = Some(Piece::WHITE_ROOK);
-
-        pieces[7][0] = Some(Piece::BLACK_ROOK);
-        pieces[7][1] = Some(Piece::BLACK_KNIGHT);
-        pieces[7][2] = Some(Piece::BLACK_BISHOP);
-        pieces[7][3] = Some(Piece::BLACK_QUEEN);
-        pieces[7][4] = Some(Piece::BLACK_KING);
-        pieces[7][5] = Some(Piece::BLACK_BISHOP);
-        pieces[7][6] = Some(Piece::BLACK_KNIGHT);
-        pieces[7][7] = Some(Piece::BLACK_ROOK);
-
-        for f in 0..8 {
-            pieces[1][f] = Some(Piece::WHITE_PAWN);
-            pieces[6][f] = Some(Piece::BLACK_PAWN);
+        let mut board = Self::new_empty();
+
+        board.pieces[0][0] = Some(Piece::WHITE_ROOK);
+        board.pieces[0][1] = Some(Piece::WHITE_KNIGHT);
+        board.pieces[0][2] = Some(Piece::WHITE_BISHOP);
+        board.pieces[0][3] = Some(Piece::WHITE_QUEEN);
+        board.pieces[0][4] = Some(Piece::WHITE_KING);
+        board.pieces[0][5] = Some(Piece::WHITE_BISHOP);
+        board.pieces[0][6] = Some(Piece::WHITE_KNIGHT);
+        board.pieces[0][7] = Some(Piece::WHITE_ROOK);
+
+        board.pieces[7][0] = Some(Piece::BLACK_ROOK);
+        board.pieces[7][1] = Some(Piece::BLACK_KNIGHT);
+        board.pieces[7][2] = Some(Piece::BLACK_BISHOP);
+        board.pieces[7][3] = Some(Piece::BLACK_QUEEN);
+        board.pieces[7][4] = Some(Piece::BLACK_KING);
+        board.pieces[7][5] = Some(Piece::BLACK_BISHOP);
+        board.pieces[7][6] = Some(Piece::BLACK_KNIGHT);
+        board.pieces[7][7] = Some(Piece::BLACK_ROOK);
+
+        board.pieces[1] = [Some(Piece::WHITE_PAWN); 8];
+        board.pieces[6] = [Some(Piece::BLACK_PAWN); 8];
+
+        board.castling = CastlingRights::ALL;
+        board
+    }
+
+    /// Creates a new chess board with no pieces, white to move and no castling rights
+    pub fn new_empty() -> Self {
+        Self {
+            pieces: [[None; 8]; 8],
+            to_move: PieceColor::White,
+            castling: CastlingRights::NONE,
+            castling_files: CastlingFiles::STANDARD,
+            en_passant: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            history: Vec::new(),
         }
+    }
+
+    /// Returns the color of the player to move
+    #[inline]
+    pub fn to_move(&self) -> PieceColor {
+        self.to_move
+    }
+
+    /// Returns the remaining castling rights
+    #[inline]
+    pub fn castling_rights(&self) -> CastlingRights {
+        self.castling
+    }
+
+    /// Returns the files the king and rooks castle from
+    #[inline]
+    pub fn castling_files(&self) -> CastlingFiles {
+        self.castling_files
+    }
+
+    /// Returns the square a pawn may capture onto en passant, if the last move was a double push
+    #[inline]
+    pub fn en_passant(&self) -> Option<Square> {
+        self.en_passant
+    }
+
+    /// Returns the number of halfmoves since the last capture or pawn move
+    #[inline]
+    pub fn halfmove_clock(&self) -> u16 {
+        self.halfmove_clock
+    }
+
+    /// Returns the fullmove number, starting at 1 and incremented after black moves
+    #[inline]
+    pub fn fullmove_number(&self) -> u16 {
+        self.fullmove_number
+    }
 
-        Self { pieces }
+    /// Sets the player to move
+    #[inline]
+    pub fn set_to_move(&mut self, color: PieceColor) {
+        self.to_move = color;
+    }
+
+    /// Sets the remaining castling rights
+    #[inline]
+    pub fn set_castling_rights(&mut self, castling: CastlingRights) {
+        self.castling = castling;
+    }
+
+    /// Sets the square a pawn may capture onto en passant
+    #[inline]
+    pub fn set_en_passant(&mut self, square: Option<Square>) {
+        self.en_passant = square;
     }
 
     /// Returns the piece at the given square
@@ -70,12 +245,47 @@ impl Board {
         self.pieces[square.rank.0 as usize][square.file.0 as usize].take()
     }
 
+    /// Iterates through every occupied square and the piece on it
+    pub fn pieces(&self) -> impl Iterator<Item = (Square, Piece)> + '_ {
+        Square::all().filter_map(|square| self.get_piece(square).map(|piece| (square, piece)))
+    }
+
+    /// Returns the total value of the given player's pieces, in pawns
+    pub fn material(&self, color: PieceColor) -> u32 {
+        self.pieces()
+            .filter(|(_, piece)| piece.color == color)
+            .map(|(_, piece)| piece.kind.value())
+            .sum()
+    }
+
     /// Plays the given move
     pub fn play_move(&mut self, r#move: Move) {
+        let moving = self.get_piece(r#move.from()).expect("illegal move");
+
+        self.history.push(Irreversible {
+            castling: self.castling,
+            en_passant: self.en_passant,
+            halfmove_clock: self.halfmove_clock,
+        });
+
+        self.castling.update(r#move.from(), self.castling_files);
+        self.castling.update(r#move.to(), self.castling_files);
+        self.en_passant = None;
+        self.halfmove_clock += 1;
+        if moving.is_pawn() || r#move.captured().is_some() {
+            self.halfmove_clock = 0;
+        }
+
         match r#move {
             Move::Move { from, to, .. } => {
                 let piece = self.take_piece(from).expect("illegal move");
                 self.set_piece(to, piece);
+                if piece.is_pawn() && from.rank.0.abs_diff(to.rank.0) == 2 {
+                    self.en_passant = Some(Square::from_indices(
+                        from.file.0,
+                        (from.rank.0 + to.rank.0) / 2,
+                    ));
+                }
             }
             Move::Capture { from, to, .. } => {
                 let piece = self.take_piece(from).expect("illegal capture");
@@ -100,51 +310,37 @@ impl Board {
                 self.remove_piece(from);
                 self.set_piece(to, piece);
             }
-            Move::KingsideCastleWhite => {
-                let king = self
-                    .take_piece("e1")
-                    .expect("illegal white kingside castle");
-                let rook = self
-                    .take_piece("h1")
-                    .expect("illegal white kingside castle");
-                self.set_piece("g1", king);
-                self.set_piece("f1", rook);
-            }
-            Move::QueensideCastleWhite => {
-                let king = self
-                    .take_piece("e1")
-                    .expect("illegal white queenside castle");
-                let rook = self
-                    .take_piece("a1")
-                    .expect("illegal white queenside castle");
-                self.set_piece("c1", king);
-                self.set_piece("d1", rook);
-            }
-            Move::KingsideCastleBlack => {
-                let king = self
-                    .take_piece("e8")
-                    .expect("illegal black kingside castle");
-                let rook = self
-                    .take_piece("h8")
-                    .expect("illegal black kingside castle");
-                self.set_piece("g8", king);
-                self.set_piece("f8", rook);
-            }
-            Move::QueensideCastleBlack => {
-                let king = self
-                    .take_piece("e8")
-                    .expect("illegal black queenside castle");
-                let rook = self
-                    .take_piece("a8")
-                    .expect("illegal black queenside castle");
-                self.set_piece("c8", king);
-                self.set_piece("d8", rook);
+            Move::KingsideCastleWhite { .. }
+            | Move::QueensideCastleWhite { .. }
+            | Move::KingsideCastleBlack { .. }
+            | Move::QueensideCastleBlack { .. } => {
+                // in Chess960 the king and rook may land on each other's squares, so both are
+                // lifted before either is placed
+                let (rook_from, rook_to) = r#move.castling_rook().expect("castles move a rook");
+                let king = self.take_piece(r#move.from()).expect("illegal castle");
+                let rook = self.take_piece(rook_from).expect("illegal castle");
+                self.set_piece(r#move.to(), king);
+                self.set_piece(rook_to, rook);
             }
         }
+
+        if self.to_move == PieceColor::Black {
+            self.fullmove_number += 1;
+        }
+        self.to_move = self.to_move.opposite();
     }
 
     /// Undoes the given move
     pub fn undo_move(&mut self, r#move: Move) {
+        let state = self.history.pop().expect("no move to undo");
+        self.castling = state.castling;
+        self.en_passant = state.en_passant;
+        self.halfmove_clock = state.halfmove_clock;
+        self.to_move = self.to_move.opposite();
+        if self.to_move == PieceColor::Black {
+            self.fullmove_number -= 1;
+        }
+
         match r#move {
             Move::Move { from, to, .. } => {
                 let piece = self.take_piece(to).expect("illegal move");
@@ -186,10 +382,16 @@ impl Board {
                 self.set_piece(to, captures);
                 self.set_piece(from, Piece::new(PieceKind::Pawn, piece.color));
             }
-            Move::KingsideCastleWhite => todo!(),
-            Move::QueensideCastleWhite => todo!(),
-            Move::KingsideCastleBlack => todo!(),
-            Move::QueensideCastleBlack => todo!(),
+            Move::KingsideCastleWhite { .. }
+            | Move::QueensideCastleWhite { .. }
+            | Move::KingsideCastleBlack { .. }
+            | Move::QueensideCastleBlack { .. } => {
+                let (rook_from, rook_to) = r#move.castling_rook().expect("castles move a rook");
+                let king = self.take_piece(r#move.to()).expect("illegal move");
+                let rook = self.take_piece(rook_to).expect("illegal move");
+                self.set_piece(r#move.from(), king);
+                self.set_piece(rook_from, rook);
+            }
         }
     }
 }
@@ -201,7 +403,7 @@ impl Default for Board {
 }
 
 /// A rank on the chess board, in the range (1..=8)
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct Rank(u8);
 
 impl Rank {
@@ -214,7 +416,7 @@ impl Rank {
 
     /// Iterates through every rank from 1 to 8
     pub fn for_each(mut action: impl FnMut(Self)) {
-        for rank in 1..8 {
+        for rank in 1..=8 {
             action(Self::new(rank));
         }
     }
@@ -247,8 +449,8 @@ impl From<&str> for Rank {
     }
 }
 
-/// A file on the chess board, in the range ('a'..='f')
-#[derive(Clone, Copy, Debug)]
+/// A file on the chess board, in the range ('a'..='h')
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct File(u8);
 
 impl File {
@@ -259,9 +461,9 @@ impl File {
         Self((file as u32 - 'a' as u32) as u8)
     }
 
-    /// Iterates through every file from 'a' to 'f'
+    /// Iterates through every file from 'a' to 'h'
     pub fn for_each(mut action: impl FnMut(Self)) {
-        for file in 'a'..'h' {
+        for file in 'a'..='h' {
             action(Self::new(file));
         }
     }
@@ -297,7 +499,7 @@ impl TryFrom<&str> for File {
 }
 
 /// A square on the chess board, i.e. "f3"
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct Square {
     /// The file associated with this square
     pub file: File,
@@ -319,10 +521,38 @@ impl Square {
         }
     }
 
-    /// Returns the indices associated with this square (rank and file)
+    /// Constructs a new square from the given file and rank indices, each in the range (0..8)
+    pub const fn from_indices(file: u8, rank: u8) -> Self {
+        assert!(file < 8 && rank < 8, "illegal square");
+        Self {
+            file: File(file),
+            rank: Rank(rank),
+        }
+    }
+
+    /// Returns the indices associated with this square (file and rank)
     pub fn indices(&self) -> (u8, u8) {
         (self.file.index(), self.rank.index())
     }
+
+    /// Iterates through every square, from a1 to h8 one rank at a time
+    pub fn all() -> impl Iterator<Item = Self> {
+        (0..8).flat_map(|rank| (0..8).map(move |file| Self::from_indices(file, rank)))
+    }
+
+    /// Returns the square offset from this one by the given number of files and ranks, if it is
+    /// on the board
+    pub fn offset(&self, files: i8, ranks: i8) -> Option<Self> {
+        let file = self.file.0 as i8 + files;
+        let rank = self.rank.0 as i8 + ranks;
+        ((0..8).contains(&file) && (0..8).contains(&rank))
+            .then(|| Self::from_indices(file as u8, rank as u8))
+    }
+
+    /// Returns true if this is a light square
+    pub fn is_light(&self) -> bool {
+        (self.file.index() + self.rank.index()) % 2 == 1
+    }
 }
 
 impl Display for Square {
@@ -331,6 +561,21 @@ impl Display for Square {
     }
 }
 
+impl FromStr for Square {
+    type Err = &'static str;
+
+    /// Parses a square such as "f3", returning an error instead of panicking on bad input
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
+        match (chars.next(), chars.next(), chars.next()) {
+            (Some(file @ 'a'..='h'), Some(rank @ '1'..='8'), None) => {
+                Ok(Self::from_indices(file as u8 - b'a', rank as u8 - b'1'))
+            }
+            _ => Err("invalid square"),
+        }
+    }
+}
+
 impl From<&str> for Square {
     fn from(value: &str) -> Self {
         assert!(value.len() == 2, "input string must be of length 2");
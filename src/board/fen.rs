@@ -0,0 +1,302 @@
+use crate::piece::{Piece, PieceColor, PieceKind};
+
+use super::{Board, CastlingFiles, CastlingRights, File};
+
+/// The files the knights are placed on among the five left after the bishops and queen, by the
+/// knights' part of a Chess960 position number
+const CHESS960_KNIGHTS: [(usize, usize); 10] = [
+    (0, 1),
+    (0, 2),
+    (0, 3),
+    (0, 4),
+    (1, 2),
+    (1, 3),
+    (1, 4),
+    (2, 3),
+    (2, 4),
+    (3, 4),
+];
+
+impl Board {
+    /// The standard starting position in Forsyth-Edwards Notation
+    pub const STARTING_FEN: &'static str =
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    /// Creates a chess board from a position in Forsyth-Edwards Notation
+    ///
+    /// Castling rights may be given as "KQkq" or, for Chess960, as the files of the castling
+    /// rooks, i.e. "HAha". The move counters may be left out.
+    pub fn from_fen(fen: &str) -> Result<Self, &'static str> {
+        let mut fields = fen.split_whitespace();
+        let mut board = Self::new_empty();
+
+        let placement = fields.next().ok_or("empty FEN")?;
+        let ranks: Vec<_> = placement.split('/').collect();
+        if ranks.len() != 8 {
+            return Err("FEN must describe 8 ranks");
+        }
+        for (index, text) in ranks.into_iter().enumerate() {
+            let rank = 7 - index;
+            let mut file = 0;
+            for c in text.chars() {
+                match c.to_digit(10) {
+                    Some(empty @ 1..=8) => file += empty as usize,
+                    Some(_) => return Err("invalid number of empty squares in FEN"),
+                    None => {
+                        let kind = PieceKind::from_letter(c).ok_or("invalid piece in FEN")?;
+                        let color = match c.is_ascii_uppercase() {
+                            true => PieceColor::White,
+                            false => PieceColor::Black,
+                        };
+                        if file >= 8 {
+                            return Err("too many squares in a FEN rank");
+                        }
+                        board.pieces[rank][file] = Some(Piece::new(kind, color));
+                        file += 1;
+                    }
+                }
+            }
+            if file != 8 {
+                return Err("FEN ranks must have 8 squares");
+            }
+        }
+
+        board.to_move = match fields.next() {
+            Some("w") | None => PieceColor::White,
+            Some("b") => PieceColor::Black,
+            Some(_) => return Err("invalid side to move in FEN"),
+        };
+
+        if let Some(castling) = fields.next() {
+            board.set_castling_from_fen(castling)?;
+        }
+
+        board.en_passant = match fields.next() {
+            Some("-") | None => None,
+            Some(square) => Some(square.parse().map_err(|_| "invalid en passant square")?),
+        };
+
+        if let Some(halfmove_clock) = fields.next() {
+            board.halfmove_clock = halfmove_clock
+                .parse()
+                .map_err(|_| "invalid halfmove clock")?;
+        }
+        if let Some(fullmove_number) = fields.next() {
+            board.fullmove_number = fullmove_number
+                .parse()
+                .map_err(|_| "invalid fullmove number")?;
+        }
+
+        Ok(board)
+    }
+
+    /// Creates the Chess960 starting position with the given number from 0 to 959, using
+    /// Scharnagl's numbering, in which 518 is the standard starting position
+    pub fn chess960(number: u16) -> Self {
+        assert!(number < 960, "illegal Chess960 position number");
+        let mut number = number as usize;
+        let mut back_rank = [None; 8];
+
+        back_rank[number % 4 * 2 + 1] = Some(PieceKind::Bishop);
+        number /= 4;
+        back_rank[number % 4 * 2] = Some(PieceKind::Bishop);
+        number /= 4;
+
+        let empty = |back_rank: &[Option<PieceKind>; 8]| -> Vec<usize> {
+            (0..8).filter(|&file| back_rank[file].is_none()).collect()
+        };
+        back_rank[empty(&back_rank)[number % 6]] = Some(PieceKind::Queen);
+        number /= 6;
+
+        let (first, second) = CHESS960_KNIGHTS[number];
+        let files = empty(&back_rank);
+        back_rank[files[first]] = Some(PieceKind::Knight);
+        back_rank[files[second]] = Some(PieceKind::Knight);
+
+        // the king always stands between the two rooks
+        let files = empty(&back_rank);
+        back_rank[files[0]] = Some(PieceKind::Rook);
+        back_rank[files[1]] = Some(PieceKind::King);
+        back_rank[files[2]] = Some(PieceKind::Rook);
+
+        let mut board = Self::new_empty();
+        for (file, kind) in back_rank.into_iter().enumerate() {
+            let kind = kind.expect("every back rank square is filled");
+            board.pieces[0][file] = Some(Piece::new(kind, PieceColor::White));
+            board.pieces[7][file] = Some(Piece::new(kind, PieceColor::Black));
+        }
+        board.pieces[1] = [Some(Piece::WHITE_PAWN); 8];
+        board.pieces[6] = [Some(Piece::BLACK_PAWN); 8];
+
+        board.castling = CastlingRights::ALL;
+        board.castling_files = CastlingFiles {
+            king: File(files[1] as u8),
+            kingside_rook: File(files[2] as u8),
+            queenside_rook: File(files[0] as u8),
+        };
+        board
+    }
+
+    /// Returns this position in Forsyth-Edwards Notation
+    pub fn fen(&self) -> String {
+        let mut fen = String::new();
+        for rank in (0..8).rev() {
+            let mut empty = 0;
+            for file in 0..8 {
+                match self.pieces[rank][file] {
+                    Some(piece) => {
+                        if empty > 0 {
+                            fen.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        let letter = piece.kind.letter();
+                        fen.push(match piece.color {
+                            PieceColor::White => letter,
+                            PieceColor::Black => letter.to_ascii_lowercase(),
+                        });
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                fen.push_str(&empty.to_string());
+            }
+            if rank > 0 {
+                fen.push('/');
+            }
+        }
+
+        fen.push_str(match self.to_move {
+            PieceColor::White => " w ",
+            PieceColor::Black => " b ",
+        });
+        fen.push_str(&self.castling_fen());
+
+        match self.en_passant {
+            Some(square) => fen.push_str(&format!(" {square}")),
+            None => fen.push_str(" -"),
+        }
+        fen.push_str(&format!(
+            " {} {}",
+            self.halfmove_clock, self.fullmove_number
+        ));
+        fen
+    }
+
+    /// Returns true if the king or rooks castle from files other than those of standard chess
+    pub fn is_chess960(&self) -> bool {
+        self.castling_files != CastlingFiles::STANDARD
+    }
+
+    /// Returns the castling rights field of the FEN, using rook files for Chess960
+    fn castling_fen(&self) -> String {
+        let rights = [
+            (
+                self.castling.white_kingside,
+                'K',
+                self.castling_files.kingside_rook,
+            ),
+            (
+                self.castling.white_queenside,
+                'Q',
+                self.castling_files.queenside_rook,
+            ),
+            (
+                self.castling.black_kingside,
+                'k',
+                self.castling_files.kingside_rook,
+            ),
+            (
+                self.castling.black_queenside,
+                'q',
+                self.castling_files.queenside_rook,
+            ),
+        ];
+
+        let chess960 = self.is_chess960();
+        let castling: String = rights
+            .into_iter()
+            .filter(|(allowed, ..)| *allowed)
+            .map(|(_, letter, rook)| match chess960 {
+                true => {
+                    let file = rook.to_string().chars().next().expect("files are a letter");
+                    match letter.is_ascii_uppercase() {
+                        true => file.to_ascii_uppercase(),
+                        false => file,
+                    }
+                }
+                false => letter,
+            })
+            .collect();
+
+        match castling.is_empty() {
+            true => String::from("-"),
+            false => castling,
+        }
+    }
+
+    /// Sets the castling rights and files from the castling field of a FEN
+    fn set_castling_from_fen(&mut self, castling: &str) -> Result<(), &'static str> {
+        self.castling = CastlingRights::NONE;
+        if castling == "-" {
+            return Ok(());
+        }
+
+        let mut king = None;
+        let mut kingside_rook = None;
+        let mut queenside_rook = None;
+        for c in castling.chars() {
+            let (color, rank) = match c.is_ascii_uppercase() {
+                true => (PieceColor::White, 0),
+                false => (PieceColor::Black, 7),
+            };
+            let king_file = (0..8)
+                .find(|&file| self.pieces[rank][file] == Some(Piece::new(PieceKind::King, color)))
+                .ok_or("castling rights need a king on the back rank")?;
+            let is_rook =
+                |file: &usize| self.pieces[rank][*file] == Some(Piece::new(PieceKind::Rook, color));
+
+            // "K" and "Q" mean the outermost rook on that side
+            let rook_file = match c.to_ascii_lowercase() {
+                'k' => (king_file + 1..8).rev().find(is_rook),
+                'q' => (0..king_file).find(is_rook),
+                file @ 'a'..='h' => Some(file as usize - 'a' as usize).filter(is_rook),
+                _ => return Err("invalid castling rights in FEN"),
+            }
+            .ok_or("castling rights need a rook on the back rank")?;
+
+            let kingside = rook_file > king_file;
+            let (rook, right) = match (kingside, color) {
+                (true, PieceColor::White) => {
+                    (&mut kingside_rook, &mut self.castling.white_kingside)
+                }
+                (false, PieceColor::White) => {
+                    (&mut queenside_rook, &mut self.castling.white_queenside)
+                }
+                (true, PieceColor::Black) => {
+                    (&mut kingside_rook, &mut self.castling.black_kingside)
+                }
+                (false, PieceColor::Black) => {
+                    (&mut queenside_rook, &mut self.castling.black_queenside)
+                }
+            };
+            *right = true;
+
+            // both players castle from the same files, as in every Chess960 starting position
+            for (known, file) in [(&mut king, king_file), (rook, rook_file)] {
+                if known.is_some_and(|known| known != file) {
+                    return Err("both players must castle from the same files");
+                }
+                *known = Some(file);
+            }
+        }
+
+        let standard = CastlingFiles::STANDARD;
+        self.castling_files = CastlingFiles {
+            king: king.map_or(standard.king, |file| File(file as u8)),
+            kingside_rook: kingside_rook.map_or(standard.kingside_rook, |file| File(file as u8)),
+            queenside_rook: queenside_rook.map_or(standard.queenside_rook, |file| File(file as u8)),
+        };
+        Ok(())
+    }
+}
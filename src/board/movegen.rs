@@ -0,0 +1,599 @@
+use crate::{
+    piece::{Piece, PieceColor, PieceKind},
+    r#move::Move,
+};
+
+use super::{Board, Square};
+
+/// The offsets a knight can jump by, in files and ranks
+const KNIGHT_OFFSETS: [(i8, i8); 8] = [
+    (1, 2),
+    (2, 1),
+    (2, -1),
+    (1, -2),
+    (-1, -2),
+    (-2, -1),
+    (-2, 1),
+    (-1, 2),
+];
+
+/// The offsets a king can step by, in files and ranks
+const KING_OFFSETS: [(i8, i8); 8] = [
+    (0, 1),
+    (1, 1),
+    (1, 0),
+    (1, -1),
+    (0, -1),
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+];
+
+/// The directions a bishop slides in
+const BISHOP_DIRECTIONS: [(i8, i8); 4] = [(1, 1), (1, -1), (-1, -1), (-1, 1)];
+
+/// The directions a rook slides in
+const ROOK_DIRECTIONS: [(i8, i8); 4] = [(0, 1), (1, 0), (0, -1), (-1, 0)];
+
+/// The pieces a pawn may promote to, in the order they are usually offered
+pub const PROMOTION_KINDS: [PieceKind; 4] = [
+    PieceKind::Queen,
+    PieceKind::Knight,
+    PieceKind::Rook,
+    PieceKind::Bishop,
+];
+
+impl Board {
+    /// Returns every legal move for the player to move
+    pub fn legal_moves(&self) -> Vec<Move> {
+        let color = self.to_move;
+        let mut board = self.clone();
+        self.pseudo_legal_moves()
+            .into_iter()
+            .filter(|&r#move| {
+                board.play_move(r#move);
+                let legal = !board.is_in_check(color);
+                board.undo_move(r#move);
+                legal
+            })
+            .collect()
+    }
+
+    /// Returns every legal move of the piece on the given square
+    pub fn legal_moves_from(&self, square: Square) -> Vec<Move> {
+        self.legal_moves()
+            .into_iter()
+            .filter(|r#move| r#move.from() == square)
+            .collect()
+    }
+
+    /// Returns the squares the piece on the given square could move to in some later position,
+    /// whoever's turn it is, for queueing premoves
+    ///
+    /// Other pieces are ignored, so sliding pieces reach the edge of the board and pawns may
+    /// capture diagonally. A king with castling rights may also move to its castling squares.
+    pub fn premove_targets(&self, from: Square) -> Vec<Square> {
+        let Some(piece) = self.get_piece(from) else {
+            return Vec::new();
+        };
+
+        let mut targets = Vec::new();
+        let mut reach = |offsets: &[(i8, i8)], slides: bool| {
+            for &(files, ranks) in offsets {
+                let mut current = from;
+                while let Some(to) = current.offset(files, ranks) {
+                    targets.push(to);
+                    if !slides {
+                        break;
+                    }
+                    current = to;
+                }
+            }
+        };
+        match piece.kind {
+            PieceKind::Pawn => {
+                let (forward, start_rank) = match piece.color {
+                    PieceColor::White => (1, 1),
+                    PieceColor::Black => (-1, 6),
+                };
+                reach(&[(-1, forward), (0, forward), (1, forward)], false);
+                if from.rank.index() == start_rank {
+                    reach(&[(0, forward * 2)], false);
+                }
+            }
+            PieceKind::Knight => reach(&KNIGHT_OFFSETS, false),
+            PieceKind::Bishop => reach(&BISHOP_DIRECTIONS, true),
+            PieceKind::Rook => reach(&ROOK_DIRECTIONS, true),
+            PieceKind::Queen => {
+                reach(&BISHOP_DIRECTIONS, true);
+                reach(&ROOK_DIRECTIONS, true);
+            }
+            PieceKind::King => {
+                reach(&KING_OFFSETS, false);
+                let (kingside, queenside) = match piece.color {
+                    PieceColor::White => {
+                        (self.castling.white_kingside, self.castling.white_queenside)
+                    }
+                    PieceColor::Black => {
+                        (self.castling.black_kingside, self.castling.black_queenside)
+                    }
+                };
+                let castles = [
+                    (
+                        kingside,
+                        Move::kingside_castle(piece.color, self.castling_files),
+                    ),
+                    (
+                        queenside,
+                        Move::queenside_castle(piece.color, self.castling_files),
+                    ),
+                ];
+                for (allowed, castle) in castles {
+                    if allowed && castle.from() == from && !targets.contains(&castle.to()) {
+                        targets.push(castle.to());
+                    }
+                }
+            }
+        }
+        targets
+    }
+
+    /// Checks that this position could be reached in a game, explaining why not if it could not
+    ///
+    /// Each side needs exactly one king, no pawns may stand on the first or last rank, and the
+    /// side not to move may not be in check. Castling rights need the king and rook on their
+    /// starting squares, and an en passant square needs the pawn that just moved past it.
+    pub fn validate(&self) -> Result<(), &'static str> {
+        for color in [PieceColor::White, PieceColor::Black] {
+            let kings = self
+                .pieces()
+                .filter(|(_, piece)| *piece == Piece::new(PieceKind::King, color))
+                .count();
+            if kings != 1 {
+                return Err("each side needs exactly one king");
+            }
+        }
+        if self.pieces().any(|(square, piece)| {
+            piece.kind == PieceKind::Pawn && [0, 7].contains(&square.rank.index())
+        }) {
+            return Err("pawns cannot stand on the first or last rank");
+        }
+        if self.is_in_check(self.to_move.opposite()) {
+            return Err("the side not to move is in check");
+        }
+
+        let rights = [
+            (self.castling.white_kingside, PieceColor::White, true),
+            (self.castling.white_queenside, PieceColor::White, false),
+            (self.castling.black_kingside, PieceColor::Black, true),
+            (self.castling.black_queenside, PieceColor::Black, false),
+        ];
+        for (allowed, color, kingside) in rights {
+            let castle = match kingside {
+                true => Move::kingside_castle(color, self.castling_files),
+                false => Move::queenside_castle(color, self.castling_files),
+            };
+            let (rook_from, _) = castle.castling_rook().expect("castles move a rook");
+            if allowed
+                && (self.get_piece(castle.from()) != Some(Piece::new(PieceKind::King, color))
+                    || self.get_piece(rook_from) != Some(Piece::new(PieceKind::Rook, color)))
+            {
+                return Err("castling rights need the king and rook on their starting squares");
+            }
+        }
+
+        if let Some(square) = self.en_passant {
+            // the pawn that just moved stands in front of the square, having come from behind it
+            let (rank, forward) = match self.to_move {
+                PieceColor::White => (5, -1),
+                PieceColor::Black => (2, 1),
+            };
+            let pawn = Piece::new(PieceKind::Pawn, self.to_move.opposite());
+            let valid = square.rank.index() == rank
+                && self.get_piece(square).is_none()
+                && square.offset(0, forward).and_then(|s| self.get_piece(s)) == Some(pawn)
+                && square
+                    .offset(0, -forward)
+                    .is_some_and(|s| self.get_piece(s).is_none());
+            if !valid {
+                return Err("the en passant square does not follow a double pawn push");
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns true if the given move is legal in this position
+    pub fn is_legal(&self, r#move: Move) -> bool {
+        self.legal_moves().contains(&r#move)
+    }
+
+    /// Returns true if the player to move is checkmated
+    pub fn is_checkmate(&self) -> bool {
+        self.is_in_check(self.to_move) && self.legal_moves().is_empty()
+    }
+
+    /// Returns true if the player to move is stalemated
+    pub fn is_stalemate(&self) -> bool {
+        !self.is_in_check(self.to_move) && self.legal_moves().is_empty()
+    }
+
+    /// Returns true if the given color cannot possibly checkmate, having only a king and at most
+    /// one bishop or knight
+    pub fn has_insufficient_material(&self, color: PieceColor) -> bool {
+        let mut minors = 0;
+        for (_, piece) in self.pieces().filter(|(_, piece)| piece.color == color) {
+            match piece.kind {
+                PieceKind::King => {}
+                PieceKind::Bishop | PieceKind::Knight => minors += 1,
+                PieceKind::Pawn | PieceKind::Rook | PieceKind::Queen => return false,
+            }
+        }
+        minors <= 1
+    }
+
+    /// Returns the square of the given color's king, if it is on the board
+    pub fn king_square(&self, color: PieceColor) -> Option<Square> {
+        let king = Piece::new(PieceKind::King, color);
+        Square::all().find(|&square| self.get_piece(square) == Some(king))
+    }
+
+    /// Returns true if the given color's king is attacked
+    pub fn is_in_check(&self, color: PieceColor) -> bool {
+        self.king_square(color)
+            .is_some_and(|square| self.is_attacked(square, color.opposite()))
+    }
+
+    /// Returns true if any piece of the given color attacks the given square
+    pub fn is_attacked(&self, square: Square, by: PieceColor) -> bool {
+        let is = |offset: Option<Square>, kind: PieceKind| {
+            offset.and_then(|s| self.get_piece(s)) == Some(Piece::new(kind, by))
+        };
+
+        // pawns attack diagonally forward, so look diagonally backward from the target
+        let pawn_rank = match by {
+            PieceColor::White => -1,
+            PieceColor::Black => 1,
+        };
+        if is(square.offset(-1, pawn_rank), PieceKind::Pawn)
+            || is(square.offset(1, pawn_rank), PieceKind::Pawn)
+        {
+            return true;
+        }
+
+        if KNIGHT_OFFSETS
+            .iter()
+            .any(|&(f, r)| is(square.offset(f, r), PieceKind::Knight))
+        {
+            return true;
+        }
+
+        if KING_OFFSETS
+            .iter()
+            .any(|&(f, r)| is(square.offset(f, r), PieceKind::King))
+        {
+            return true;
+        }
+
+        let slider = |directions: &[(i8, i8)], kind: PieceKind| {
+            directions.iter().any(|&(f, r)| {
+                let mut current = square;
+                while let Some(next) = current.offset(f, r) {
+                    match self.get_piece(next) {
+                        Some(piece) => {
+                            return piece.color == by && (piece.kind == kind || piece.is_queen());
+                        }
+                        None => current = next,
+                    }
+                }
+                false
+            })
+        };
+
+        slider(&BISHOP_DIRECTIONS, PieceKind::Bishop) || slider(&ROOK_DIRECTIONS, PieceKind::Rook)
+    }
+
+    /// Returns every move for the player to move, including those that leave their king in check
+    ///
+    /// Castling is only generated when the king does not start in, pass through, or land on an
+    /// attacked square.
+    pub fn pseudo_legal_moves(&self) -> Vec<Move> {
+        let mut moves = Vec::with_capacity(48);
+        for (from, piece) in self.pieces() {
+            if piece.color != self.to_move {
+                continue;
+            }
+            match piece.kind {
+                PieceKind::Pawn => self.pawn_moves(from, piece.color, &mut moves),
+                PieceKind::Knight => self.step_moves(from, &KNIGHT_OFFSETS, &mut moves),
+                PieceKind::Bishop => self.slide_moves(from, &BISHOP_DIRECTIONS, &mut moves),
+                PieceKind::Rook => self.slide_moves(from, &ROOK_DIRECTIONS, &mut moves),
+                PieceKind::Queen => {
+                    self.slide_moves(from, &BISHOP_DIRECTIONS, &mut moves);
+                    self.slide_moves(from, &ROOK_DIRECTIONS, &mut moves);
+                }
+                PieceKind::King => {
+                    self.step_moves(from, &KING_OFFSETS, &mut moves);
+                    self.castling_moves(piece.color, &mut moves);
+                }
+            }
+        }
+        moves
+    }
+
+    /// Adds a move or capture from one square to another, if the destination is not occupied by a
+    /// friendly piece
+    fn push_move(&self, from: Square, to: Square, moves: &mut Vec<Move>) -> bool {
+        match self.get_piece(to) {
+            None => {
+                moves.push(Move::Move { from, to });
+                true
+            }
+            Some(capturing) if capturing.color != self.to_move => {
+                moves.push(Move::Capture {
+                    capturing,
+                    from,
+                    to,
+                });
+                false
+            }
+            Some(_) => false,
+        }
+    }
+
+    /// Adds the moves of a knight or king
+    fn step_moves(&self, from: Square, offsets: &[(i8, i8)], moves: &mut Vec<Move>) {
+        for &(f, r) in offsets {
+            if let Some(to) = from.offset(f, r) {
+                self.push_move(from, to, moves);
+            }
+        }
+    }
+
+    /// Adds the moves of a bishop, rook or queen
+    fn slide_moves(&self, from: Square, directions: &[(i8, i8)], moves: &mut Vec<Move>) {
+        for &(f, r) in directions {
+            let mut current = from;
+            while let Some(to) = current.offset(f, r) {
+                if !self.push_move(from, to, moves) {
+                    break;
+                }
+                current = to;
+            }
+        }
+    }
+
+    /// Adds the moves of a pawn, including promotions and en passant captures
+    fn pawn_moves(&self, from: Square, color: PieceColor, moves: &mut Vec<Move>) {
+        let (forward, start_rank, last_rank) = match color {
+            PieceColor::White => (1, 1, 7),
+            PieceColor::Black => (-1, 6, 0),
+        };
+
+        let push = |to: Square, capturing: Option<Piece>, moves: &mut Vec<Move>| {
+            if to.rank.index() == last_rank {
+                for kind in PROMOTION_KINDS {
+                    let promoting = Piece::new(kind, color);
+                    moves.push(match capturing {
+                        Some(capturing) => Move::PromotionCapture {
+                            promoting,
+                            capturing,
+                            from,
+                            to,
+                        },
+                        None => Move::PromotionMove {
+                            promoting,
+                            from,
+                            to,
+                        },
+                    });
+                }
+            } else {
+                moves.push(match capturing {
+                    Some(capturing) => Move::Capture {
+                        capturing,
+                        from,
+                        to,
+                    },
+                    None => Move::Move { from, to },
+                });
+            }
+        };
+
+        if let Some(to) = from.offset(0, forward) {
+            if self.get_piece(to).is_none() {
+                push(to, None, moves);
+                if from.rank.index() == start_rank {
+                    let to = to
+                        .offset(0, forward)
+                        .expect("double push stays on the board");
+                    if self.get_piece(to).is_none() {
+                        moves.push(Move::Move { from, to });
+                    }
+                }
+            }
+        }
+
+        for side in [-1, 1] {
+            let Some(to) = from.offset(side, forward) else {
+                continue;
+            };
+            match self.get_piece(to) {
+                Some(capturing) if capturing.color != color => push(to, Some(capturing), moves),
+                None if self.en_passant == Some(to) => moves.push(Move::EnPassantCapture {
+                    capturing: Piece::new(PieceKind::Pawn, color.opposite()),
+                    from,
+                    to,
+                }),
+                _ => {}
+            }
+        }
+    }
+
+    /// Adds the castling moves of the given color
+    ///
+    /// Every square between the king and rook and their destinations must be empty, apart from
+    /// the king and rook themselves, and the king may not castle out of, through, or into check.
+    fn castling_moves(&self, color: PieceColor, moves: &mut Vec<Move>) {
+        let (kingside, queenside) = match color {
+            PieceColor::White => (self.castling.white_kingside, self.castling.white_queenside),
+            PieceColor::Black => (self.castling.black_kingside, self.castling.black_queenside),
+        };
+        let king = Piece::new(PieceKind::King, color);
+        let rook = Piece::new(PieceKind::Rook, color);
+
+        let castles = [
+            (kingside, Move::kingside_castle(color, self.castling_files)),
+            (
+                queenside,
+                Move::queenside_castle(color, self.castling_files),
+            ),
+        ];
+        for (allowed, castle) in castles {
+            let (king_from, king_to) = (castle.from(), castle.to());
+            let (rook_from, rook_to) = castle.castling_rook().expect("castles move a rook");
+            if !allowed
+                || self.get_piece(king_from) != Some(king)
+                || self.get_piece(rook_from) != Some(rook)
+            {
+                continue;
+            }
+
+            let rank = king_from.rank;
+            let files = |a: Square, b: Square| {
+                let (a, b) = (a.file.index(), b.file.index());
+                a.min(b)..=a.max(b)
+            };
+            let involved = [king_from, king_to, rook_from, rook_to].map(|s| s.file.index());
+            let low = involved.into_iter().min().expect("four files");
+            let high = involved.into_iter().max().expect("four files");
+            let empty = (low..=high)
+                .map(|file| Square::from_indices(file, rank.index()))
+                .all(|square| {
+                    square == king_from || square == rook_from || self.get_piece(square).is_none()
+                });
+            let safe = files(king_from, king_to)
+                .map(|file| Square::from_indices(file, rank.index()))
+                .all(|square| !self.is_attacked(square, color.opposite()));
+
+            if empty && safe {
+                moves.push(castle);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::CastlingRights;
+
+    /// Counts the positions reached by every sequence of legal moves of the given length
+    fn perft(board: &mut Board, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        let mut nodes = 0;
+        for r#move in board.legal_moves() {
+            board.play_move(r#move);
+            nodes += perft(board, depth - 1);
+            board.undo_move(r#move);
+        }
+        nodes
+    }
+
+    /// Checks the node counts of the given position at each depth from one on
+    fn check(fen: &str, counts: &[u64]) {
+        let mut board = Board::from_fen(fen).unwrap();
+        for (depth, &count) in (1..).zip(counts) {
+            assert_eq!(perft(&mut board, depth), count, "{fen} at depth {depth}");
+        }
+        assert_eq!(board.fen(), Board::from_fen(fen).unwrap().fen());
+    }
+
+    #[test]
+    fn validation() {
+        let validate = |fen: &str| Board::from_fen(fen).and_then(|board| board.validate());
+        assert_eq!(validate(Board::STARTING_FEN), Ok(()));
+        assert_eq!(
+            validate("rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 3"),
+            Ok(())
+        );
+
+        let kings = Err("each side needs exactly one king");
+        assert_eq!(validate("8/8/8/8/8/8/8/4K3 w - - 0 1"), kings);
+        assert_eq!(validate("4k3/8/8/8/8/8/8/3KK3 w - - 0 1"), kings);
+
+        let pawns = Err("pawns cannot stand on the first or last rank");
+        assert_eq!(validate("P3k3/8/8/8/8/8/8/4K3 w - - 0 1"), pawns);
+        assert_eq!(validate("4k3/8/8/8/8/8/8/p3K3 w - - 0 1"), pawns);
+
+        assert_eq!(validate("4k3/8/8/8/8/8/8/4K2R w - - 0 1"), Ok(()));
+        assert_eq!(
+            validate("4k3/8/8/8/8/8/8/4R1K1 w - - 0 1"),
+            Err("the side not to move is in check")
+        );
+
+        // the board editor can move the pieces away from castling rights, which FEN cannot
+        let castling = Err("castling rights need the king and rook on their starting squares");
+        let rights = || Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        let mut rookless = rights();
+        rookless.remove_piece(Square::from_indices(7, 0));
+        assert_eq!(rookless.validate(), castling);
+        let mut king_moved = rights();
+        let king = king_moved.take_piece(Square::from_indices(4, 7)).unwrap();
+        king_moved.set_piece(Square::from_indices(3, 7), king);
+        assert_eq!(king_moved.validate(), castling);
+        let mut rights = rights();
+        rights.set_castling_rights(CastlingRights {
+            white_kingside: false,
+            ..rights.castling_rights()
+        });
+        rights.remove_piece(Square::from_indices(7, 0));
+        assert_eq!(rights.validate(), Ok(()));
+
+        let en_passant = Err("the en passant square does not follow a double pawn push");
+        // no pawn stands in front of the square
+        assert_eq!(validate("4k3/8/8/8/8/8/8/4K3 b - e3 0 1"), en_passant);
+        // the square is on the wrong rank for the side to move
+        assert_eq!(validate("4k3/8/8/8/4P3/8/8/4K3 w - e3 0 1"), en_passant);
+        // the pawn could not have come from behind the square
+        assert_eq!(validate("4k3/8/8/8/4P3/8/4N3/4K3 b - e3 0 1"), en_passant);
+    }
+
+    #[test]
+    fn perft_starting_position() {
+        check(Board::STARTING_FEN, &[20, 400, 8902, 197_281]);
+    }
+
+    #[test]
+    fn perft_kiwipete() {
+        check(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            &[48, 2039, 97_862],
+        );
+    }
+
+    #[test]
+    fn perft_en_passant_and_pins() {
+        check(
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            &[14, 191, 2812, 43_238],
+        );
+    }
+
+    #[test]
+    fn perft_promotions_and_castling() {
+        check(
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            &[6, 264, 9467],
+        );
+        check(
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            &[44, 1486, 62_379],
+        );
+        check(
+            "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+            &[46, 2079, 89_890],
+        );
+    }
+}
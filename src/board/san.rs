@@ -0,0 +1,296 @@
+use crate::{piece::PieceKind, r#move::Move};
+
+use super::{Board, Square};
+
+impl Board {
+    /// Returns the given legal move in standard algebraic notation, i.e. "Nbd7" or "exd8=Q+"
+    pub fn san(&self, r#move: Move) -> String {
+        let mut san = match r#move {
+            _ if r#move.is_kingside_castle() => String::from("O-O"),
+            _ if r#move.is_castle() => String::from("O-O-O"),
+            _ => self.san_without_suffix(r#move),
+        };
+
+        let mut board = self.clone();
+        board.play_move(r#move);
+        if board.is_in_check(board.to_move) {
+            match board.legal_moves().is_empty() {
+                true => san.push('#'),
+                false => san.push('+'),
+            }
+        }
+
+        san
+    }
+
+    /// Parses a legal move in standard algebraic notation, i.e. "Nf3", "exd5", or "O-O"
+    ///
+    /// Check and annotation suffixes are ignored, and captures may be written without an "x",
+    /// promotions without an "=", and castling with zeros.
+    pub fn parse_san(&self, san: &str) -> Result<Move, &'static str> {
+        let san = san.trim().trim_end_matches(['+', '#', '!', '?']);
+        if !san.is_ascii() {
+            return Err("invalid move");
+        }
+
+        let castle = match san {
+            "O-O" | "0-0" => Some(Move::kingside_castle(self.to_move, self.castling_files)),
+            "O-O-O" | "0-0-0" => Some(Move::queenside_castle(self.to_move, self.castling_files)),
+            _ => None,
+        };
+        if let Some(castle) = castle {
+            return match self.is_legal(castle) {
+                true => Ok(castle),
+                false => Err("castling is not legal here"),
+            };
+        }
+
+        // piece letters are uppercase, so that "b" is always a file
+        let (kind, mut rest) = match san
+            .chars()
+            .next()
+            .filter(char::is_ascii_uppercase)
+            .and_then(PieceKind::from_letter)
+        {
+            Some(kind) => (kind, &san[1..]),
+            None => (PieceKind::Pawn, san),
+        };
+
+        // the destination square always ends in a digit, so a trailing letter is a promotion
+        let mut promotion = None;
+        if let Some(letter) = rest.chars().last().filter(char::is_ascii_alphabetic) {
+            promotion = match PieceKind::from_letter(letter) {
+                Some(
+                    kind @ (PieceKind::Knight
+                    | PieceKind::Bishop
+                    | PieceKind::Rook
+                    | PieceKind::Queen),
+                ) => Some(kind),
+                _ => return Err("invalid promotion piece"),
+            };
+            rest = rest[..rest.len() - 1].trim_end_matches('=');
+        }
+
+        if rest.len() < 2 {
+            return Err("invalid move");
+        }
+        let (qualifier, to) = rest.split_at(rest.len() - 2);
+        let to: Square = to.parse()?;
+
+        // whatever precedes the destination narrows down the moving piece's square
+        let (mut file, mut rank) = (None, None);
+        for c in qualifier.chars().filter(|c| !matches!(c, 'x' | ':' | '-')) {
+            match c {
+                'a'..='h' if file.is_none() && rank.is_none() => file = Some(c as u8 - b'a'),
+                '1'..='8' if rank.is_none() => rank = Some(c as u8 - b'1'),
+                _ => return Err("invalid move"),
+            }
+        }
+
+        // only the moves matching the notation are checked for legality, which is much quicker
+        // than finding every legal move, as reading large PGN files does this for every move
+        let mut board = self.clone();
+        let candidates: Vec<_> = self
+            .pseudo_legal_moves()
+            .into_iter()
+            .filter(|r#move| {
+                let (from_file, from_rank) = r#move.from().indices();
+                r#move.to() == to
+                    && self.get_piece(r#move.from()).map(|piece| piece.kind) == Some(kind)
+                    && file.is_none_or(|file| file == from_file)
+                    && rank.is_none_or(|rank| rank == from_rank)
+                    && promotion.is_none_or(|promotion| {
+                        r#move.promotion().map(|piece| piece.kind) == Some(promotion)
+                    })
+            })
+            .filter(|&r#move| {
+                board.play_move(r#move);
+                let legal = !board.is_in_check(self.to_move);
+                board.undo_move(r#move);
+                legal
+            })
+            .collect();
+
+        match candidates[..] {
+            [r#move] => Ok(r#move),
+            [] => Err("no legal move matches"),
+            _ if candidates.iter().all(|r#move| r#move.promotion().is_some()) => {
+                Err("promotion piece is missing")
+            }
+            _ => Err("ambiguous move"),
+        }
+    }
+
+    /// Returns the legal moves whose standard algebraic or UCI notation starts with the given
+    /// partial input, so that "Nf" matches both "Nf3" and "Nxf7"
+    pub fn moves_matching(&self, partial: &str) -> Vec<Move> {
+        let partial = partial.trim().replace('0', "O").replace('x', "");
+        if partial.is_empty() {
+            return Vec::new();
+        }
+
+        self.legal_moves()
+            .into_iter()
+            .filter(|&r#move| {
+                let san = self.san(r#move).replace('x', "");
+                san.starts_with(&partial) || r#move.uci().starts_with(&partial)
+            })
+            .collect()
+    }
+
+    /// Returns the given non-castling move in standard algebraic notation, without a check or
+    /// checkmate suffix
+    fn san_without_suffix(&self, r#move: Move) -> String {
+        let (from, to) = (r#move.from(), r#move.to());
+        let piece = self.get_piece(from).expect("illegal move");
+        let capture = match r#move.captured() {
+            Some(_) => "x",
+            None => "",
+        };
+
+        let mut san = match piece.is_pawn() {
+            true if r#move.captured().is_some() => format!("{}x{to}", from.file),
+            true => to.to_string(),
+            false => format!("{}{}{capture}{to}", piece.kind, self.disambiguation(r#move)),
+        };
+
+        if let Some(promoting) = r#move.promotion() {
+            san.push_str(&format!("={}", promoting.kind));
+        }
+
+        san
+    }
+
+    /// Returns the file, rank, or square needed to tell the given move apart from other moves of
+    /// the same kind of piece to the same square
+    fn disambiguation(&self, r#move: Move) -> String {
+        let from = r#move.from();
+        let piece = self.get_piece(from);
+        let others: Vec<_> = self
+            .legal_moves()
+            .into_iter()
+            .filter(|other| {
+                other.to() == r#move.to()
+                    && other.from() != from
+                    && !other.is_castle()
+                    && self.get_piece(other.from()) == piece
+            })
+            .map(|other| other.from())
+            .collect();
+
+        if others.is_empty() {
+            String::new()
+        } else if others.iter().all(|other| other.file != from.file) {
+            from.file.to_string()
+        } else if others.iter().all(|other| other.rank != from.rank) {
+            from.rank.to_string()
+        } else {
+            from.to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board(fen: &str) -> Board {
+        Board::from_fen(fen).unwrap()
+    }
+
+    /// Parses the given moves, returning them in UCI notation
+    fn uci(board: &Board, san: &str) -> Result<String, &'static str> {
+        board.parse_san(san).map(|r#move| r#move.uci())
+    }
+
+    #[test]
+    fn castling() {
+        let rights = board("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+        for (san, expected) in [("O-O", "e1g1"), ("0-0", "e1g1"), ("O-O-O+", "e1c1")] {
+            assert_eq!(rights.parse_san(san), rights.parse_uci(expected), "{san}");
+        }
+        assert_eq!(rights.san(rights.parse_uci("e1c1").unwrap()), "O-O-O");
+
+        let no_rights = board("r3k2r/8/8/8/8/8/8/R3K2R w kq - 0 1");
+        assert_eq!(
+            no_rights.parse_san("O-O"),
+            Err("castling is not legal here")
+        );
+    }
+
+    #[test]
+    fn promotions() {
+        let board = board("1n6/P7/8/8/8/8/8/k6K w - - 0 1");
+        assert_eq!(uci(&board, "a8=Q"), Ok(String::from("a7a8q")));
+        assert_eq!(uci(&board, "a8N"), Ok(String::from("a7a8n")));
+        assert_eq!(uci(&board, "axb8=R"), Ok(String::from("a7b8r")));
+        assert_eq!(uci(&board, "ab8B"), Ok(String::from("a7b8b")));
+        assert_eq!(board.san(board.parse_uci("a7b8q").unwrap()), "axb8=Q");
+
+        assert_eq!(board.parse_san("a8"), Err("promotion piece is missing"));
+        assert_eq!(board.parse_san("a8=K"), Err("invalid promotion piece"));
+    }
+
+    #[test]
+    fn disambiguation() {
+        let knights = board("4k3/8/8/8/8/5N2/8/1N2K3 w - - 0 1");
+        assert_eq!(uci(&knights, "Nbd2"), Ok(String::from("b1d2")));
+        assert_eq!(uci(&knights, "Nfxd2"), Ok(String::from("f3d2")));
+        assert_eq!(knights.parse_san("Nd2"), Err("ambiguous move"));
+        assert_eq!(knights.san(knights.parse_uci("b1d2").unwrap()), "Nbd2");
+
+        let rooks = board("4k3/8/8/R7/8/8/8/R3K3 w - - 0 1");
+        assert_eq!(uci(&rooks, "R1a3"), Ok(String::from("a1a3")));
+        assert_eq!(uci(&rooks, "R5a3"), Ok(String::from("a5a3")));
+        assert_eq!(rooks.parse_san("Ra3"), Err("ambiguous move"));
+        assert_eq!(rooks.san(rooks.parse_uci("a5a3").unwrap()), "R5a3");
+
+        let queens = board("4k3/8/8/8/8/Q7/8/Q1Q1K3 w - - 0 1");
+        assert_eq!(uci(&queens, "Qa1b2"), Ok(String::from("a1b2")));
+        assert_eq!(queens.san(queens.parse_uci("a1b2").unwrap()), "Qa1b2");
+
+        // a pinned knight cannot move, so the other needs no disambiguation
+        let pinned = board("4k3/8/8/8/8/8/8/1N2KN1r w - - 0 1");
+        assert_eq!(uci(&pinned, "Nd2"), Ok(String::from("b1d2")));
+        assert_eq!(pinned.san(pinned.parse_uci("b1d2").unwrap()), "Nd2");
+    }
+
+    #[test]
+    fn illegal_moves() {
+        let board = Board::new_init();
+        assert_eq!(uci(&board, "e4"), Ok(String::from("e2e4")));
+        assert_eq!(uci(&board, "Nf3!?"), Ok(String::from("g1f3")));
+        assert_eq!(board.parse_san("e5"), Err("no legal move matches"));
+        assert_eq!(board.parse_san("Nd4"), Err("no legal move matches"));
+        assert_eq!(board.parse_san("O-O"), Err("castling is not legal here"));
+        assert_eq!(board.parse_san("N"), Err("invalid move"));
+        assert_eq!(board.parse_san("Ne2e4e"), Err("invalid promotion piece"));
+        assert!(board.parse_san("Nf9").is_err());
+        assert!(board.parse_san("Sf3").is_err());
+    }
+
+    #[test]
+    fn matching() {
+        let start = Board::new_init();
+        let matching = |partial: &str| {
+            let mut moves: Vec<_> = start
+                .moves_matching(partial)
+                .into_iter()
+                .map(|r#move| r#move.uci())
+                .collect();
+            moves.sort();
+            moves
+        };
+        assert_eq!(matching("Nf"), ["g1f3"]);
+        assert_eq!(matching("N"), ["b1a3", "b1c3", "g1f3", "g1h3"]);
+        assert_eq!(matching("e2"), ["e2e3", "e2e4"]);
+        assert!(matching("").is_empty());
+        assert!(matching("Q").is_empty());
+
+        let position = board("4k3/8/8/3p4/4P3/8/8/4K2R w K - 0 1");
+        let matching = |partial: &str| position.moves_matching(partial).len();
+        assert_eq!(matching("exd"), 1);
+        assert_eq!(matching("ed5"), 1);
+        assert_eq!(matching("0-0"), 1);
+    }
+}
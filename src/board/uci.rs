@@ -0,0 +1,37 @@
+use crate::{piece::PieceKind, r#move::Move};
+
+use super::{Board, Square};
+
+impl Board {
+    /// Parses a legal move in the long algebraic notation used by the Universal Chess Interface,
+    /// i.e. "e2e4", "e7e8q", or "e1g1" or "e1h1" for castling
+    pub fn parse_uci(&self, uci: &str) -> Result<Move, &'static str> {
+        let uci = uci.trim();
+        if !uci.is_ascii() || !(4..=5).contains(&uci.len()) {
+            return Err("invalid move");
+        }
+
+        let from: Square = uci[0..2].parse()?;
+        let to: Square = uci[2..4].parse()?;
+        let promotion = match uci[4..].chars().next() {
+            Some(letter) => match PieceKind::from_letter(letter) {
+                Some(PieceKind::Pawn | PieceKind::King) | None => {
+                    return Err("invalid promotion piece")
+                }
+                kind => kind,
+            },
+            None => None,
+        };
+
+        // castling may also be written as the king capturing its own rook, as in Chess960
+        self.legal_moves()
+            .into_iter()
+            .find(|r#move| {
+                let to_rook = r#move.castling_rook().map(|(rook, _)| rook) == Some(to);
+                r#move.from() == from
+                    && (r#move.to() == to || to_rook)
+                    && r#move.promotion().map(|piece| piece.kind) == promotion
+            })
+            .ok_or("illegal move")
+    }
+}
@@ -0,0 +1,151 @@
+use std::{fs, path::Path};
+
+use crate::{
+    board::{Board, Square},
+    piece::PieceKind,
+    r#move::Move,
+};
+
+/// Building opening books from collections of games
+pub mod builder;
+
+/// The size of each entry in a Polyglot book, in bytes
+const ENTRY_SIZE: usize = 16;
+
+/// A move a book suggests, and how often it should be played
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BookMove {
+    /// The move
+    pub r#move: Move,
+
+    /// How often the move should be played, relative to the other moves in the position
+    pub weight: u16,
+}
+
+/// An opening book in the Polyglot format
+///
+/// A book is a list of entries sorted by the Zobrist key of their position, each holding a move
+/// and its weight, so the moves for a position are found by binary search.
+pub struct Book {
+    entries: Vec<[u8; ENTRY_SIZE]>,
+}
+
+impl Book {
+    /// Reads the book in the given file
+    pub fn open(path: &Path) -> Result<Self, &'static str> {
+        let bytes = fs::read(path).map_err(|_| "unable to read the book")?;
+        Self::from_bytes(bytes)
+    }
+
+    /// Constructs a book from the contents of a Polyglot book file
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, &'static str> {
+        if !bytes.len().is_multiple_of(ENTRY_SIZE) {
+            return Err("not a Polyglot book");
+        }
+        let entries = bytes
+            .chunks_exact(ENTRY_SIZE)
+            .map(|entry| entry.try_into().expect("chunks are entry sized"))
+            .collect();
+        Ok(Self { entries })
+    }
+
+    /// Returns the number of entries in the book
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if the book has no entries
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the legal moves the book suggests in the given position, most often played first
+    pub fn moves(&self, board: &Board) -> Vec<BookMove> {
+        let key = board.zobrist();
+        let start = self.entries.partition_point(|entry| entry_key(entry) < key);
+        let mut moves: Vec<_> = self.entries[start..]
+            .iter()
+            .take_while(|entry| entry_key(entry) == key)
+            .filter_map(|entry| {
+                let raw = u16::from_be_bytes([entry[8], entry[9]]);
+                let weight = u16::from_be_bytes([entry[10], entry[11]]);
+                let r#move = decode_move(board, raw)?;
+                Some(BookMove { r#move, weight })
+            })
+            .collect();
+        moves.sort_by_key(|book_move| std::cmp::Reverse(book_move.weight));
+        moves
+    }
+
+    /// Chooses one of the book's moves in the given position at random, in proportion to their
+    /// weights, or returns `None` if the position is not in the book
+    pub fn choose(&self, board: &Board) -> Option<Move> {
+        let moves = self.moves(board);
+        let total: u32 = moves.iter().map(|book_move| book_move.weight as u32).sum();
+        if total == 0 {
+            return moves.first().map(|book_move| book_move.r#move);
+        }
+
+        let mut pick = fastrand::u32(0..total);
+        for book_move in moves {
+            if pick < book_move.weight as u32 {
+                return Some(book_move.r#move);
+            }
+            pick -= book_move.weight as u32;
+        }
+        None
+    }
+}
+
+/// Returns the Zobrist key of the position a book entry is for
+fn entry_key(entry: &[u8; ENTRY_SIZE]) -> u64 {
+    u64::from_be_bytes(entry[..8].try_into().expect("keys are eight bytes"))
+}
+
+/// Converts a move into the Polyglot encoding, the reverse of [`decode_move`]
+pub fn encode_move(r#move: Move) -> u16 {
+    // castling is written as the king taking its own rook
+    let to = match r#move.castling_rook() {
+        Some((rook, _)) => rook,
+        None => r#move.to(),
+    };
+    let (to_file, to_rank) = to.indices();
+    let (from_file, from_rank) = r#move.from().indices();
+    let promotion = match r#move.promotion().map(|piece| piece.kind) {
+        Some(PieceKind::Knight) => 1,
+        Some(PieceKind::Bishop) => 2,
+        Some(PieceKind::Rook) => 3,
+        Some(PieceKind::Queen) => 4,
+        _ => 0,
+    };
+    [to_file, to_rank, from_file, from_rank, promotion]
+        .into_iter()
+        .enumerate()
+        .fold(0, |raw, (index, field)| raw | (field as u16) << (3 * index))
+}
+
+/// Converts a move in the Polyglot encoding into the legal move it stands for, if any
+///
+/// Moves are packed into 16 bits as the destination file and rank, the origin file and rank,
+/// and the promotion piece, with three bits each. Castling is written as the king taking its own
+/// rook, in standard chess as well as Chess960.
+pub fn decode_move(board: &Board, raw: u16) -> Option<Move> {
+    let field = |shift: u16| (raw >> shift & 0b111) as u8;
+    let to = Square::from_indices(field(0), field(3));
+    let from = Square::from_indices(field(6), field(9));
+    let promotion = match field(12) {
+        0 => None,
+        1 => Some(PieceKind::Knight),
+        2 => Some(PieceKind::Bishop),
+        3 => Some(PieceKind::Rook),
+        4 => Some(PieceKind::Queen),
+        _ => return None,
+    };
+
+    board.legal_moves().into_iter().find(|r#move| {
+        let to_rook = r#move.castling_rook().map(|(rook, _)| rook) == Some(to);
+        r#move.from() == from
+            && (to_rook || (r#move.to() == to && !r#move.is_castle()))
+            && r#move.promotion().map(|piece| piece.kind) == promotion
+    })
+}
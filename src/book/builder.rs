@@ -0,0 +1,382 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+};
+
+use crate::{
+    game::{Game, NodeId},
+    pgn,
+    piece::PieceColor,
+};
+
+use super::{encode_move, ENTRY_SIZE};
+
+/// How `chess book` is used, shown when it is run wrongly or with `--help`
+const USAGE: &str = "\
+usage: chess book [options] <output.bin> <games.pgn>...
+
+Builds a Polyglot opening book from the main lines of the given games.
+
+options:
+  --min-games <n>       leave out positions reached in fewer than n games (default 1)
+  --max-ply <n>         only read the first n plies of each game (default 30)
+  --winners-only        only keep moves played by the side that went on to win
+  --min-elo <n>         only keep moves by players rated at least n
+  --weights <w,d,l>     how much a win, draw, and loss count towards a move (default 2,1,0)";
+
+/// Which games and moves go into a book, and how the moves are weighed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BuildOptions {
+    /// The fewest games a position must be reached in to be kept
+    pub min_games: u32,
+
+    /// The number of plies read from the start of each game
+    pub max_ply: usize,
+
+    /// Whether only the moves of the side that won are kept, leaving out drawn games
+    pub winners_only: bool,
+
+    /// The lowest rating of a player whose moves are kept, or `None` to keep every player's
+    ///
+    /// Players without an Elo tag are left out when this is set.
+    pub min_elo: Option<u32>,
+
+    /// How much each game the player of a move won counts towards its weight
+    pub win_weight: u32,
+
+    /// How much each drawn game counts towards a move's weight
+    pub draw_weight: u32,
+
+    /// How much each game the player of a move lost counts towards its weight
+    pub loss_weight: u32,
+}
+
+impl Default for BuildOptions {
+    fn default() -> Self {
+        Self {
+            min_games: 1,
+            max_ply: 30,
+            winners_only: false,
+            min_elo: None,
+            win_weight: 2,
+            draw_weight: 1,
+            loss_weight: 0,
+        }
+    }
+}
+
+/// How the games a move was played in ended, for the player who played it
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct Record {
+    wins: u32,
+    draws: u32,
+    losses: u32,
+}
+
+impl Record {
+    /// Returns the number of games the move was played in
+    fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+}
+
+/// Collects the moves played in games, and how those games ended, into an opening book
+pub struct BookBuilder {
+    options: BuildOptions,
+
+    /// The record of every move, by the Zobrist key of its position and its Polyglot encoding
+    moves: HashMap<(u64, u16), Record>,
+}
+
+impl BookBuilder {
+    /// Constructs a new builder of a book with the given options
+    pub fn new(options: BuildOptions) -> Self {
+        Self {
+            options,
+            moves: HashMap::new(),
+        }
+    }
+
+    /// Adds the moves of the main line of the given game, returning false if the game was left
+    /// out because it has no result to weigh its moves by
+    pub fn add(&mut self, game: &Game) -> bool {
+        let winner = match game.headers().get("Result") {
+            Some("1-0") => Some(PieceColor::White),
+            Some("0-1") => Some(PieceColor::Black),
+            Some("1/2-1/2") => None,
+            _ => return false,
+        };
+        let rated = |tag: &str| match self.options.min_elo {
+            Some(min_elo) => game
+                .headers()
+                .get(tag)
+                .and_then(|elo| elo.parse::<u32>().ok())
+                .is_some_and(|elo| elo >= min_elo),
+            None => true,
+        };
+        let (white_rated, black_rated) = (rated("WhiteElo"), rated("BlackElo"));
+
+        let end = game.main_line().last().copied().unwrap_or(NodeId::ROOT);
+        let mut board = game.start().clone();
+        // a position repeated within a game counts once
+        let mut seen = HashSet::new();
+        for r#move in game.moves_to(end).into_iter().take(self.options.max_ply) {
+            let mover = board.to_move();
+            let kept = match mover {
+                PieceColor::White => white_rated,
+                PieceColor::Black => black_rated,
+            } && (!self.options.winners_only || winner == Some(mover));
+            let key = board.zobrist();
+            if kept && seen.insert(key) {
+                let record = self.moves.entry((key, encode_move(r#move))).or_default();
+                match winner {
+                    Some(winner) if winner == mover => record.wins += 1,
+                    Some(_) => record.losses += 1,
+                    None => record.draws += 1,
+                }
+            }
+            board.play_move(r#move);
+        }
+        true
+    }
+
+    /// Returns the book, in the Polyglot format
+    ///
+    /// Weights are scaled down within positions where they would not fit in an entry, and
+    /// moves that weigh nothing are left out.
+    pub fn build(&self) -> Vec<u8> {
+        let options = &self.options;
+        let mut positions: HashMap<u64, Vec<(u16, u64)>> = HashMap::new();
+        let mut games: HashMap<u64, u32> = HashMap::new();
+        for (&(key, raw), record) in &self.moves {
+            let weight = record.wins as u64 * options.win_weight as u64
+                + record.draws as u64 * options.draw_weight as u64
+                + record.losses as u64 * options.loss_weight as u64;
+            positions.entry(key).or_default().push((raw, weight));
+            *games.entry(key).or_default() += record.games();
+        }
+
+        let mut entries = Vec::new();
+        for (key, mut moves) in positions {
+            if games[&key] < options.min_games {
+                continue;
+            }
+            let heaviest = moves.iter().map(|&(_, weight)| weight).max().unwrap_or(0);
+            let scale = |weight: u64| match heaviest > u16::MAX as u64 {
+                true => weight * u16::MAX as u64 / heaviest,
+                false => weight,
+            };
+            moves.sort_by_key(|&(raw, weight)| (std::cmp::Reverse(weight), raw));
+            for (raw, weight) in moves {
+                let weight = scale(weight) as u16;
+                if weight > 0 {
+                    entries.push((key, raw, weight));
+                }
+            }
+        }
+
+        // the moves of a position stay heaviest first, as the sort is stable
+        entries.sort_by_key(|&(key, _, _)| key);
+        let mut bytes = Vec::with_capacity(entries.len() * ENTRY_SIZE);
+        for (key, raw, weight) in entries {
+            bytes.extend_from_slice(&key.to_be_bytes());
+            bytes.extend_from_slice(&raw.to_be_bytes());
+            bytes.extend_from_slice(&weight.to_be_bytes());
+            // the learning field is left for programs that learn from the book
+            bytes.extend_from_slice(&0u32.to_be_bytes());
+        }
+        bytes
+    }
+}
+
+/// Runs `chess book` with the given arguments, building a book from PGN files and writing it
+/// out, i.e. `chess book --max-ply 20 repertoire.bin games.pgn`
+pub fn run(mut args: impl Iterator<Item = String>) -> Result<(), String> {
+    let mut options = BuildOptions::default();
+    let mut paths = Vec::new();
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| -> Result<String, String> {
+            args.next()
+                .ok_or_else(|| format!("{name} needs a value\n\n{USAGE}"))
+        };
+        let number = |text: String| -> Result<u32, String> {
+            text.parse()
+                .map_err(|_| format!("expected a number, not {text}"))
+        };
+        match arg.as_str() {
+            "--help" | "-h" => {
+                println!("{USAGE}");
+                return Ok(());
+            }
+            "--min-games" => options.min_games = number(value(&arg)?)?,
+            "--max-ply" => options.max_ply = number(value(&arg)?)? as usize,
+            "--winners-only" => options.winners_only = true,
+            "--min-elo" => options.min_elo = Some(number(value(&arg)?)?),
+            "--weights" => {
+                let weights = value(&arg)?;
+                let weights: Vec<_> = weights.split(',').map(|weight| weight.trim()).collect();
+                let [win, draw, loss] = weights[..] else {
+                    return Err(String::from("expected --weights <win>,<draw>,<loss>"));
+                };
+                options.win_weight = number(win.to_owned())?;
+                options.draw_weight = number(draw.to_owned())?;
+                options.loss_weight = number(loss.to_owned())?;
+            }
+            _ if arg.starts_with("--") => return Err(format!("unknown option {arg}\n\n{USAGE}")),
+            _ => paths.push(arg),
+        }
+    }
+    if paths.len() < 2 {
+        return Err(String::from(USAGE));
+    }
+
+    let output = paths.remove(0);
+    let mut builder = BookBuilder::new(options);
+    let (mut added, mut skipped) = (0, 0);
+    for path in &paths {
+        let bytes = fs::read(path).map_err(|error| format!("unable to read {path}: {error}"))?;
+        // older collections are often not UTF-8, but the moves themselves are ASCII
+        let text = String::from_utf8_lossy(&bytes);
+        for game in pgn::Reader::new(&text) {
+            match game.is_ok_and(|game| builder.add(&game)) {
+                true => added += 1,
+                false => skipped += 1,
+            }
+        }
+    }
+
+    let book = builder.build();
+    fs::write(&output, &book).map_err(|error| format!("unable to write {output}: {error}"))?;
+    println!(
+        "{added} games read, {skipped} skipped, {} entries written to {output}",
+        book.len() / ENTRY_SIZE
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{board::Board, book::Book};
+
+    const GAMES: &str = concat!(
+        "[WhiteElo \"2500\"]\n[BlackElo \"2000\"]\n[Result \"1-0\"]\n\n1. e4 e5 1-0\n\n",
+        "[Result \"0-1\"]\n\n1. e4 c5 0-1\n\n",
+        "[Result \"1/2-1/2\"]\n\n1. d4 d5 1/2-1/2\n\n",
+        "[Result \"*\"]\n\n1. c4 *\n",
+    );
+
+    /// Builds a book from the games with the given options, returning the moves and weights it
+    /// gives after each of the given lines
+    fn build(options: BuildOptions, lines: &[&str]) -> Vec<Vec<(String, u16)>> {
+        let mut builder = BookBuilder::new(options);
+        let added: Vec<_> = pgn::Reader::new(GAMES)
+            .map(|game| builder.add(&game.unwrap()))
+            .collect();
+        assert_eq!(added, [true, true, true, false]);
+        let book = Book::from_bytes(builder.build()).unwrap();
+        lines.iter().map(|line| moves(&book, line)).collect()
+    }
+
+    /// Returns the moves and weights the book gives after the given moves from the start
+    fn moves(book: &Book, line: &str) -> Vec<(String, u16)> {
+        let mut board = Board::new_init();
+        for uci in line.split_whitespace() {
+            board.play_move(board.parse_uci(uci).unwrap());
+        }
+        book.moves(&board)
+            .into_iter()
+            .map(|book_move| (book_move.r#move.uci(), book_move.weight))
+            .collect()
+    }
+
+    fn weighted(moves: &[(&str, u16)]) -> Vec<(String, u16)> {
+        moves
+            .iter()
+            .map(|&(uci, weight)| (uci.to_string(), weight))
+            .collect()
+    }
+
+    #[test]
+    fn weights() {
+        // a win counts two, a draw one, and a loss nothing, so 1... e5 is left out
+        let books = build(BuildOptions::default(), &["", "e2e4", "d2d4"]);
+        assert_eq!(books[0], weighted(&[("e2e4", 2), ("d2d4", 1)]));
+        assert_eq!(books[1], weighted(&[("c7c5", 2)]));
+        assert_eq!(books[2], weighted(&[("d7d5", 1)]));
+
+        let options = BuildOptions {
+            win_weight: 3,
+            draw_weight: 0,
+            loss_weight: 1,
+            ..BuildOptions::default()
+        };
+        let books = build(options, &["", "e2e4"]);
+        assert_eq!(books[0], weighted(&[("e2e4", 4)]));
+        assert_eq!(books[1], weighted(&[("c7c5", 3), ("e7e5", 1)]));
+    }
+
+    #[test]
+    fn filters() {
+        let options = BuildOptions {
+            winners_only: true,
+            ..BuildOptions::default()
+        };
+        let books = build(options, &["", "e2e4"]);
+        assert_eq!(books[0], weighted(&[("e2e4", 2)]));
+        assert_eq!(books[1], weighted(&[("c7c5", 2)]));
+
+        // only white in the first game is rated highly enough
+        let options = BuildOptions {
+            min_elo: Some(2400),
+            ..BuildOptions::default()
+        };
+        let books = build(options, &["", "e2e4"]);
+        assert_eq!(books[0], weighted(&[("e2e4", 2)]));
+        assert!(books[1].is_empty());
+
+        let options = BuildOptions {
+            min_games: 2,
+            ..BuildOptions::default()
+        };
+        let books = build(options, &["", "e2e4", "d2d4"]);
+        assert_eq!(books[0], weighted(&[("e2e4", 2), ("d2d4", 1)]));
+        assert_eq!(books[1], weighted(&[("c7c5", 2)]));
+        assert!(books[2].is_empty());
+
+        let options = BuildOptions {
+            max_ply: 1,
+            ..BuildOptions::default()
+        };
+        assert!(build(options, &["e2e4"])[0].is_empty());
+    }
+
+    #[test]
+    fn repeated_positions_count_once() {
+        let mut builder = BookBuilder::new(BuildOptions::default());
+        let game = pgn::read("[Result \"1-0\"]\n\n1. Nf3 Nf6 2. Ng1 Ng8 3. Nf3 1-0").unwrap();
+        assert!(builder.add(&game));
+        let book = Book::from_bytes(builder.build()).unwrap();
+        assert_eq!(moves(&book, ""), weighted(&[("g1f3", 2)]));
+    }
+
+    #[test]
+    fn heavy_weights_are_scaled() {
+        let mut builder = BookBuilder::new(BuildOptions::default());
+        let board = Board::new_init();
+        for (uci, wins) in [("e2e4", 100_000), ("d2d4", 50_000), ("c2c4", 1)] {
+            let raw = encode_move(board.parse_uci(uci).unwrap());
+            let record = Record {
+                wins,
+                ..Record::default()
+            };
+            builder.moves.insert((board.zobrist(), raw), record);
+        }
+        let book = Book::from_bytes(builder.build()).unwrap();
+        // the lightest move weighs nothing once scaled, so it is left out
+        assert_eq!(
+            moves(&book, ""),
+            weighted(&[("e2e4", 65535), ("d2d4", 32767)])
+        );
+    }
+}
@@ -0,0 +1,571 @@
+use std::{
+    cell::Cell,
+    fmt::Display,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use crate::piece::PieceColor;
+
+/// A source of the current time, measured from an arbitrary fixed point
+///
+/// Clocks are generic over their time source so that they can be driven by [`ManualTime`]
+/// instead of the system clock.
+pub trait TimeSource {
+    /// Returns the time elapsed since this source's fixed point
+    fn now(&self) -> Duration;
+}
+
+/// A time source backed by the system's monotonic clock
+#[derive(Clone, Copy, Debug)]
+pub struct MonotonicTime {
+    epoch: Instant,
+}
+
+impl MonotonicTime {
+    /// Constructs a new time source, measuring from the current instant
+    pub fn new() -> Self {
+        Self {
+            epoch: Instant::now(),
+        }
+    }
+}
+
+impl Default for MonotonicTime {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TimeSource for MonotonicTime {
+    fn now(&self) -> Duration {
+        self.epoch.elapsed()
+    }
+}
+
+/// A time source that only moves when told to
+#[derive(Debug, Default)]
+pub struct ManualTime {
+    now: Cell<Duration>,
+}
+
+impl ManualTime {
+    /// Constructs a new time source, starting at zero
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Moves the current time forward by the given duration
+    pub fn advance(&self, duration: Duration) {
+        self.now.set(self.now.get() + duration);
+    }
+}
+
+impl TimeSource for ManualTime {
+    fn now(&self) -> Duration {
+        self.now.get()
+    }
+}
+
+/// How time is given back to a player for each move they make
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bonus {
+    /// No time is given back
+    None,
+
+    /// The given time is added after every move
+    Fischer(Duration),
+
+    /// The time spent on the move is added back after it, up to the given time
+    Bronstein(Duration),
+
+    /// The clock waits for the given time at the start of every move before counting down
+    Delay(Duration),
+}
+
+/// One stage of a time control, i.e. "40 moves in 90 minutes with a 30 second increment"
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Stage {
+    /// The number of moves to be made in this stage, or `None` for the rest of the game
+    pub moves: Option<u32>,
+
+    /// The time added to the clock at the start of this stage
+    pub time: Duration,
+
+    /// The time given back for each move made in this stage
+    pub bonus: Bonus,
+}
+
+/// A time control made up of one or more stages
+///
+/// The last stage repeats once its moves are made. Time controls are written as stages
+/// separated by commas, each in the form `[moves/]minutes[+seconds|d seconds|b seconds]`, where
+/// `+` is a Fischer increment, `d` a simple delay and `b` a Bronstein delay, i.e. "5+3",
+/// "40/90+30, 30+30" or "25d5".
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TimeControl {
+    stages: Vec<Stage>,
+}
+
+impl TimeControl {
+    /// Constructs a new time control from the given stages, failing if there are none or a stage
+    /// has no moves to make
+    pub fn new(stages: Vec<Stage>) -> Result<Self, &'static str> {
+        if stages.is_empty() {
+            return Err("a time control needs at least one stage");
+        }
+        // a stage of no moves would never end
+        if stages.iter().any(|stage| stage.moves == Some(0)) {
+            return Err("expected a move count of at least one");
+        }
+        Ok(Self { stages })
+    }
+
+    /// Constructs a single stage time control with no bonus
+    pub fn sudden_death(time: Duration) -> Self {
+        Self {
+            stages: vec![Stage {
+                moves: None,
+                time,
+                bonus: Bonus::None,
+            }],
+        }
+    }
+
+    /// Returns the stages of this time control
+    pub fn stages(&self) -> &[Stage] {
+        &self.stages
+    }
+
+    /// Returns the stage at the given index, repeating the last stage past the end
+    pub fn stage(&self, index: usize) -> &Stage {
+        &self.stages[index.min(self.stages.len() - 1)]
+    }
+}
+
+impl Display for TimeControl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (index, stage) in self.stages.iter().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            if let Some(moves) = stage.moves {
+                write!(f, "{moves}/")?;
+            }
+            write!(f, "{}", format_minutes(stage.time))?;
+            match stage.bonus {
+                Bonus::None => {}
+                Bonus::Fischer(time) => write!(f, "+{}", time.as_secs_f64())?,
+                Bonus::Delay(time) => write!(f, "d{}", time.as_secs_f64())?,
+                Bonus::Bronstein(time) => write!(f, "b{}", time.as_secs_f64())?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Formats a duration in minutes, with a fraction if it is not a whole number of minutes
+fn format_minutes(time: Duration) -> String {
+    match time.as_nanos() % 60_000_000_000 {
+        0 => (time.as_secs() / 60).to_string(),
+        _ => format!("{}", time.as_secs_f64() / 60.0),
+    }
+}
+
+impl FromStr for TimeControl {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // parses a number of the given unit, in seconds
+        let parse_time = |s: &str, unit: f64| {
+            let number = s
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|n| n.is_finite() && *n >= 0.0)
+                .ok_or("expected a number")?;
+            Duration::try_from_secs_f64(number * unit).map_err(|_| "the time is too long")
+        };
+
+        let stages = s
+            .split([',', ':'])
+            .map(|stage| {
+                let stage = stage.trim();
+                let stage = stage.strip_prefix("then").unwrap_or(stage).trim();
+
+                let (moves, rest) = match stage.split_once('/') {
+                    Some((moves, rest)) => {
+                        let moves = (moves.trim().parse().ok())
+                            .ok_or("expected a move count of at least one")?;
+                        (Some(moves), rest)
+                    }
+                    None => (None, stage),
+                };
+
+                let (minutes, bonus) = match rest.find(['+', 'd', 'b']) {
+                    Some(index) => {
+                        let seconds = parse_time(&rest[index + 1..], 1.0)?;
+                        let bonus = match &rest[index..=index] {
+                            "+" => Bonus::Fischer(seconds),
+                            "d" => Bonus::Delay(seconds),
+                            _ => Bonus::Bronstein(seconds),
+                        };
+                        (&rest[..index], bonus)
+                    }
+                    None => (rest, Bonus::None),
+                };
+
+                let time = parse_time(minutes, 60.0)?;
+                if time.is_zero() && bonus == Bonus::None {
+                    return Err("a stage needs some time");
+                }
+
+                Ok(Stage { moves, time, bonus })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Self::new(stages)
+    }
+}
+
+/// Per-player state of a clock
+#[derive(Clone, Copy, Debug, Default)]
+struct Side {
+    remaining: Duration,
+    stage: usize,
+    moves_in_stage: u32,
+}
+
+/// A chess clock for both players
+pub struct Clock<T: TimeSource = MonotonicTime> {
+    control: TimeControl,
+    time: T,
+    white: Side,
+    black: Side,
+
+    /// The player whose clock is running, and when their turn started
+    running: Option<(PieceColor, Duration)>,
+
+    flagged: Option<PieceColor>,
+}
+
+impl Clock {
+    /// Constructs a new clock driven by the system clock
+    pub fn new(control: TimeControl) -> Self {
+        Self::with_time_source(control, MonotonicTime::new())
+    }
+}
+
+impl<T: TimeSource> Clock<T> {
+    /// Constructs a new clock driven by the given time source
+    pub fn with_time_source(control: TimeControl, time: T) -> Self {
+        let side = Side {
+            remaining: control.stage(0).time,
+            stage: 0,
+            moves_in_stage: 0,
+        };
+        Self {
+            control,
+            time,
+            white: side,
+            black: side,
+            running: None,
+            flagged: None,
+        }
+    }
+
+    /// Returns the time control this clock was set up with
+    pub fn control(&self) -> &TimeControl {
+        &self.control
+    }
+
+    /// Returns the time source driving this clock
+    pub fn time_source(&self) -> &T {
+        &self.time
+    }
+
+    /// Returns the color whose clock is running, if any
+    pub fn running(&self) -> Option<PieceColor> {
+        self.running.map(|(color, _)| color)
+    }
+
+    /// Returns the time the given player has left
+    pub fn remaining(&self, color: PieceColor) -> Duration {
+        let side = self.side(color);
+        match self.running {
+            Some((running, started)) if running == color => {
+                side.remaining.saturating_sub(self.counted(color, started))
+            }
+            _ => side.remaining,
+        }
+    }
+
+    /// Returns the delay left before the given player's clock starts counting down, if they are
+    /// playing with a simple delay and their clock is running
+    pub fn delay_remaining(&self, color: PieceColor) -> Option<Duration> {
+        let (running, started) = self.running?;
+        match self.control.stage(self.side(color).stage).bonus {
+            Bonus::Delay(delay) if running == color => {
+                Some(delay.saturating_sub(self.time.now().saturating_sub(started)))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the time the given player gets back for each move they make, at most, in their
+    /// current stage
+    pub fn increment(&self, color: PieceColor) -> Duration {
+        match self.control.stage(self.side(color).stage).bonus {
+            Bonus::Fischer(time) | Bonus::Bronstein(time) | Bonus::Delay(time) => time,
+            Bonus::None => Duration::ZERO,
+        }
+    }
+
+    /// Returns the number of moves the given player must make before their next stage starts,
+    /// or `None` if their stage lasts for the rest of the game
+    pub fn moves_to_go(&self, color: PieceColor) -> Option<u32> {
+        let side = self.side(color);
+        let moves = self.control.stage(side.stage).moves?;
+        Some(moves.saturating_sub(side.moves_in_stage))
+    }
+
+    /// Returns the player whose time has run out, if any
+    ///
+    /// This checks the running clock, so it should be polled while the clock is running.
+    pub fn flagged(&mut self) -> Option<PieceColor> {
+        if self.flagged.is_none() {
+            if let Some((color, _)) = self.running {
+                if self.remaining(color).is_zero() {
+                    self.side_mut(color).remaining = Duration::ZERO;
+                    self.running = None;
+                    self.flagged = Some(color);
+                }
+            }
+        }
+        self.flagged
+    }
+
+    /// Ends the given player's turn, applying their bonus and starting their opponent's clock
+    ///
+    /// The first press starts the opponent's clock without charging the pressing player.
+    pub fn press(&mut self, color: PieceColor) {
+        if self.flagged().is_some() {
+            return;
+        }
+
+        let now = self.time.now();
+        if let Some((running, started)) = self.running {
+            if running != color {
+                return;
+            }
+
+            let counted = self.counted(color, started);
+            let spent = now.saturating_sub(started);
+            let index = self.side(color).stage;
+            let stage = *self.control.stage(index);
+            let next_stage_time = self.control.stage(index + 1).time;
+
+            let side = self.side_mut(color);
+            side.remaining = side.remaining.saturating_sub(counted);
+            side.remaining += match stage.bonus {
+                Bonus::Fischer(increment) => increment,
+                Bonus::Bronstein(delay) => spent.min(delay),
+                Bonus::None | Bonus::Delay(_) => Duration::ZERO,
+            };
+
+            side.moves_in_stage += 1;
+            if stage.moves == Some(side.moves_in_stage) {
+                side.stage += 1;
+                side.moves_in_stage = 0;
+                side.remaining += next_stage_time;
+            }
+        }
+
+        self.running = Some((color.opposite(), now));
+    }
+
+    /// Stops the running clock, charging the player for the time they have used
+    pub fn stop(&mut self) {
+        if let Some((color, started)) = self.running.take() {
+            let counted = self.counted(color, started);
+            let side = self.side_mut(color);
+            side.remaining = side.remaining.saturating_sub(counted);
+        }
+    }
+
+    /// Restarts the given player's clock after it was stopped
+    pub fn resume(&mut self, color: PieceColor) {
+        if self.running.is_none() && self.flagged.is_none() {
+            self.running = Some((color, self.time.now()));
+        }
+    }
+
+    /// Returns the time counted against the given player since their turn started
+    fn counted(&self, color: PieceColor, started: Duration) -> Duration {
+        let spent = self.time.now().saturating_sub(started);
+        match self.control.stage(self.side(color).stage).bonus {
+            Bonus::Delay(delay) => spent.saturating_sub(delay),
+            _ => spent,
+        }
+    }
+
+    fn side(&self, color: PieceColor) -> &Side {
+        match color {
+            PieceColor::White => &self.white,
+            PieceColor::Black => &self.black,
+        }
+    }
+
+    fn side_mut(&mut self, color: PieceColor) -> &mut Side {
+        match color {
+            PieceColor::White => &mut self.white,
+            PieceColor::Black => &mut self.black,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clock(control: &str) -> Clock<ManualTime> {
+        let control = control.parse().expect("the time control is valid");
+        Clock::with_time_source(control, ManualTime::new())
+    }
+
+    fn advance(clock: &Clock<ManualTime>, seconds: u64) {
+        clock.time_source().advance(Duration::from_secs(seconds));
+    }
+
+    fn seconds(seconds: u64) -> Duration {
+        Duration::from_secs(seconds)
+    }
+
+    #[test]
+    fn sudden_death() {
+        let mut clock = clock("1");
+        clock.press(PieceColor::White);
+        assert_eq!(clock.running(), Some(PieceColor::Black));
+        advance(&clock, 10);
+        assert_eq!(clock.remaining(PieceColor::Black), seconds(50));
+        assert_eq!(clock.remaining(PieceColor::White), seconds(60));
+
+        clock.press(PieceColor::Black);
+        advance(&clock, 5);
+        assert_eq!(clock.remaining(PieceColor::Black), seconds(50));
+        assert_eq!(clock.remaining(PieceColor::White), seconds(55));
+        assert_eq!(clock.moves_to_go(PieceColor::White), None);
+    }
+
+    #[test]
+    fn fischer() {
+        let mut clock = clock("1+2");
+        clock.press(PieceColor::White);
+        advance(&clock, 10);
+        clock.press(PieceColor::Black);
+        assert_eq!(clock.remaining(PieceColor::Black), seconds(52));
+        assert_eq!(clock.increment(PieceColor::Black), seconds(2));
+    }
+
+    #[test]
+    fn bronstein() {
+        let mut clock = clock("1b5");
+        clock.press(PieceColor::White);
+        advance(&clock, 3);
+        clock.press(PieceColor::Black);
+        // time spent within the delay is all given back
+        assert_eq!(clock.remaining(PieceColor::Black), seconds(60));
+
+        clock.press(PieceColor::White);
+        advance(&clock, 10);
+        clock.press(PieceColor::Black);
+        assert_eq!(clock.remaining(PieceColor::Black), seconds(55));
+    }
+
+    #[test]
+    fn simple_delay() {
+        let mut clock = clock("1d5");
+        clock.press(PieceColor::White);
+        advance(&clock, 3);
+        assert_eq!(clock.remaining(PieceColor::Black), seconds(60));
+        assert_eq!(clock.delay_remaining(PieceColor::Black), Some(seconds(2)));
+        assert_eq!(clock.delay_remaining(PieceColor::White), None);
+
+        advance(&clock, 5);
+        assert_eq!(clock.remaining(PieceColor::Black), seconds(57));
+        clock.press(PieceColor::Black);
+        assert_eq!(clock.remaining(PieceColor::Black), seconds(57));
+    }
+
+    #[test]
+    fn stage_rollover() {
+        let mut clock = clock("2/1, 1");
+        clock.press(PieceColor::White);
+        assert_eq!(clock.moves_to_go(PieceColor::Black), Some(2));
+        advance(&clock, 10);
+        clock.press(PieceColor::Black);
+        assert_eq!(clock.moves_to_go(PieceColor::Black), Some(1));
+
+        clock.press(PieceColor::White);
+        advance(&clock, 10);
+        clock.press(PieceColor::Black);
+        // the second stage's time is added once the first stage's moves are made
+        assert_eq!(clock.remaining(PieceColor::Black), seconds(100));
+        assert_eq!(clock.moves_to_go(PieceColor::Black), None);
+    }
+
+    #[test]
+    fn flagging() {
+        let mut clock = clock("1");
+        clock.press(PieceColor::White);
+        advance(&clock, 59);
+        assert_eq!(clock.flagged(), None);
+        advance(&clock, 2);
+        assert_eq!(clock.flagged(), Some(PieceColor::Black));
+        assert_eq!(clock.remaining(PieceColor::Black), Duration::ZERO);
+        assert_eq!(clock.running(), None);
+
+        // a flagged clock can no longer be pressed
+        clock.press(PieceColor::Black);
+        assert_eq!(clock.running(), None);
+    }
+
+    #[test]
+    fn stages_need_moves() {
+        let stage = Stage {
+            moves: Some(0),
+            time: seconds(60),
+            bonus: Bonus::None,
+        };
+        assert!(TimeControl::new(vec![stage]).is_err());
+        assert!(TimeControl::new(Vec::new()).is_err());
+    }
+
+    #[test]
+    fn parsing() {
+        let control: TimeControl = "40/90+30, 30+30".parse().unwrap();
+        assert_eq!(control.stages().len(), 2);
+        assert_eq!(control.stages()[0].moves, Some(40));
+        assert_eq!(control.stages()[0].bonus, Bonus::Fischer(seconds(30)));
+        assert_eq!(control.to_string(), "40/90+30, 30+30");
+
+        assert!("0/5".parse::<TimeControl>().is_err());
+        assert!("x/5".parse::<TimeControl>().is_err());
+        assert!("0".parse::<TimeControl>().is_err());
+        assert!("1e20".parse::<TimeControl>().is_err());
+        assert!("5+1e20".parse::<TimeControl>().is_err());
+    }
+
+    #[test]
+    fn fractions_round_trip() {
+        for text in ["3+0.5", "0.5+2", "40/90.5d1.25, 1.5b0.5"] {
+            let control: TimeControl = text.parse().unwrap();
+            assert_eq!(control.to_string(), text);
+        }
+        let control: TimeControl = "3+0.5".parse().unwrap();
+        assert_eq!(
+            control.stages()[0].bonus,
+            Bonus::Fischer(Duration::from_millis(500))
+        );
+    }
+}
@@ -0,0 +1,510 @@
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    board::Board,
+    eco,
+    game::{Game, NodeId},
+    pgn::{self, Headers},
+    piece::{PieceColor, PieceKind},
+};
+
+/// The bytes every database starts with, changed whenever the format changes
+const MAGIC: &[u8; 8] = b"CHSDB001";
+
+/// The kinds of piece counted in a material signature, in the order they are packed
+const SIGNATURE_KINDS: [PieceKind; 5] = [
+    PieceKind::Pawn,
+    PieceKind::Knight,
+    PieceKind::Bishop,
+    PieceKind::Rook,
+    PieceKind::Queen,
+];
+
+/// What games to find in a database, where every field left empty matches any game
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Query {
+    /// Part of the name of either player, in any case
+    pub player: String,
+
+    /// Part of the name of the event, in any case
+    pub event: String,
+
+    /// The earliest date, i.e. "2019" or "2019.06.01"
+    pub from: String,
+
+    /// The latest date, i.e. "2020" or "2020.12"
+    pub to: String,
+
+    /// The result, i.e. "1-0"
+    pub result: String,
+
+    /// The start of the opening's ECO code, i.e. "B" or "C65"
+    pub eco: String,
+
+    /// A position the main line reaches, in Forsyth-Edwards Notation
+    pub fen: String,
+
+    /// Material the main line reaches, i.e. "Q v RR", as parsed by [`MaterialPattern`]
+    pub material: String,
+}
+
+/// The pieces each side has, for finding games that reach an imbalance such as a queen against
+/// two rooks
+///
+/// Patterns are written as the pieces of one side, "v" or "vs", and the pieces of the other, in
+/// either order, i.e. "Q v RR" or "KRB vs KR". Kings may be left out. Pawns are only compared if
+/// the pattern names any, so "R v B" matches a rook against a bishop with any pawns.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MaterialPattern {
+    /// The number of each kind of piece in [`SIGNATURE_KINDS`] of each side
+    sides: [[u8; 5]; 2],
+    pawns: bool,
+}
+
+impl MaterialPattern {
+    /// Parses a pattern such as "Q v RR"
+    pub fn parse(pattern: &str) -> Result<Self, &'static str> {
+        let pattern = pattern.to_ascii_uppercase();
+        let (first, second) = pattern
+            .split_once("VS")
+            .or_else(|| pattern.split_once('V'))
+            .ok_or("expected the pieces of each side, i.e. Q v RR")?;
+
+        let mut sides = [[0; 5]; 2];
+        let mut pawns = false;
+        for (side, pieces) in sides.iter_mut().zip([first, second]) {
+            for letter in pieces.chars().filter(|c| !c.is_whitespace()) {
+                let kind = PieceKind::from_letter(letter).ok_or("unknown piece letter")?;
+                pawns |= kind == PieceKind::Pawn;
+                if let Some(index) = SIGNATURE_KINDS.iter().position(|&other| other == kind) {
+                    side[index] += 1;
+                }
+            }
+        }
+        Ok(Self { sides, pawns })
+    }
+
+    /// Returns true if the material of the given signature matches the pattern, with either side
+    /// having the pieces named first
+    fn matches(&self, signature: u64) -> bool {
+        let counts = |color: usize| {
+            let mut side = [0; 5];
+            for (index, count) in side.iter_mut().enumerate() {
+                *count = (signature >> (4 * (color * 5 + index)) & 0xF) as u8;
+            }
+            side
+        };
+        let (white, black) = (counts(0), counts(1));
+        // the pawns are the first kind, so they are skipped unless the pattern names any
+        let skip = (!self.pawns) as usize;
+        let same = |a: &[u8; 5], b: &[u8; 5]| a[skip..] == b[skip..];
+        (same(&white, &self.sides[0]) && same(&black, &self.sides[1]))
+            || (same(&white, &self.sides[1]) && same(&black, &self.sides[0]))
+    }
+}
+
+/// Returns the number of each kind of piece but the king each side has in the given position,
+/// packed four bits to a count
+fn material_signature(board: &Board) -> u64 {
+    let mut signature = 0;
+    for (_, piece) in board.pieces() {
+        let color = match piece.color {
+            PieceColor::White => 0,
+            PieceColor::Black => 1,
+        };
+        if let Some(index) = SIGNATURE_KINDS.iter().position(|&kind| kind == piece.kind) {
+            let shift = 4 * (color * 5 + index);
+            // promotions can make more than fifteen of a piece, which is then counted as fifteen
+            if signature >> shift & 0xF < 0xF {
+                signature += 1 << shift;
+            }
+        }
+    }
+    signature
+}
+
+/// What the database keeps in memory about one of its games
+struct Entry {
+    headers: Headers,
+
+    /// The distinct material signatures of the positions in the main line
+    materials: Vec<u64>,
+
+    /// Where the game's PGN is in the file, and its length in bytes
+    offset: u64,
+    length: u32,
+}
+
+/// A collection of games stored in one file, searchable by their tags, the positions they reach,
+/// and their material
+///
+/// Each game is stored as its tags, the Zobrist keys and material of the positions in its main
+/// line, and its PGN. Everything but the PGN is read into memory when the database is opened, so
+/// searches do not touch the disk, and a game's PGN is read only when it is opened.
+pub struct Database {
+    path: PathBuf,
+    entries: Vec<Entry>,
+
+    /// The games reaching each position, by its Zobrist key
+    positions: HashMap<u64, Vec<u32>>,
+}
+
+impl Database {
+    /// Creates a new, empty database in the given file, replacing anything already there
+    pub fn create(path: &Path) -> Result<Self, &'static str> {
+        let mut file = File::create(path).map_err(|_| "unable to create the database")?;
+        file.write_all(MAGIC)
+            .map_err(|_| "unable to create the database")?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            entries: Vec::new(),
+            positions: HashMap::new(),
+        })
+    }
+
+    /// Opens the database in the given file
+    pub fn open(path: &Path) -> Result<Self, &'static str> {
+        let file = File::open(path).map_err(|_| "unable to read the database")?;
+        let mut reader = BufReader::new(file);
+        let mut magic = [0; MAGIC.len()];
+        reader
+            .read_exact(&mut magic)
+            .map_err(|_| "not a game database")?;
+        if &magic != MAGIC {
+            return Err("not a game database");
+        }
+
+        let mut database = Self {
+            path: path.to_path_buf(),
+            entries: Vec::new(),
+            positions: HashMap::new(),
+        };
+        let mut offset = MAGIC.len() as u64;
+        // a game cut short by a failed write is left out, along with anything after it
+        while let Ok(length) = read_u32(&mut reader) {
+            let mut bytes = vec![0; length as usize];
+            if reader.read_exact(&mut bytes).is_err() {
+                break;
+            }
+            let Some((entry, keys)) = read_entry(&bytes, offset + 4) else {
+                return Err("the database is damaged");
+            };
+            database.add(entry, keys);
+            offset += 4 + length as u64;
+        }
+        Ok(database)
+    }
+
+    /// Returns the file the database is stored in
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the number of games in the database
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if the database has no games
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the tags of the game with the given number
+    pub fn headers(&self, id: usize) -> &Headers {
+        &self.entries[id].headers
+    }
+
+    /// Reads the game with the given number from the file
+    pub fn game(&self, id: usize) -> Result<Game, &'static str> {
+        let entry = &self.entries[id];
+        let mut file = File::open(&self.path).map_err(|_| "unable to read the database")?;
+        let mut bytes = vec![0; entry.length as usize];
+        file.seek(SeekFrom::Start(entry.offset))
+            .and_then(|_| file.read_exact(&mut bytes))
+            .map_err(|_| "unable to read the database")?;
+        let text = String::from_utf8(bytes).map_err(|_| "the database is damaged")?;
+        pgn::read(&text)
+    }
+
+    /// Adds every game in the given PGN text to the database, returning the number added and the
+    /// number skipped because they could not be read
+    ///
+    /// Games without an ECO tag are given one if their opening is known.
+    pub fn import(&mut self, text: &str) -> Result<(usize, usize), &'static str> {
+        let file = OpenOptions::new()
+            .append(true)
+            .open(&self.path)
+            .map_err(|_| "unable to write the database")?;
+        let mut offset = file
+            .metadata()
+            .map_err(|_| "unable to write the database")?
+            .len();
+        let mut writer = BufWriter::new(file);
+
+        let (mut added, mut skipped) = (0, 0);
+        for game in pgn::Reader::new(text) {
+            let Ok(mut game) = game else {
+                skipped += 1;
+                continue;
+            };
+            let end = game.main_line().last().copied().unwrap_or(NodeId::ROOT);
+            if game.headers().get("ECO").is_none() {
+                if let Some(opening) = eco::classify_game(&game, end) {
+                    let headers = game.headers_mut();
+                    headers.set("ECO", opening.eco);
+                    headers.set("Opening", opening.name);
+                }
+            }
+
+            let mut board = game.start().clone();
+            let mut keys = vec![board.zobrist()];
+            let mut materials = vec![material_signature(&board)];
+            for r#move in game.moves_to(end) {
+                board.play_move(r#move);
+                keys.push(board.zobrist());
+                let material = material_signature(&board);
+                if !materials.contains(&material) {
+                    materials.push(material);
+                }
+            }
+
+            let pgn = pgn::write(&game);
+            let bytes = write_entry(game.headers(), &keys, &materials, &pgn);
+            writer
+                .write_all(&(bytes.len() as u32).to_le_bytes())
+                .and_then(|_| writer.write_all(&bytes))
+                .map_err(|_| "unable to write the database")?;
+
+            // the PGN is written last, so it ends where the entry does
+            let entry = Entry {
+                headers: game.headers().clone(),
+                materials,
+                offset: offset + 4 + (bytes.len() - pgn.len()) as u64,
+                length: pgn.len() as u32,
+            };
+            offset += 4 + bytes.len() as u64;
+            self.add(entry, keys);
+            added += 1;
+        }
+        writer.flush().map_err(|_| "unable to write the database")?;
+        Ok((added, skipped))
+    }
+
+    /// Returns the numbers of the games matching the given query, in the order they were added
+    pub fn search(&self, query: &Query) -> Result<Vec<usize>, &'static str> {
+        let material = match query.material.trim() {
+            "" => None,
+            pattern => Some(MaterialPattern::parse(pattern)?),
+        };
+        // a position narrows the search to the games reaching it, without looking at the rest
+        let reaching = match query.fen.trim() {
+            "" => None,
+            fen => {
+                let board = Board::from_fen(fen)?;
+                board.validate()?;
+                let key = board.zobrist();
+                Some(self.positions.get(&key).map_or(&[][..], Vec::as_slice))
+            }
+        };
+
+        let contains = |tag: &str, part: &str| {
+            let part = part.trim().to_lowercase();
+            part.is_empty() || tag.to_lowercase().contains(&part)
+        };
+        let matches = |entry: &Entry| {
+            let tag = |name: &str| entry.headers.get(name).unwrap_or_default();
+            let date = tag("Date");
+            let known_date = date.starts_with(|c: char| c.is_ascii_digit());
+            let (from, to) = (query.from.trim(), query.to.trim());
+            (contains(tag("White"), &query.player) || contains(tag("Black"), &query.player))
+                && contains(tag("Event"), &query.event)
+                && (from.is_empty() || (known_date && date >= from))
+                // a partial date covers the whole year or month it names
+                && (to.is_empty()
+                    || (known_date && date.as_bytes()[..to.len().min(date.len())] <= *to.as_bytes()))
+                && (query.result.is_empty() || tag("Result") == query.result)
+                && tag("ECO").starts_with(query.eco.trim().to_uppercase().as_str())
+                && material.is_none_or(|pattern| {
+                    entry.materials.iter().any(|&signature| pattern.matches(signature))
+                })
+        };
+
+        let ids: Vec<usize> = match reaching {
+            Some(ids) => ids.iter().map(|&id| id as usize).collect(),
+            None => (0..self.entries.len()).collect(),
+        };
+        Ok(ids
+            .into_iter()
+            .filter(|&id| matches(&self.entries[id]))
+            .collect())
+    }
+
+    /// Adds a game read from or written to the file, with the keys of the positions it reaches
+    fn add(&mut self, entry: Entry, mut keys: Vec<u64>) {
+        let id = self.entries.len() as u32;
+        // a position repeated within a game lists the game once
+        keys.sort_unstable();
+        keys.dedup();
+        for key in keys {
+            self.positions.entry(key).or_default().push(id);
+        }
+        self.entries.push(entry);
+    }
+}
+
+/// Writes a game's tags, the keys and material of its positions, and its PGN, each preceded by
+/// its length
+fn write_entry(headers: &Headers, keys: &[u64], materials: &[u64], pgn: &str) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let tags: Vec<_> = headers.iter().collect();
+    bytes.extend_from_slice(&(tags.len() as u32).to_le_bytes());
+    for (name, value) in tags {
+        for text in [name, value] {
+            bytes.extend_from_slice(&(text.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(text.as_bytes());
+        }
+    }
+    for list in [keys, materials] {
+        bytes.extend_from_slice(&(list.len() as u32).to_le_bytes());
+        for value in list {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+    bytes.extend_from_slice(&(pgn.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(pgn.as_bytes());
+    bytes
+}
+
+/// Reads an entry written by [`write_entry`] that starts at the given offset in the file,
+/// returning it with the keys of its positions
+fn read_entry(bytes: &[u8], offset: u64) -> Option<(Entry, Vec<u64>)> {
+    let mut rest = bytes;
+    let text = |rest: &mut &[u8]| {
+        let length = read_u32(rest).ok()? as usize;
+        let (text, after) = rest.split_at_checked(length)?;
+        *rest = after;
+        String::from_utf8(text.to_vec()).ok()
+    };
+
+    let mut headers = Headers::new();
+    for _ in 0..read_u32(&mut rest).ok()? {
+        let name = text(&mut rest)?;
+        let value = text(&mut rest)?;
+        headers.set(&name, value);
+    }
+    let mut lists = [Vec::new(), Vec::new()];
+    for list in &mut lists {
+        for _ in 0..read_u32(&mut rest).ok()? {
+            let mut value = [0; 8];
+            rest.read_exact(&mut value).ok()?;
+            list.push(u64::from_le_bytes(value));
+        }
+    }
+    let [keys, materials] = lists;
+    let length = read_u32(&mut rest).ok()?;
+    // the PGN is the rest of the entry
+    if rest.len() != length as usize {
+        return None;
+    }
+
+    let entry = Entry {
+        headers,
+        materials,
+        offset: offset + (bytes.len() - rest.len()) as u64,
+        length,
+    };
+    Some((entry, keys))
+}
+
+/// Reads a little-endian `u32`
+fn read_u32(reader: &mut impl Read) -> std::io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Scratch;
+
+    fn signature(fen: &str) -> u64 {
+        material_signature(&Board::from_fen(fen).unwrap())
+    }
+
+    #[test]
+    fn material_patterns() {
+        let pattern = MaterialPattern::parse("Q v RR").unwrap();
+        assert_eq!(pattern, MaterialPattern::parse("q vs rr").unwrap());
+        assert_eq!(pattern, MaterialPattern::parse("KQ v KRR").unwrap());
+
+        // either side may have the queen, and pawns are ignored
+        assert!(pattern.matches(signature("1r1r2k1/5ppp/8/8/8/8/5PPP/3Q2K1 w - - 0 1")));
+        assert!(pattern.matches(signature("3q2k1/8/8/8/8/8/8/1R1R2K1 w - - 0 1")));
+        assert!(!pattern.matches(signature("3r2k1/8/8/8/8/8/8/3Q2K1 w - - 0 1")));
+        assert!(!pattern.matches(signature("1r1r2k1/8/8/8/8/8/8/2BQ2K1 w - - 0 1")));
+
+        // named pawns are counted exactly
+        let pattern = MaterialPattern::parse("RP v R").unwrap();
+        assert!(pattern.matches(signature("3r2k1/8/8/8/8/8/5P2/3R2K1 w - - 0 1")));
+        assert!(!pattern.matches(signature("3r2k1/8/8/8/8/8/4PP2/3R2K1 w - - 0 1")));
+
+        assert!(MaterialPattern::parse("QRR").is_err());
+        assert!(MaterialPattern::parse("Q v X").is_err());
+    }
+
+    #[test]
+    fn import_and_search() {
+        let scratch = Scratch::new("database-import");
+        let path = scratch.path().join("games.db");
+        let mut database = Database::create(&path).unwrap();
+        let text = concat!(
+            "[White \"Morphy\"]\n[Black \"Duke of Brunswick\"]\n[Date \"1858.11.02\"]\n",
+            "[Result \"1-0\"]\n\n1. e4 e5 2. Nf3 d6 3. d4 Bg4 1-0\n\n",
+            "[White \"Carlsen\"]\n[Black \"Anand\"]\n[Date \"2013.11.22\"]\n",
+            "[Result \"1/2-1/2\"]\n\n1. d4 d5 1/2-1/2\n\n",
+            "1. e4 e6 2. Kxe8 *\n\n",
+            "[Date \"2é58\"]\n\n1. e4 *\n",
+        );
+        assert_eq!(database.import(text).unwrap(), (3, 1));
+
+        let search = |query: Query| database.search(&query).unwrap();
+        assert_eq!(search(Query::default()), [0, 1, 2]);
+        let query = Query {
+            player: String::from("carlsen"),
+            ..Query::default()
+        };
+        assert_eq!(search(query), [1]);
+        let query = Query {
+            from: String::from("1900"),
+            to: String::from("2013.11"),
+            ..Query::default()
+        };
+        assert_eq!(search(query), [1]);
+        // the cut lands inside the "é" of the last game's date
+        let query = Query {
+            to: String::from("18"),
+            ..Query::default()
+        };
+        assert_eq!(search(query), [0]);
+        let query = Query {
+            fen: String::from("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2"),
+            ..Query::default()
+        };
+        assert_eq!(search(query), [0]);
+
+        // the games and what is known about them survive reopening the file
+        let reopened = Database::open(&path).unwrap();
+        assert_eq!(reopened.len(), 3);
+        assert_eq!(reopened.headers(0).get("ECO"), Some("C41"));
+        let game = reopened.game(1).unwrap();
+        assert_eq!(game.headers().get("White"), Some("Carlsen"));
+        assert_eq!(game.main_line().len(), 2);
+    }
+}
@@ -0,0 +1,135 @@
+use std::{collections::HashMap, fmt::Display, sync::OnceLock};
+
+use crate::{
+    board::Board,
+    game::{Game, NodeId},
+};
+
+/// The openings, one per line after a header, as their ECO code, name, and moves in SAN
+/// separated by tabs
+const OPENINGS: &str = include_str!("../assets/openings.tsv");
+
+/// A named opening
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Opening {
+    /// The opening's code in the Encyclopaedia of Chess Openings, i.e. "C65"
+    pub eco: &'static str,
+
+    /// The opening's name, with its variation if any, i.e. "Ruy Lopez: Berlin Defense"
+    pub name: &'static str,
+}
+
+impl Display for Opening {
+    /// Formats the opening as its code and name, i.e. "C65 Ruy Lopez: Berlin Defense"
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.eco, self.name)
+    }
+}
+
+/// Returns the opening whose moves reach the given position, if any
+///
+/// Openings are found by the position they reach rather than the moves played, so a move order
+/// that transposes into an opening is named too.
+pub fn classify(board: &Board) -> Option<Opening> {
+    openings().get(&board.zobrist()).copied()
+}
+
+/// Returns the deepest opening reached on the way to the given move of a game, if any
+pub fn classify_game(game: &Game, id: NodeId) -> Option<Opening> {
+    let mut board = game.start().clone();
+    let mut opening = classify(&board);
+    for r#move in game.moves_to(id) {
+        board.play_move(r#move);
+        opening = classify(&board).or(opening);
+    }
+    opening
+}
+
+/// Returns the openings by the Zobrist key of the position they reach, reading them the first
+/// time they are needed
+fn openings() -> &'static HashMap<u64, Opening> {
+    static OPENINGS_BY_KEY: OnceLock<HashMap<u64, Opening>> = OnceLock::new();
+    OPENINGS_BY_KEY.get_or_init(|| {
+        let mut openings = HashMap::new();
+        for line in OPENINGS.lines().skip(1) {
+            let mut fields = line.split('\t');
+            let (Some(eco), Some(name), Some(moves)) =
+                (fields.next(), fields.next(), fields.next())
+            else {
+                continue;
+            };
+
+            let mut board = Board::new_init();
+            // move numbers are skipped, as they end with a period
+            for san in moves
+                .split_whitespace()
+                .filter(|token| !token.ends_with('.'))
+            {
+                let r#move = board.parse_san(san).expect("opening moves are legal");
+                board.play_move(r#move);
+            }
+            // where two lines reach the same position, the first listed names it
+            openings
+                .entry(board.zobrist())
+                .or_insert(Opening { eco, name });
+        }
+        openings
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pgn;
+
+    /// Returns the code and name of the opening reached by the given moves, if any
+    fn opening(moves: &str) -> Option<(&'static str, &'static str)> {
+        let mut board = Board::new_init();
+        for san in moves.split_whitespace() {
+            board.play_move(board.parse_san(san).unwrap());
+        }
+        classify(&board).map(|opening| (opening.eco, opening.name))
+    }
+
+    #[test]
+    fn known_openings() {
+        assert!(openings().len() > 400);
+        assert_eq!(opening(""), None);
+        assert_eq!(opening("e4 c6"), Some(("B10", "Caro-Kann Defense")));
+        assert_eq!(opening("e4 c6 d4"), Some(("B12", "Caro-Kann Defense")));
+        assert_eq!(
+            opening("e4 e5 Nf3 Nc6 Bb5 Nf6"),
+            Some(("C65", "Ruy Lopez: Berlin Defense"))
+        );
+        assert_eq!(opening("e4 e5 Nf3 Nc6 Bb5 Nf6 Qe2"), None);
+    }
+
+    #[test]
+    fn transpositions() {
+        assert_eq!(
+            opening("Nf3 Nc6 e4 e5 Bb5 Nf6"),
+            Some(("C65", "Ruy Lopez: Berlin Defense"))
+        );
+        assert_eq!(
+            opening("c4 e6 d4 d5"),
+            Some(("D30", "Queen's Gambit Declined"))
+        );
+        assert_eq!(
+            opening("c4 e6 Nc3 d5 d4"),
+            Some(("D31", "Queen's Gambit Declined: Queen's Knight Variation"))
+        );
+    }
+
+    #[test]
+    fn games() {
+        let game = pgn::read("1. e4 e5 2. Nf3 Nc6 3. Bb5 Nf6 4. Qe2 Bc5 5. Qc4 *").unwrap();
+        let line = game.main_line();
+        let named = |ply: usize| classify_game(&game, line[ply - 1]).map(|opening| opening.eco);
+        assert_eq!(named(1), Some("B00"));
+        assert_eq!(named(2), Some("C20"));
+        assert_eq!(named(5), Some("C60"));
+        // the deepest opening is kept once the game leaves the book
+        assert_eq!(named(9), Some("C65"));
+        assert_eq!(classify_game(&game, NodeId::ROOT), None);
+    }
+}
@@ -0,0 +1,233 @@
+use std::{
+    fmt::Display,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use crate::{board::Board, piece::PieceColor, r#move::Move, tablebase::Tablebase};
+
+use self::{strength::Strength, uci::UciEngine};
+
+/// The built-in engine's search and evaluation
+pub mod search;
+
+/// How strongly the built-in engine plays
+pub mod strength;
+
+/// Running the built-in engine for other chess programs over the Universal Chess Interface
+pub mod server;
+
+/// Talking to external engines over the Universal Chess Interface
+pub mod uci;
+
+/// The strongest level of the built-in engine, at which it plays at full strength
+pub const MAX_LEVEL: u8 = 10;
+
+/// The time held back from every move so engines never lose on time to overhead
+const MOVE_OVERHEAD: Duration = Duration::from_millis(50);
+
+/// How good a position is for the player to move
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Score {
+    /// An advantage in hundredths of a pawn
+    Centipawns(i32),
+
+    /// Checkmate in the given number of moves, negative if the player to move is being mated
+    Mate(i32),
+}
+
+impl Score {
+    /// Returns the score from the other player's point of view
+    pub const fn negate(self) -> Self {
+        match self {
+            Self::Centipawns(centipawns) => Self::Centipawns(-centipawns),
+            Self::Mate(moves) => Self::Mate(-moves),
+        }
+    }
+}
+
+impl Display for Score {
+    /// Formats the score in pawns, i.e. "+1.25", or as a mate, i.e. "#3" or "#-2"
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Centipawns(centipawns) => write!(f, "{:+.2}", *centipawns as f32 / 100.0),
+            Self::Mate(moves) => write!(f, "#{moves}"),
+        }
+    }
+}
+
+/// What an engine reports about its search so far
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Info {
+    /// Which of the best lines this is, from 1 for the best
+    pub multi_pv: u32,
+
+    /// The depth searched, in plies
+    pub depth: u32,
+
+    /// The score of the best line, for the player to move
+    pub score: Score,
+
+    /// The number of positions searched
+    pub nodes: u64,
+
+    /// The time spent searching
+    pub time: Duration,
+
+    /// The best line found, starting with the best move
+    pub pv: Vec<Move>,
+}
+
+/// The time both players have left, for engines to plan how long to think
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ClockTime {
+    /// White's remaining time
+    pub white: Duration,
+
+    /// Black's remaining time
+    pub black: Duration,
+
+    /// The time white gets back for each move
+    pub white_increment: Duration,
+
+    /// The time black gets back for each move
+    pub black_increment: Duration,
+
+    /// The number of moves until the next time control, or `None` for the rest of the game
+    pub moves_to_go: Option<u32>,
+}
+
+/// When an engine should stop thinking, and how many lines it should report
+///
+/// The search stops at whichever limit is reached first. With no limits, it continues until it
+/// is stopped.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Limits {
+    /// The deepest search, in plies
+    pub depth: Option<u32>,
+
+    /// The most positions to search
+    pub nodes: Option<u64>,
+
+    /// The time to think for
+    pub move_time: Option<Duration>,
+
+    /// The players' clocks, from which the engine decides how long to think
+    pub clock: Option<ClockTime>,
+
+    /// The number of best lines to report, or `None` for only the best
+    pub multi_pv: Option<u32>,
+}
+
+impl Limits {
+    /// Returns the longest the given player should think, if limited by time
+    pub fn time_for(&self, color: PieceColor) -> Option<Duration> {
+        let from_clock = self.clock.map(|clock| {
+            let (remaining, increment) = match color {
+                PieceColor::White => (clock.white, clock.white_increment),
+                PieceColor::Black => (clock.black, clock.black_increment),
+            };
+            // spread the time over the moves left, assuming a long game when the count is unknown
+            let moves = clock.moves_to_go.unwrap_or(30).clamp(1, 30);
+            let planned = remaining / moves + increment * 3 / 4;
+            planned
+                .min(remaining / 2)
+                .saturating_sub(MOVE_OVERHEAD)
+                .max(Duration::from_millis(10))
+        });
+        match (self.move_time, from_clock) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+}
+
+/// Something an engine reports while thinking
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// Progress of the search
+    Info(Info),
+
+    /// The move the engine chose, or `None` if it has no legal moves
+    BestMove(Option<Move>),
+
+    /// The engine failed, and will not report a move
+    Failed(&'static str),
+}
+
+/// An engine thinking about a position on a background thread
+///
+/// The engine is stopped when this is dropped, so the search can be cancelled by dropping it.
+pub struct Thinking {
+    events: Receiver<Event>,
+    stop: Arc<AtomicBool>,
+}
+
+impl Thinking {
+    /// Runs the given search on a new thread, passing it where to send its events and a flag
+    /// that is set when it should stop
+    fn spawn(search: impl FnOnce(Sender<Event>, Arc<AtomicBool>) + Send + 'static) -> Self {
+        let (sender, events) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&stop);
+        thread::spawn(move || search(sender, flag));
+        Self { events, stop }
+    }
+
+    /// Takes every event reported since the last call, without waiting
+    pub fn events(&self) -> Vec<Event> {
+        self.events.try_iter().collect()
+    }
+
+    /// Asks the engine to stop thinking and report its best move so far
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Drop for Thinking {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// A chess engine that can play moves
+pub enum Engine {
+    /// The built-in engine, playing at the given strength with the given endgame tablebase
+    BuiltIn(Strength, Option<Arc<Tablebase>>),
+
+    /// An external engine, talking over the Universal Chess Interface
+    External(UciEngine),
+}
+
+impl Engine {
+    /// Starts thinking about the position reached by playing the given moves from the given
+    /// starting position
+    pub fn think(&self, start: &Board, moves: &[Move], limits: Limits) -> Thinking {
+        match self {
+            Self::BuiltIn(strength, tablebase) => {
+                let mut board = start.clone();
+                for &r#move in moves {
+                    board.play_move(r#move);
+                }
+                let strength = *strength;
+                let tablebase = tablebase.clone();
+                Thinking::spawn(move |events, stop| {
+                    let tablebase = tablebase.as_deref();
+                    let best =
+                        search::search(&board, &limits, &strength, tablebase, &stop, |info| {
+                            // the receiver may already be gone if the search was cancelled
+                            let _ = events.send(Event::Info(info.clone()));
+                        });
+                    let _ = events.send(Event::BestMove(best));
+                })
+            }
+            Self::External(engine) => engine.think(start, moves, limits),
+        }
+    }
+}
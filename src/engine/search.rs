@@ -0,0 +1,524 @@
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Instant,
+};
+
+use crate::{
+    board::{Board, Square},
+    piece::{PieceColor, PieceKind},
+    r#move::Move,
+    tablebase::{Tablebase, Wdl},
+};
+
+use super::{strength::Strength, Info, Limits, Score};
+
+/// The score of checkmating on the next move, less one for every ply further away
+const MATE: i32 = 30_000;
+
+/// The score of a position the tablebase says is won, less one for every ply from the root,
+/// below any mate the search finds itself
+const TABLEBASE_WIN: i32 = MATE - 1000;
+
+/// A score beyond any real one, used as the initial search window
+const INFINITY: i32 = 32_000;
+
+/// The deepest search, and the most plies a mate can be found beyond the root
+const MAX_DEPTH: u32 = 64;
+
+/// How many positions are searched between checks of the clock
+const CHECK_INTERVAL: u64 = 1024;
+
+/// The most moves a weakened engine weighs against each other when choosing one at random
+const MAX_CANDIDATES: u32 = 8;
+
+/// The value of each kind of piece, in centipawns, in the order of [`piece_index`]
+const PIECE_VALUES: [i32; 6] = [100, 320, 330, 500, 900, 0];
+
+/// Bonuses for each kind of piece standing on each square, from white's side with the eighth
+/// rank first, in the order of [`piece_index`]
+#[rustfmt::skip]
+const PIECE_SQUARE_TABLES: [[i32; 64]; 6] = [
+    // pawn
+    [
+         0,  0,  0,  0,  0,  0,  0,  0,
+        50, 50, 50, 50, 50, 50, 50, 50,
+        10, 10, 20, 30, 30, 20, 10, 10,
+         5,  5, 10, 25, 25, 10,  5,  5,
+         0,  0,  0, 20, 20,  0,  0,  0,
+         5, -5,-10,  0,  0,-10, -5,  5,
+         5, 10, 10,-20,-20, 10, 10,  5,
+         0,  0,  0,  0,  0,  0,  0,  0,
+    ],
+    // knight
+    [
+        -50,-40,-30,-30,-30,-30,-40,-50,
+        -40,-20,  0,  0,  0,  0,-20,-40,
+        -30,  0, 10, 15, 15, 10,  0,-30,
+        -30,  5, 15, 20, 20, 15,  5,-30,
+        -30,  0, 15, 20, 20, 15,  0,-30,
+        -30,  5, 10, 15, 15, 10,  5,-30,
+        -40,-20,  0,  5,  5,  0,-20,-40,
+        -50,-40,-30,-30,-30,-30,-40,-50,
+    ],
+    // bishop
+    [
+        -20,-10,-10,-10,-10,-10,-10,-20,
+        -10,  0,  0,  0,  0,  0,  0,-10,
+        -10,  0,  5, 10, 10,  5,  0,-10,
+        -10,  5,  5, 10, 10,  5,  5,-10,
+        -10,  0, 10, 10, 10, 10,  0,-10,
+        -10, 10, 10, 10, 10, 10, 10,-10,
+        -10,  5,  0,  0,  0,  0,  5,-10,
+        -20,-10,-10,-10,-10,-10,-10,-20,
+    ],
+    // rook
+    [
+         0,  0,  0,  0,  0,  0,  0,  0,
+         5, 10, 10, 10, 10, 10, 10,  5,
+        -5,  0,  0,  0,  0,  0,  0, -5,
+        -5,  0,  0,  0,  0,  0,  0, -5,
+        -5,  0,  0,  0,  0,  0,  0, -5,
+        -5,  0,  0,  0,  0,  0,  0, -5,
+        -5,  0,  0,  0,  0,  0,  0, -5,
+         0,  0,  0,  5,  5,  0,  0,  0,
+    ],
+    // queen
+    [
+        -20,-10,-10, -5, -5,-10,-10,-20,
+        -10,  0,  0,  0,  0,  0,  0,-10,
+        -10,  0,  5,  5,  5,  5,  0,-10,
+         -5,  0,  5,  5,  5,  5,  0, -5,
+          0,  0,  5,  5,  5,  5,  0, -5,
+        -10,  5,  5,  5,  5,  5,  0,-10,
+        -10,  0,  5,  0,  0,  0,  0,-10,
+        -20,-10,-10, -5, -5,-10,-10,-20,
+    ],
+    // king, which should stay sheltered while there are pieces to attack it
+    [
+        -30,-40,-40,-50,-50,-40,-40,-30,
+        -30,-40,-40,-50,-50,-40,-40,-30,
+        -30,-40,-40,-50,-50,-40,-40,-30,
+        -30,-40,-40,-50,-50,-40,-40,-30,
+        -20,-30,-30,-40,-40,-30,-30,-20,
+        -10,-20,-20,-20,-20,-20,-20,-10,
+         20, 20,  0,  0,  0,  0, 20, 20,
+         20, 30, 10,  0,  0, 10, 30, 20,
+    ],
+];
+
+/// Bonuses for the king standing on each square in the endgame, where it should be active
+#[rustfmt::skip]
+const KING_ENDGAME_TABLE: [i32; 64] = [
+    -50,-40,-30,-20,-20,-30,-40,-50,
+    -30,-20,-10,  0,  0,-10,-20,-30,
+    -30,-10, 20, 30, 30, 20,-10,-30,
+    -30,-10, 30, 40, 40, 30,-10,-30,
+    -30,-10, 30, 40, 40, 30,-10,-30,
+    -30,-10, 20, 30, 30, 20,-10,-30,
+    -30,-30,  0,  0,  0,  0,-30,-30,
+    -50,-30,-30,-30,-30,-30,-30,-50,
+];
+
+/// The material, not counting kings and pawns, below which the position counts as an endgame
+const ENDGAME_MATERIAL: i32 = 1300;
+
+/// Searches the given position for a move to play at the given strength, reporting each of the
+/// best lines after every depth
+///
+/// The search deepens one ply at a time until a limit is reached or `stop` is set, and returns
+/// the best move of the deepest search, or `None` if there are no legal moves. A weakened engine
+/// may instead choose a slightly worse move, or the best move it saw at its horizon.
+///
+/// At full strength, the engine only considers the moves the tablebase, if any, says are best
+/// at the root, and trusts its results for positions reached by captures and pawn moves.
+pub fn search(
+    board: &Board,
+    limits: &Limits,
+    strength: &Strength,
+    tablebase: Option<&Tablebase>,
+    stop: &AtomicBool,
+    mut report: impl FnMut(&Info),
+) -> Option<Move> {
+    let start = Instant::now();
+    let time = limits.time_for(board.to_move());
+    let max_nodes = match (limits.nodes, strength.nodes) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    };
+    let max_depth = match (limits.depth, strength.depth) {
+        (Some(a), Some(b)) => a.min(b),
+        (a, b) => a.or(b).unwrap_or(MAX_DEPTH),
+    };
+    // a weakened engine plays endgames by itself, as the tablebase would make it perfect
+    let tablebase = tablebase.filter(|_| *strength == Strength::FULL);
+    let root_moves = tablebase
+        .and_then(|tablebase| tablebase.probe(board))
+        .map(|probe| probe.best_moves)
+        .filter(|moves| !moves.is_empty());
+    let mut searcher = Searcher {
+        board: board.clone(),
+        tablebase,
+        stop,
+        deadline: time.map(|time| start + time),
+        max_nodes,
+        nodes: 0,
+        stopped: false,
+        root_order: Vec::new(),
+        excluded: Vec::new(),
+        root_moves,
+    };
+
+    let legal = match &searcher.root_moves {
+        Some(moves) => moves.clone(),
+        None => board.legal_moves(),
+    };
+    let first_legal = *legal.first()?;
+    let reported = limits.multi_pv.unwrap_or(1).clamp(1, legal.len() as u32);
+    // a weakened engine also scores the moves it might choose instead of the best
+    let lines = match strength.randomness > 0 {
+        true => reported.max(MAX_CANDIDATES.min(legal.len() as u32)),
+        false => reported,
+    };
+
+    let mut candidates = Vec::new();
+    let mut horizon_candidates = None;
+    for depth in 1..=max_depth.min(MAX_DEPTH) {
+        // each line after the best is found by searching again without the moves of the others
+        searcher.excluded.clear();
+        let mut scored = Vec::new();
+        for multi_pv in 1..=lines {
+            let mut pv = Vec::new();
+            let score = searcher.negamax(depth, 0, -INFINITY, INFINITY, &mut pv);
+
+            // an unfinished search is only trusted if the best move so far was searched first
+            let expected = searcher.root_order.get(multi_pv as usize - 1);
+            if searcher.stopped && pv.first() != expected && depth > 1 {
+                break;
+            }
+            let Some(&first) = pv.first() else {
+                break;
+            };
+            searcher.excluded.push(first);
+            scored.push((first, score));
+            if multi_pv <= reported {
+                report(&Info {
+                    multi_pv,
+                    depth,
+                    score: to_score(score),
+                    nodes: searcher.nodes,
+                    time: start.elapsed(),
+                    pv,
+                });
+            }
+            if searcher.stopped {
+                break;
+            }
+        }
+
+        // choosing among moves needs them all scored at the same depth
+        let complete = scored.len() == lines as usize;
+        let best_score = scored.first().map_or(0, |&(_, score)| score);
+        if complete || candidates.is_empty() || (strength.randomness == 0 && !scored.is_empty()) {
+            candidates = scored;
+        }
+        if complete && strength.horizon == Some(depth) {
+            horizon_candidates = Some(candidates.clone());
+        }
+
+        // the next depth searches the best moves of this one first
+        let mut root_order = searcher.excluded.clone();
+        root_order.extend(
+            searcher
+                .root_order
+                .iter()
+                .filter(|r#move| !searcher.excluded.contains(r#move)),
+        );
+        searcher.root_order = root_order;
+
+        // a deeper search is unlikely to finish in less than the time already spent
+        let half_time_used = time.is_some_and(|time| start.elapsed() * 2 > time);
+        if searcher.stopped || half_time_used || best_score.abs() >= MATE - MAX_DEPTH as i32 {
+            break;
+        }
+    }
+
+    // now and then, a human-like engine overlooks what it saw beyond its horizon
+    if let Some(horizon) = horizon_candidates {
+        if fastrand::f32() < strength.miss_chance {
+            candidates = horizon;
+        }
+    }
+    Some(choose(&candidates, strength.randomness).unwrap_or(first_legal))
+}
+
+/// Chooses one of the given moves and their scores, at random among those no more than
+/// `randomness` centipawns worse than the best, and more likely the closer they are to it
+fn choose(candidates: &[(Move, i32)], randomness: u32) -> Option<Move> {
+    let best_score = candidates.iter().map(|&(_, score)| score).max()?;
+    let temperature = randomness as f32 / 3.0;
+    let weighted: Vec<_> = candidates
+        .iter()
+        .filter(|&&(_, score)| best_score - score <= randomness as i32)
+        .map(|&(r#move, score)| {
+            let weight = match randomness {
+                0 => 1.0,
+                _ => (-(best_score - score) as f32 / temperature).exp(),
+            };
+            (r#move, weight)
+        })
+        .collect();
+
+    let mut pick = fastrand::f32() * weighted.iter().map(|(_, weight)| weight).sum::<f32>();
+    for &(r#move, weight) in &weighted {
+        if pick < weight {
+            return Some(r#move);
+        }
+        pick -= weight;
+    }
+    weighted.first().map(|&(r#move, _)| r#move)
+}
+
+/// Returns how good the position is for the player to move, in centipawns, without searching
+pub fn evaluate(board: &Board) -> i32 {
+    let endgame = board
+        .pieces()
+        .filter(|(_, piece)| !matches!(piece.kind, PieceKind::Pawn | PieceKind::King))
+        .map(|(_, piece)| PIECE_VALUES[piece_index(piece.kind)])
+        .sum::<i32>()
+        <= ENDGAME_MATERIAL * 2;
+
+    let score: i32 = board
+        .pieces()
+        .map(|(square, piece)| {
+            let index = table_index(square, piece.color);
+            let table = match (piece.kind, endgame) {
+                (PieceKind::King, true) => &KING_ENDGAME_TABLE,
+                (kind, _) => &PIECE_SQUARE_TABLES[piece_index(kind)],
+            };
+            let value = PIECE_VALUES[piece_index(piece.kind)] + table[index];
+            match piece.color {
+                PieceColor::White => value,
+                PieceColor::Black => -value,
+            }
+        })
+        .sum();
+
+    match board.to_move() {
+        PieceColor::White => score,
+        PieceColor::Black => -score,
+    }
+}
+
+/// The state of a search in progress
+struct Searcher<'a> {
+    board: Board,
+    tablebase: Option<&'a Tablebase>,
+    stop: &'a AtomicBool,
+    deadline: Option<Instant>,
+    max_nodes: Option<u64>,
+    nodes: u64,
+    stopped: bool,
+
+    /// The moves to search first from the root, best first
+    root_order: Vec<Move>,
+
+    /// The moves not to search from the root, as they lead the lines already found
+    excluded: Vec<Move>,
+
+    /// The only moves to search from the root, if the tablebase knows the best ones
+    root_moves: Option<Vec<Move>>,
+}
+
+impl Searcher<'_> {
+    /// Returns the score of the position searched to the given depth, filling `pv` with the best
+    /// line found
+    fn negamax(
+        &mut self,
+        depth: u32,
+        ply: u32,
+        mut alpha: i32,
+        beta: i32,
+        pv: &mut Vec<Move>,
+    ) -> i32 {
+        if self.should_stop() {
+            return 0;
+        }
+        if ply > 0 && self.board.halfmove_clock() >= 100 {
+            return 0;
+        }
+        // the tablebase is only asked after captures and pawn moves, when the material changes
+        if ply > 0 && self.board.halfmove_clock() == 0 {
+            if let Some(wdl) = self.tablebase.and_then(|tb| tb.probe_wdl(&self.board)) {
+                return match wdl {
+                    Wdl::Win => TABLEBASE_WIN - ply as i32,
+                    Wdl::Loss => -TABLEBASE_WIN + ply as i32,
+                    Wdl::BlessedLoss | Wdl::Draw | Wdl::CursedWin => 0,
+                };
+            }
+        }
+
+        let color = self.board.to_move();
+        let in_check = self.board.is_in_check(color);
+        // checks are searched one ply deeper, so they are never cut off before they are answered
+        let depth = match in_check && ply < MAX_DEPTH {
+            true => depth + 1,
+            false => depth,
+        };
+        if depth == 0 || ply >= MAX_DEPTH {
+            return self.quiescence(ply, alpha, beta);
+        }
+        self.nodes += 1;
+
+        let mut moves = self.board.pseudo_legal_moves();
+        self.order(&mut moves);
+        if ply == 0 {
+            moves.retain(|r#move| !self.excluded.contains(r#move));
+            if let Some(root_moves) = &self.root_moves {
+                moves.retain(|r#move| root_moves.contains(r#move));
+            }
+            moves.sort_by_key(|r#move| {
+                let index = self.root_order.iter().position(|best| best == r#move);
+                index.unwrap_or(usize::MAX)
+            });
+        }
+
+        let mut legal = 0;
+        let mut line = Vec::new();
+        for r#move in moves {
+            self.board.play_move(r#move);
+            if self.board.is_in_check(color) {
+                self.board.undo_move(r#move);
+                continue;
+            }
+            legal += 1;
+
+            line.clear();
+            let score = -self.negamax(depth - 1, ply + 1, -beta, -alpha, &mut line);
+            self.board.undo_move(r#move);
+            if self.stopped {
+                return alpha;
+            }
+
+            if score > alpha {
+                alpha = score;
+                pv.clear();
+                pv.push(r#move);
+                pv.extend_from_slice(&line);
+                if alpha >= beta {
+                    break;
+                }
+            }
+        }
+
+        match (legal, in_check) {
+            (0, true) => -MATE + ply as i32,
+            (0, false) => 0,
+            _ => alpha,
+        }
+    }
+
+    /// Returns the score of the position once every capture worth making has been made
+    fn quiescence(&mut self, ply: u32, mut alpha: i32, beta: i32) -> i32 {
+        if self.should_stop() {
+            return 0;
+        }
+        self.nodes += 1;
+
+        // the player to move may decline every capture
+        let standing = evaluate(&self.board);
+        if standing >= beta || ply >= MAX_DEPTH * 2 {
+            return standing;
+        }
+        alpha = alpha.max(standing);
+
+        let color = self.board.to_move();
+        let mut moves: Vec<_> = self
+            .board
+            .pseudo_legal_moves()
+            .into_iter()
+            .filter(|r#move| r#move.captured().is_some() || r#move.promotion().is_some())
+            .collect();
+        self.order(&mut moves);
+
+        for r#move in moves {
+            self.board.play_move(r#move);
+            if self.board.is_in_check(color) {
+                self.board.undo_move(r#move);
+                continue;
+            }
+            let score = -self.quiescence(ply + 1, -beta, -alpha);
+            self.board.undo_move(r#move);
+            if self.stopped {
+                return alpha;
+            }
+
+            if score >= beta {
+                return score;
+            }
+            alpha = alpha.max(score);
+        }
+        alpha
+    }
+
+    /// Sorts moves so the most promising are searched first: promotions, then captures of the
+    /// most valuable pieces by the least valuable ones
+    fn order(&self, moves: &mut [Move]) {
+        moves.sort_by_cached_key(|r#move| {
+            let promotion = r#move
+                .promotion()
+                .map_or(0, |piece| PIECE_VALUES[piece_index(piece.kind)]);
+            let capture = r#move.captured().map_or(0, |captured| {
+                let attacker = self
+                    .board
+                    .get_piece(r#move.from())
+                    .map_or(0, |piece| PIECE_VALUES[piece_index(piece.kind)]);
+                PIECE_VALUES[piece_index(captured.kind)] * 10 - attacker / 10
+            });
+            -(promotion + capture)
+        });
+    }
+
+    /// Returns true if the search must stop, checking the clock every so often
+    fn should_stop(&mut self) -> bool {
+        if self.max_nodes.is_some_and(|max| self.nodes >= max) {
+            self.stopped = true;
+        }
+        if !self.stopped && self.nodes.is_multiple_of(CHECK_INTERVAL) {
+            self.stopped = self.stop.load(Ordering::Relaxed)
+                || (self.deadline).is_some_and(|deadline| Instant::now() >= deadline);
+        }
+        self.stopped
+    }
+}
+
+/// Converts a score from the search into a score to report
+fn to_score(score: i32) -> Score {
+    let mate_distance = MATE - score.abs();
+    match mate_distance < MAX_DEPTH as i32 * 2 {
+        true if score > 0 => Score::Mate((mate_distance + 1) / 2),
+        true => Score::Mate(-(mate_distance / 2)),
+        false => Score::Centipawns(score),
+    }
+}
+
+/// Returns the index of the given kind of piece in the evaluation tables
+const fn piece_index(kind: PieceKind) -> usize {
+    match kind {
+        PieceKind::Pawn => 0,
+        PieceKind::Knight => 1,
+        PieceKind::Bishop => 2,
+        PieceKind::Rook => 3,
+        PieceKind::Queen => 4,
+        PieceKind::King => 5,
+    }
+}
+
+/// Returns the index of the given square in a piece-square table, for a piece of the given color
+fn table_index(square: Square, color: PieceColor) -> usize {
+    let (file, rank) = square.indices();
+    let row = match color {
+        PieceColor::White => 7 - rank,
+        PieceColor::Black => rank,
+    };
+    row as usize * 8 + file as usize
+}
@@ -0,0 +1,296 @@
+use std::{
+    io::{self, BufRead},
+    path::Path,
+    str::SplitWhitespace,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crate::{board::Board, book::Book, r#move::Move, tablebase::Tablebase};
+
+use super::{
+    search,
+    strength::{Strength, MAX_ELO, MIN_ELO},
+    ClockTime, Info, Limits, Score,
+};
+
+/// The name the engine gives itself
+const NAME: &str = "Chess";
+
+/// The rating the engine plays at when its strength is limited, until told otherwise
+const DEFAULT_ELO: u32 = 1500;
+
+/// The most lines the engine reports at once
+const MAX_MULTI_PV: u32 = 10;
+
+/// How often an infinite search that has finished checks whether it may report its move
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// The built-in engine's state between commands
+struct Server {
+    board: Board,
+    chess960: bool,
+    multi_pv: u32,
+    limit_strength: bool,
+    elo: u32,
+    human_like: bool,
+    own_book: bool,
+    book: Option<Book>,
+    tablebase: Option<Arc<Tablebase>>,
+    search: Option<(JoinHandle<()>, Arc<AtomicBool>)>,
+}
+
+/// Runs the built-in engine for other chess programs, reading Universal Chess Interface commands
+/// from standard input and answering on standard output until told to quit
+pub fn run() {
+    let mut server = Server {
+        board: Board::new_init(),
+        chess960: false,
+        multi_pv: 1,
+        limit_strength: false,
+        elo: DEFAULT_ELO,
+        human_like: true,
+        own_book: false,
+        book: None,
+        tablebase: None,
+        search: None,
+    };
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else { break };
+        if !server.handle(&line) {
+            break;
+        }
+    }
+    server.stop();
+}
+
+impl Server {
+    /// Handles a command, returning false if the engine should quit
+    fn handle(&mut self, line: &str) -> bool {
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("uci") => {
+                println!("id name {NAME}");
+                println!("option name MultiPV type spin default 1 min 1 max {MAX_MULTI_PV}");
+                println!("option name UCI_Chess960 type check default false");
+                println!("option name UCI_LimitStrength type check default false");
+                println!(
+                    "option name UCI_Elo type spin default {DEFAULT_ELO} min {MIN_ELO} max {MAX_ELO}"
+                );
+                println!("option name HumanLike type check default true");
+                println!("option name OwnBook type check default false");
+                println!("option name BookFile type string default <empty>");
+                println!("option name SyzygyPath type string default <empty>");
+                println!("uciok");
+            }
+            Some("isready") => println!("readyok"),
+            Some("setoption") => self.set_option(line),
+            Some("ucinewgame") | Some("stop") => self.stop(),
+            Some("position") => self.set_position(words),
+            Some("go") => self.go(words),
+            Some("quit") => return false,
+            // unknown commands are ignored, as the protocol asks
+            _ => {}
+        }
+        true
+    }
+
+    /// Handles a `setoption` command, i.e. "setoption name UCI_Elo value 1200"
+    fn set_option(&mut self, line: &str) {
+        let Some(option) = line.trim().strip_prefix("setoption") else {
+            return;
+        };
+        let Some(option) = option.trim_start().strip_prefix("name") else {
+            return;
+        };
+        let (name, value) = match option.split_once(" value ") {
+            Some((name, value)) => (name.trim(), value.trim()),
+            None => (option.trim(), ""),
+        };
+
+        // option names are not case sensitive
+        match name.to_ascii_lowercase().as_str() {
+            "multipv" => {
+                if let Ok(lines) = value.parse::<u32>() {
+                    self.multi_pv = lines.clamp(1, MAX_MULTI_PV);
+                }
+            }
+            "uci_chess960" => self.chess960 = value == "true",
+            "uci_limitstrength" => self.limit_strength = value == "true",
+            "uci_elo" => {
+                if let Ok(elo) = value.parse::<u32>() {
+                    self.elo = elo.clamp(MIN_ELO, MAX_ELO);
+                }
+            }
+            "humanlike" => self.human_like = value == "true",
+            "ownbook" => self.own_book = value == "true",
+            "bookfile" => {
+                self.book = None;
+                if !value.is_empty() && value != "<empty>" {
+                    match Book::open(Path::new(value)) {
+                        Ok(book) => self.book = Some(book),
+                        Err(error) => println!("info string unable to open {value}: {error}"),
+                    }
+                }
+            }
+            "syzygypath" => {
+                self.tablebase = None;
+                if !value.is_empty() && value != "<empty>" {
+                    match Tablebase::open(Path::new(value)) {
+                        Ok(tablebase) => self.tablebase = Some(Arc::new(tablebase)),
+                        Err(error) => println!("info string unable to open {value}: {error}"),
+                    }
+                }
+            }
+            _ => println!("info string unknown option {name}"),
+        }
+    }
+
+    /// Handles a `position` command, i.e. "position startpos moves e2e4 e7e5"
+    fn set_position(&mut self, words: SplitWhitespace) {
+        let words: Vec<_> = words.collect();
+        let moves_at = words.iter().position(|&word| word == "moves");
+        let setup = &words[..moves_at.unwrap_or(words.len())];
+        let board = match setup.first() {
+            Some(&"startpos") => Ok(Board::new_init()),
+            Some(&"fen") => Board::from_fen(&setup[1..].join(" "))
+                .and_then(|board| board.validate().map(|_| board)),
+            _ => Err("expected startpos or fen"),
+        };
+        let mut board = match board {
+            Ok(board) => board,
+            Err(error) => {
+                println!("info string invalid position: {error}");
+                return;
+            }
+        };
+
+        for uci in moves_at.map_or(&[][..], |index| &words[index + 1..]) {
+            match board.parse_uci(uci) {
+                Ok(r#move) => board.play_move(r#move),
+                Err(error) => {
+                    println!("info string invalid move {uci}: {error}");
+                    break;
+                }
+            }
+        }
+        self.board = board;
+    }
+
+    /// Handles a `go` command by starting to search, i.e. "go wtime 60000 btime 60000"
+    fn go(&mut self, mut words: SplitWhitespace) {
+        self.stop();
+
+        let mut limits = Limits {
+            multi_pv: Some(self.multi_pv),
+            ..Limits::default()
+        };
+        let mut clock = ClockTime::default();
+        let mut timed = false;
+        let mut infinite = false;
+        while let Some(word) = words.next() {
+            match word {
+                "wtime" => (clock.white, timed) = (next_milliseconds(&mut words), true),
+                "btime" => (clock.black, timed) = (next_milliseconds(&mut words), true),
+                "winc" => clock.white_increment = next_milliseconds(&mut words),
+                "binc" => clock.black_increment = next_milliseconds(&mut words),
+                "movestogo" => clock.moves_to_go = next_number(&mut words).map(|n| n as u32),
+                "depth" => limits.depth = next_number(&mut words).map(|n| n as u32),
+                "nodes" => limits.nodes = next_number(&mut words),
+                "movetime" => limits.move_time = Some(next_milliseconds(&mut words)),
+                "infinite" => infinite = true,
+                _ => {}
+            }
+        }
+        if timed {
+            limits.clock = Some(clock);
+        }
+
+        let board = self.board.clone();
+        let chess960 = self.chess960 || board.is_chess960();
+        if self.own_book && !infinite {
+            if let Some(r#move) = self.book.as_ref().and_then(|book| book.choose(&board)) {
+                println!("bestmove {}", uci(r#move, chess960));
+                return;
+            }
+        }
+        let strength = match self.limit_strength {
+            true => Strength::from_elo(self.elo, self.human_like),
+            false => Strength::FULL,
+        };
+        let tablebase = self.tablebase.clone();
+        let stop = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&stop);
+        let thread = thread::spawn(move || {
+            let tablebase = tablebase.as_deref();
+            let best = search::search(&board, &limits, &strength, tablebase, &flag, |info| {
+                println!("{}", info_line(info, chess960));
+            });
+
+            // an infinite search reports its move only once it is told to stop
+            while infinite && !flag.load(Ordering::Relaxed) {
+                thread::sleep(POLL_INTERVAL);
+            }
+            match best {
+                Some(r#move) => println!("bestmove {}", uci(r#move, chess960)),
+                None => println!("bestmove 0000"),
+            }
+        });
+        self.search = Some((thread, stop));
+    }
+
+    /// Stops the search in progress, if any, waiting for it to report its move
+    fn stop(&mut self) {
+        if let Some((thread, stop)) = self.search.take() {
+            stop.store(true, Ordering::Relaxed);
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Parses the next word as a number, treating negative numbers as zero
+fn next_number(words: &mut SplitWhitespace) -> Option<u64> {
+    let number: i64 = words.next()?.parse().ok()?;
+    Some(number.max(0) as u64)
+}
+
+/// Parses the next word as a number of milliseconds, or zero if it is missing
+fn next_milliseconds(words: &mut SplitWhitespace) -> Duration {
+    Duration::from_millis(next_number(words).unwrap_or(0))
+}
+
+/// Writes a move in the notation of the Universal Chess Interface, with castling as the king
+/// taking its rook in Chess960
+fn uci(r#move: Move, chess960: bool) -> String {
+    match chess960 {
+        true => r#move.uci_chess960(),
+        false => r#move.uci(),
+    }
+}
+
+/// Writes the `info` line reporting the given search progress
+fn info_line(info: &Info, chess960: bool) -> String {
+    let score = match info.score {
+        Score::Centipawns(centipawns) => format!("cp {centipawns}"),
+        Score::Mate(moves) => format!("mate {moves}"),
+    };
+    let milliseconds = info.time.as_millis().max(1);
+    let pv: Vec<_> = info
+        .pv
+        .iter()
+        .map(|&r#move| uci(r#move, chess960))
+        .collect();
+    format!(
+        "info multipv {} depth {} score {score} nodes {} nps {} time {} pv {}",
+        info.multi_pv,
+        info.depth,
+        info.nodes,
+        info.nodes as u128 * 1000 / milliseconds,
+        info.time.as_millis(),
+        pv.join(" "),
+    )
+}
@@ -0,0 +1,142 @@
+use super::MAX_LEVEL;
+
+/// The rating of the weakest play
+pub const MIN_ELO: u32 = 600;
+
+/// The rating of the strongest play, at which the engine plays as well as it can
+pub const MAX_ELO: u32 = 2400;
+
+/// The fewest positions a weakened engine searches
+const MIN_NODES: f32 = 200.0;
+
+/// The most positions a weakened engine searches, short of full strength
+const MAX_NODES: f32 = 200_000.0;
+
+/// The most centipawns the weakest play gives away by choosing a worse move
+const MAX_RANDOMNESS: f32 = 250.0;
+
+/// How often the weakest human-like play overlooks what lies beyond its horizon
+const MAX_MISS_CHANCE: f32 = 0.35;
+
+/// How strongly the built-in engine plays
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Strength {
+    /// The deepest the engine searches, in plies
+    pub depth: Option<u32>,
+
+    /// The most positions the engine searches
+    pub nodes: Option<u64>,
+
+    /// How many centipawns worse than the best a move may be for the engine to choose it
+    ///
+    /// Moves within this of the best are chosen at random, more likely the closer they are.
+    pub randomness: u32,
+
+    /// The depth the engine sometimes stops seeing at, missing tactics beyond it
+    pub horizon: Option<u32>,
+
+    /// How often the engine only sees as far as its horizon, from 0 to 1
+    pub miss_chance: f32,
+}
+
+impl Strength {
+    /// Playing as well as possible
+    pub const FULL: Self = Self {
+        depth: None,
+        nodes: None,
+        randomness: 0,
+        horizon: None,
+        miss_chance: 0.0,
+    };
+
+    /// Returns the strength of play at the given rating, from [`MIN_ELO`] to [`MAX_ELO`]
+    ///
+    /// Human-like play makes its mistakes by sometimes not seeing as deep as usual, so it misses
+    /// tactics rather than choosing a worse move it has seen through.
+    pub fn from_elo(elo: u32, human_like: bool) -> Self {
+        if elo >= MAX_ELO {
+            return Self::FULL;
+        }
+
+        // every limit loosens smoothly from the weakest rating to the strongest
+        let t = elo.saturating_sub(MIN_ELO) as f32 / (MAX_ELO - MIN_ELO) as f32;
+        let depth = 1 + (t * 8.0).round() as u32;
+        let nodes = MIN_NODES * (MAX_NODES / MIN_NODES).powf(t);
+        let randomness = MAX_RANDOMNESS * (1.0 - t).powf(1.5);
+        let (horizon, miss_chance) = match human_like {
+            true => (Some((depth / 2).max(1)), MAX_MISS_CHANCE * (1.0 - t)),
+            false => (None, 0.0),
+        };
+
+        Self {
+            depth: Some(depth),
+            nodes: Some(nodes as u64),
+            randomness: randomness as u32,
+            horizon,
+            miss_chance,
+        }
+    }
+
+    /// Returns the strength of play at the given level, from 1 to [`MAX_LEVEL`]
+    pub fn from_level(level: u8, human_like: bool) -> Self {
+        Self::from_elo(level_elo(level), human_like)
+    }
+}
+
+/// Returns the rating of the given level, from [`MIN_ELO`] at level 1 to [`MAX_ELO`] at
+/// [`MAX_LEVEL`]
+pub fn level_elo(level: u8) -> u32 {
+    let level = level.clamp(1, MAX_LEVEL) as u32 - 1;
+    MIN_ELO + level * (MAX_ELO - MIN_ELO) / (MAX_LEVEL as u32 - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels() {
+        assert_eq!(level_elo(1), MIN_ELO);
+        assert_eq!(level_elo(0), MIN_ELO);
+        assert_eq!(level_elo(MAX_LEVEL), MAX_ELO);
+        assert_eq!(level_elo(u8::MAX), MAX_ELO);
+        assert!((1..MAX_LEVEL).all(|level| level_elo(level) < level_elo(level + 1)));
+
+        assert_eq!(Strength::from_level(MAX_LEVEL, false), Strength::FULL);
+        assert_eq!(Strength::from_level(MAX_LEVEL, true), Strength::FULL);
+        assert_eq!(Strength::from_elo(3000, true), Strength::FULL);
+    }
+
+    #[test]
+    fn weakest() {
+        let weakest = Strength::from_level(1, true);
+        assert_eq!(weakest.depth, Some(1));
+        assert_eq!(weakest.nodes, Some(MIN_NODES as u64));
+        assert_eq!(weakest.randomness, MAX_RANDOMNESS as u32);
+        assert_eq!(weakest.horizon, Some(1));
+        assert_eq!(weakest.miss_chance, MAX_MISS_CHANCE);
+        assert_eq!(Strength::from_elo(0, true), weakest);
+    }
+
+    #[test]
+    fn limits_loosen_as_the_rating_rises() {
+        for human_like in [false, true] {
+            let strengths: Vec<_> = (MIN_ELO..MAX_ELO)
+                .step_by(50)
+                .map(|elo| Strength::from_elo(elo, human_like))
+                .collect();
+            for pair in strengths.windows(2) {
+                let (weaker, stronger) = (pair[0], pair[1]);
+                assert!(weaker.depth <= stronger.depth);
+                assert!(weaker.nodes < stronger.nodes);
+                assert!(weaker.randomness >= stronger.randomness);
+                assert!(weaker.miss_chance >= stronger.miss_chance);
+                assert!(weaker.horizon <= stronger.horizon);
+            }
+            for strength in &strengths {
+                assert_eq!(strength.horizon.is_some(), human_like);
+                assert!(strength.horizon <= strength.depth);
+            }
+        }
+    }
+}
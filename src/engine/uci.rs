@@ -0,0 +1,316 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    path::Path,
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{board::Board, r#move::Move};
+
+use super::{Event, Info, Limits, Score, Thinking};
+
+/// How often a waiting engine checks whether it should stop
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// How long to wait for an engine to answer a command other than `go`
+const REPLY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A running external engine, talking over the Universal Chess Interface
+///
+/// The engine process is asked to quit, then killed, once this and any search using it are
+/// dropped.
+pub struct UciEngine {
+    process: Arc<Mutex<Process>>,
+}
+
+/// The pipes to an engine process
+struct Process {
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+    initialized: bool,
+    chess960: bool,
+    multi_pv: u32,
+}
+
+impl UciEngine {
+    /// Starts the engine at the given path
+    ///
+    /// The engine is only set up when it is first asked to think, so this returns without
+    /// waiting for it, and an engine that does not speak the protocol fails then.
+    pub fn spawn(path: &Path) -> Result<Self, &'static str> {
+        let mut child = Command::new(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|_| "unable to start the engine")?;
+        let stdin = child.stdin.take().ok_or("unable to talk to the engine")?;
+        let stdout = child.stdout.take().ok_or("unable to talk to the engine")?;
+
+        // engines write whenever they like, so their output is read on its own thread
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else { break };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let process = Process {
+            child,
+            stdin,
+            lines,
+            initialized: false,
+            chess960: false,
+            multi_pv: 1,
+        };
+        Ok(Self {
+            process: Arc::new(Mutex::new(process)),
+        })
+    }
+
+    /// Starts thinking about the position reached by playing the given moves from the given
+    /// starting position
+    pub fn think(&self, start: &Board, moves: &[Move], limits: Limits) -> Thinking {
+        let process = Arc::clone(&self.process);
+        let start = start.clone();
+        let moves = moves.to_vec();
+        Thinking::spawn(move |events, stop| {
+            // a previous search may still be finishing, so this waits its turn
+            let result = match process.lock() {
+                Ok(mut process) => process.think(&start, &moves, limits, &events, &stop),
+                Err(_) => Err("the engine stopped unexpectedly"),
+            };
+            if let Err(error) = result {
+                let _ = events.send(Event::Failed(error));
+            }
+        })
+    }
+}
+
+impl Drop for Process {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+impl Process {
+    /// Sends a command to the engine
+    fn send(&mut self, command: &str) -> Result<(), &'static str> {
+        writeln!(self.stdin, "{command}")
+            .and_then(|()| self.stdin.flush())
+            .map_err(|_| "the engine stopped unexpectedly")
+    }
+
+    /// Waits for a line from the engine
+    fn receive(&self, timeout: Duration) -> Result<String, &'static str> {
+        self.lines
+            .recv_timeout(timeout)
+            .map_err(|error| match error {
+                RecvTimeoutError::Timeout => "the engine stopped responding",
+                RecvTimeoutError::Disconnected => "the engine stopped unexpectedly",
+            })
+    }
+
+    /// Skips lines from the engine until one starts with the given word
+    fn wait_for(&mut self, word: &str) -> Result<String, &'static str> {
+        loop {
+            let line = self.receive(REPLY_TIMEOUT)?;
+            if line.split_whitespace().next() == Some(word) {
+                return Ok(line);
+            }
+        }
+    }
+
+    /// Switches the engine to the Universal Chess Interface, if not already done
+    fn initialize(&mut self) -> Result<(), &'static str> {
+        if !self.initialized {
+            self.send("uci")?;
+            self.wait_for("uciok")?;
+            self.initialized = true;
+        }
+        Ok(())
+    }
+
+    /// Searches the given position, sending the engine's progress and best move as events
+    fn think(
+        &mut self,
+        start: &Board,
+        moves: &[Move],
+        limits: Limits,
+        events: &Sender<Event>,
+        stop: &AtomicBool,
+    ) -> Result<(), &'static str> {
+        self.initialize()?;
+        let chess960 = start.is_chess960();
+        if chess960 != self.chess960 {
+            self.send(&format!("setoption name UCI_Chess960 value {chess960}"))?;
+            self.chess960 = chess960;
+        }
+        let multi_pv = limits.multi_pv.unwrap_or(1);
+        if multi_pv != self.multi_pv {
+            self.send(&format!("setoption name MultiPV value {multi_pv}"))?;
+            self.multi_pv = multi_pv;
+        }
+        self.send("isready")?;
+        self.wait_for("readyok")?;
+        if stop.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+
+        let mut position = format!("position fen {}", start.fen());
+        if !moves.is_empty() {
+            position.push_str(" moves");
+            for r#move in moves {
+                position.push(' ');
+                position.push_str(&match chess960 {
+                    true => r#move.uci_chess960(),
+                    false => r#move.uci(),
+                });
+            }
+        }
+        self.send(&position)?;
+        self.send(&go_command(&limits))?;
+
+        let mut board = start.clone();
+        for &r#move in moves {
+            board.play_move(r#move);
+        }
+
+        let mut stopped_at = None;
+        loop {
+            if stopped_at.is_none() && stop.load(Ordering::Relaxed) {
+                self.send("stop")?;
+                stopped_at = Some(Instant::now());
+            }
+            let line = match self.lines.recv_timeout(POLL_INTERVAL) {
+                Ok(line) => line,
+                Err(RecvTimeoutError::Timeout) => {
+                    // an engine that was stopped must answer promptly with its best move
+                    match stopped_at.is_some_and(|time| time.elapsed() > REPLY_TIMEOUT) {
+                        true => return Err("the engine stopped responding"),
+                        false => continue,
+                    }
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err("the engine stopped unexpectedly")
+                }
+            };
+
+            let mut words = line.split_whitespace();
+            match words.next() {
+                Some("info") => {
+                    if let Some(info) = parse_info(&board, &line) {
+                        let _ = events.send(Event::Info(info));
+                    }
+                }
+                Some("bestmove") => {
+                    let best = words.next().and_then(|uci| board.parse_uci(uci).ok());
+                    let _ = events.send(Event::BestMove(best));
+                    return Ok(());
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Returns the `go` command that searches within the given limits
+fn go_command(limits: &Limits) -> String {
+    let mut command = String::from("go");
+    if let Some(clock) = limits.clock {
+        command.push_str(&format!(
+            " wtime {} btime {} winc {} binc {}",
+            clock.white.as_millis(),
+            clock.black.as_millis(),
+            clock.white_increment.as_millis(),
+            clock.black_increment.as_millis(),
+        ));
+        if let Some(moves) = clock.moves_to_go {
+            command.push_str(&format!(" movestogo {moves}"));
+        }
+    }
+    if let Some(depth) = limits.depth {
+        command.push_str(&format!(" depth {depth}"));
+    }
+    if let Some(nodes) = limits.nodes {
+        command.push_str(&format!(" nodes {nodes}"));
+    }
+    if let Some(time) = limits.move_time {
+        command.push_str(&format!(" movetime {}", time.as_millis()));
+    }
+    if command == "go" {
+        command.push_str(" infinite");
+    }
+    command
+}
+
+/// Parses an `info` line from an engine searching the given position
+///
+/// Returns `None` if the line does not report a score and a line of moves, as engines also
+/// send lines about the move they are currently searching or the strings they want to show.
+pub fn parse_info(board: &Board, line: &str) -> Option<Info> {
+    let mut words = line.split_whitespace();
+    if words.next() != Some("info") {
+        return None;
+    }
+
+    let mut multi_pv = 1;
+    let mut depth = None;
+    let mut score = None;
+    let mut nodes = 0;
+    let mut time = Duration::ZERO;
+    let mut pv = Vec::new();
+    while let Some(word) = words.next() {
+        match word {
+            "multipv" => multi_pv = words.next()?.parse().ok()?,
+            "depth" => depth = words.next()?.parse().ok(),
+            "nodes" => nodes = words.next()?.parse().ok()?,
+            "time" => time = Duration::from_millis(words.next()?.parse().ok()?),
+            "score" => {
+                score = match (words.next()?, words.next()?.parse().ok()?) {
+                    ("cp", centipawns) => Some(Score::Centipawns(centipawns)),
+                    ("mate", moves) => Some(Score::Mate(moves)),
+                    _ => None,
+                }
+            }
+            "pv" => {
+                // the rest of the line is the moves, which are checked against the board
+                let mut board = board.clone();
+                for uci in words.by_ref() {
+                    let Ok(r#move) = board.parse_uci(uci) else {
+                        break;
+                    };
+                    board.play_move(r#move);
+                    pv.push(r#move);
+                }
+            }
+            // everything after "string" is free text
+            "string" => break,
+            _ => {}
+        }
+    }
+
+    match pv.is_empty() {
+        true => None,
+        false => Some(Info {
+            multi_pv,
+            depth: depth?,
+            score: score?,
+            nodes,
+            time,
+            pv,
+        }),
+    }
+}
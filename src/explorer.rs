@@ -0,0 +1,177 @@
+use std::{
+    ffi::OsString,
+    fs::{self, File},
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use memmap2::Mmap;
+
+use crate::{board::Board, book::decode_move, r#move::Move};
+
+/// Building the index of the positions in a database of games
+pub mod indexing;
+
+/// The bytes every index starts with, changed whenever the format changes
+const MAGIC: &[u8; 8] = b"CHSIDX01";
+
+/// The size of an index's header: its magic, and the length and modification time of the
+/// database it was built from
+const HEADER_SIZE: usize = 24;
+
+/// The size of each record in an index, in bytes
+const RECORD_SIZE: usize = 34;
+
+/// How often a move was played in a database, and how those games ended
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MoveStats {
+    /// The move
+    pub r#move: Move,
+
+    /// The number of games white won
+    pub white: u32,
+
+    /// The number of games drawn
+    pub draws: u32,
+
+    /// The number of games black won
+    pub black: u32,
+
+    /// The average rating of the players, or `None` if none of the games give ratings
+    pub average_rating: Option<u32>,
+}
+
+impl MoveStats {
+    /// Returns the number of games the move was played in
+    pub fn games(&self) -> u32 {
+        self.white + self.draws + self.black
+    }
+}
+
+/// The results of the games a move was played in, as stored in an index
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct Counts {
+    white: u32,
+    draws: u32,
+    black: u32,
+
+    /// The number of games with a rating, and the sum of their ratings
+    rated: u32,
+    rating_sum: u64,
+}
+
+impl Counts {
+    /// Adds the games of the given counts to these
+    fn add(&mut self, other: &Self) {
+        self.white += other.white;
+        self.draws += other.draws;
+        self.black += other.black;
+        self.rated += other.rated;
+        self.rating_sum += other.rating_sum;
+    }
+}
+
+/// Writes a record of the games in which the given move, in the Polyglot encoding, was played in
+/// the position with the given Zobrist key
+fn write_record(key: u64, raw: u16, counts: &Counts) -> [u8; RECORD_SIZE] {
+    let mut record = [0; RECORD_SIZE];
+    record[..8].copy_from_slice(&key.to_be_bytes());
+    record[8..10].copy_from_slice(&raw.to_be_bytes());
+    record[10..14].copy_from_slice(&counts.white.to_be_bytes());
+    record[14..18].copy_from_slice(&counts.draws.to_be_bytes());
+    record[18..22].copy_from_slice(&counts.black.to_be_bytes());
+    record[22..26].copy_from_slice(&counts.rated.to_be_bytes());
+    record[26..34].copy_from_slice(&counts.rating_sum.to_be_bytes());
+    record
+}
+
+/// Reads a record, the reverse of [`write_record`]
+fn read_record(record: &[u8; RECORD_SIZE]) -> (u64, u16, Counts) {
+    let u32_at = |at: usize| u32::from_be_bytes(record[at..at + 4].try_into().expect("four bytes"));
+    let key = u64::from_be_bytes(record[..8].try_into().expect("keys are eight bytes"));
+    let raw = u16::from_be_bytes([record[8], record[9]]);
+    let counts = Counts {
+        white: u32_at(10),
+        draws: u32_at(14),
+        black: u32_at(18),
+        rated: u32_at(22),
+        rating_sum: u64::from_be_bytes(record[26..34].try_into().expect("eight bytes")),
+    };
+    (key, raw, counts)
+}
+
+/// Returns the path of the index of the given database, beside it with `.idx` added
+pub fn index_path(database: &Path) -> PathBuf {
+    let mut path = OsString::from(database.as_os_str());
+    path.push(".idx");
+    PathBuf::from(path)
+}
+
+/// Returns the header of an index of the given database as it is now, so an index built before
+/// it last changed can be told apart
+fn header(database: &Path) -> Result<[u8; HEADER_SIZE], &'static str> {
+    let metadata = fs::metadata(database).map_err(|_| "unable to read the database")?;
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |modified| modified.as_secs());
+
+    let mut header = [0; HEADER_SIZE];
+    header[..8].copy_from_slice(MAGIC);
+    header[8..16].copy_from_slice(&metadata.len().to_be_bytes());
+    header[16..24].copy_from_slice(&modified.to_be_bytes());
+    Ok(header)
+}
+
+/// An index of every move played in the opening of a database of games, by the Zobrist key of
+/// the position it was played in
+///
+/// The index is a list of records sorted by key, each holding a move and the results of the
+/// games it was played in, so the moves of a position are found by binary search without reading
+/// the whole file.
+pub struct Index {
+    map: Mmap,
+}
+
+impl Index {
+    /// Opens the index of the given database, failing if it has not been built or is out of date
+    pub fn open(database: &Path) -> Result<Self, &'static str> {
+        let header = header(database)?;
+        let file = File::open(index_path(database)).map_err(|_| "the database is not indexed")?;
+        // SAFETY: the index is only written to a temporary file that is then renamed over it, so
+        // the mapped file is not changed while it is mapped
+        let map = unsafe { Mmap::map(&file) }.map_err(|_| "unable to read the index")?;
+        if map.len() < HEADER_SIZE || map[..HEADER_SIZE] != header {
+            return Err("the index is out of date");
+        }
+        if !(map.len() - HEADER_SIZE).is_multiple_of(RECORD_SIZE) {
+            return Err("the index is damaged");
+        }
+        Ok(Self { map })
+    }
+
+    /// Returns the legal moves played in the given position, most often played first
+    pub fn moves(&self, board: &Board) -> Vec<MoveStats> {
+        let key = board.zobrist();
+        let (records, _) = self.map[HEADER_SIZE..].as_chunks::<RECORD_SIZE>();
+        let start = records.partition_point(|record| read_record(record).0 < key);
+        let mut moves: Vec<_> = records[start..]
+            .iter()
+            .map(read_record)
+            .take_while(|&(record_key, _, _)| record_key == key)
+            .filter_map(|(_, raw, counts)| {
+                Some(MoveStats {
+                    r#move: decode_move(board, raw)?,
+                    white: counts.white,
+                    draws: counts.draws,
+                    black: counts.black,
+                    average_rating: (counts.rated > 0)
+                        .then(|| (counts.rating_sum / counts.rated as u64) as u32),
+                })
+            })
+            .collect();
+        moves.sort_by_key(|stats| std::cmp::Reverse(stats.games()));
+        moves
+    }
+}
@@ -0,0 +1,326 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+    fs::{self, File},
+    io::{BufRead, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use crate::{
+    book::encode_move,
+    pgn::{self, MainLine},
+};
+
+use super::{header, index_path, read_record, write_record, Counts, RECORD_SIZE};
+
+/// The number of plies of each game that are indexed, as later positions are rarely reached in
+/// more than one game
+pub const MAX_PLY: usize = 40;
+
+/// The most moves counted in memory before they are sorted and written out to a temporary run,
+/// so databases of any size can be indexed
+const RUN_SIZE: usize = 1 << 21;
+
+/// Temporary files of sorted records, merged into the index at the end and removed when dropped
+struct Runs {
+    index: PathBuf,
+    paths: Vec<PathBuf>,
+}
+
+impl Runs {
+    /// Writes the given moves to a new run, sorted by key
+    fn write(&mut self, moves: &mut HashMap<(u64, u16), Counts>) -> Result<(), &'static str> {
+        let path = self
+            .index
+            .with_extension(format!("run{}", self.paths.len()));
+        self.paths.push(path.clone());
+        write_sorted(&path, &[], moves.drain())
+    }
+}
+
+impl Drop for Runs {
+    fn drop(&mut self) {
+        for path in &self.paths {
+            // the runs may not all have been written if indexing failed
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// Builds the index of the given database of games in PGN, replacing any index it has, and
+/// reporting how much of the database has been read, from 0 to 1, as it goes
+///
+/// Indexing stops early, leaving any old index in place, if `stop` is set.
+pub fn build(
+    database: &Path,
+    mut progress: impl FnMut(f32),
+    stop: &AtomicBool,
+) -> Result<(), &'static str> {
+    let header = header(database)?;
+    let file = File::open(database).map_err(|_| "unable to read the database")?;
+    let length = file.metadata().map_or(0, |metadata| metadata.len()).max(1);
+    let mut reader = BufReader::new(file);
+
+    let index = index_path(database);
+    let mut runs = Runs {
+        index: index.clone(),
+        paths: Vec::new(),
+    };
+    let mut moves = HashMap::new();
+    let (mut text, mut line) = (String::new(), Vec::new());
+    let (mut read, mut has_moves, mut reported) = (0, false, 0);
+    loop {
+        line.clear();
+        let count = reader
+            .read_until(b'\n', &mut line)
+            .map_err(|_| "unable to read the database")?;
+        read += count as u64;
+
+        // a tag after the moves of a game starts the next game
+        let is_tag = line.first() == Some(&b'[');
+        if count == 0 || (is_tag && has_moves) {
+            if let Some(Ok(game)) = pgn::Reader::new(&text).next_main_line(MAX_PLY) {
+                add_game(&mut moves, &game);
+            }
+            text.clear();
+            has_moves = false;
+
+            if moves.len() >= RUN_SIZE {
+                runs.write(&mut moves)?;
+            }
+            if stop.load(Ordering::Relaxed) {
+                return Err("indexing was cancelled");
+            }
+            let permille = read * 1000 / length;
+            if permille != reported {
+                reported = permille;
+                progress(permille as f32 / 1000.0);
+            }
+        }
+        if count == 0 {
+            break;
+        }
+        // older collections are often not UTF-8, but the moves themselves are ASCII
+        text.push_str(&String::from_utf8_lossy(&line));
+        has_moves |= !is_tag && !line.trim_ascii().is_empty();
+    }
+
+    let temporary = index.with_extension("tmp");
+    match runs.paths.is_empty() {
+        true => write_sorted(&temporary, &header, moves.drain())?,
+        false => {
+            runs.write(&mut moves)?;
+            merge(&temporary, &header, &runs.paths)?;
+        }
+    }
+    // the finished index replaces the old one at once, so a reader never sees half of it
+    fs::rename(&temporary, &index).map_err(|_| "unable to write the index")
+}
+
+/// Counts the moves of the opening of the given game, unless it has no result
+fn add_game(moves: &mut HashMap<(u64, u16), Counts>, game: &MainLine) {
+    let mut counts = match game.headers.get("Result") {
+        Some("1-0") => Counts {
+            white: 1,
+            ..Counts::default()
+        },
+        Some("1/2-1/2") => Counts {
+            draws: 1,
+            ..Counts::default()
+        },
+        Some("0-1") => Counts {
+            black: 1,
+            ..Counts::default()
+        },
+        _ => return,
+    };
+    let ratings: Vec<u64> = ["WhiteElo", "BlackElo"]
+        .into_iter()
+        .filter_map(|tag| game.headers.get(tag)?.parse().ok())
+        .collect();
+    if !ratings.is_empty() {
+        counts.rated = 1;
+        counts.rating_sum = ratings.iter().sum::<u64>() / ratings.len() as u64;
+    }
+
+    let mut board = game.start.clone();
+    for &r#move in &game.moves {
+        let key = (board.zobrist(), encode_move(r#move));
+        moves.entry(key).or_default().add(&counts);
+        board.play_move(r#move);
+    }
+}
+
+/// Writes the given header and then the given moves to the given file, sorted by key
+fn write_sorted(
+    path: &Path,
+    header: &[u8],
+    moves: impl Iterator<Item = ((u64, u16), Counts)>,
+) -> Result<(), &'static str> {
+    let mut moves: Vec<_> = moves.collect();
+    moves.sort_unstable_by_key(|&(key, _)| key);
+
+    let file = File::create(path).map_err(|_| "unable to write the index")?;
+    let mut writer = BufWriter::new(file);
+    let mut write = || {
+        writer.write_all(header)?;
+        for ((key, raw), counts) in &moves {
+            writer.write_all(&write_record(*key, *raw, counts))?;
+        }
+        writer.flush()
+    };
+    write().map_err(|_| "unable to write the index")
+}
+
+/// Merges the given runs into one file with the given header, adding up the records of the same
+/// move in the same position
+fn merge(path: &Path, header: &[u8], runs: &[PathBuf]) -> Result<(), &'static str> {
+    let mut readers = runs
+        .iter()
+        .map(|run| File::open(run).map(BufReader::new))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| "unable to read a temporary file")?;
+    let next = |reader: &mut BufReader<File>| {
+        let mut record = [0; RECORD_SIZE];
+        reader
+            .read_exact(&mut record)
+            .ok()
+            .map(|_| read_record(&record))
+    };
+
+    // the heap holds the next record of each run, smallest key first
+    let mut heap = BinaryHeap::new();
+    let mut counts = Vec::with_capacity(readers.len());
+    for (run, reader) in readers.iter_mut().enumerate() {
+        let first = next(reader);
+        counts.push(first.map(|(_, _, counts)| counts).unwrap_or_default());
+        if let Some((key, raw, _)) = first {
+            heap.push(Reverse((key, raw, run)));
+        }
+    }
+
+    let file = File::create(path).map_err(|_| "unable to write the index")?;
+    let mut writer = BufWriter::new(file);
+    let mut write = || {
+        writer.write_all(header)?;
+        let mut current: Option<((u64, u16), Counts)> = None;
+        while let Some(Reverse((key, raw, run))) = heap.pop() {
+            match &mut current {
+                Some((current_key, total)) if *current_key == (key, raw) => total.add(&counts[run]),
+                _ => {
+                    if let Some(((key, raw), total)) = current.take() {
+                        writer.write_all(&write_record(key, raw, &total))?;
+                    }
+                    current = Some(((key, raw), counts[run]));
+                }
+            }
+            if let Some((key, raw, run_counts)) = next(&mut readers[run]) {
+                counts[run] = run_counts;
+                heap.push(Reverse((key, raw, run)));
+            }
+        }
+        if let Some(((key, raw), total)) = current {
+            writer.write_all(&write_record(key, raw, &total))?;
+        }
+        writer.flush()
+    };
+    write().map_err(|_| "unable to write the index")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{board::Board, explorer::Index, testing::Scratch};
+
+    fn counts(white: u32, draws: u32, black: u32) -> Counts {
+        Counts {
+            white,
+            draws,
+            black,
+            ..Counts::default()
+        }
+    }
+
+    #[test]
+    fn merge_sums_records_split_across_runs() {
+        let scratch = Scratch::new("explorer-merge");
+        let directory = scratch.path();
+        let mut runs = Runs {
+            index: directory.join("index"),
+            paths: Vec::new(),
+        };
+        let rated = Counts {
+            rated: 1,
+            rating_sum: 2000,
+            ..counts(0, 0, 1)
+        };
+        runs.write(&mut HashMap::from([
+            ((1, 10), counts(1, 0, 0)),
+            ((2, 5), counts(0, 1, 0)),
+        ]))
+        .unwrap();
+        runs.write(&mut HashMap::from([
+            ((1, 10), rated),
+            ((1, 11), counts(1, 0, 0)),
+        ]))
+        .unwrap();
+        runs.write(&mut HashMap::new()).unwrap();
+        runs.write(&mut HashMap::from([((1, 10), counts(2, 3, 0))]))
+            .unwrap();
+
+        let merged = directory.join("merged");
+        merge(&merged, b"header", &runs.paths).unwrap();
+        let bytes = fs::read(&merged).unwrap();
+        assert_eq!(&bytes[..6], b"header");
+        let (records, rest) = bytes[6..].as_chunks::<RECORD_SIZE>();
+        assert!(rest.is_empty());
+        let records: Vec<_> = records.iter().map(read_record).collect();
+        let total = Counts {
+            rated: 1,
+            rating_sum: 2000,
+            ..counts(3, 3, 1)
+        };
+        assert_eq!(
+            records,
+            [
+                (1, 10, total),
+                (1, 11, counts(1, 0, 0)),
+                (2, 5, counts(0, 1, 0))
+            ]
+        );
+
+        drop(runs);
+        assert_eq!(fs::read_dir(directory).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn build_and_look_up() {
+        let scratch = Scratch::new("explorer-build");
+        let directory = scratch.path();
+        let database = directory.join("games.pgn");
+        let games = concat!(
+            "[Result \"1-0\"]\n[WhiteElo \"2400\"]\n[BlackElo \"2200\"]\n\n1. e4 e5 2. Nf3 1-0\n\n",
+            "[Result \"0-1\"]\n\n1. e4 c5 0-1\n\n",
+            "[Result \"1/2-1/2\"]\n\n1. d4 d5 1/2-1/2\n\n",
+            "[Result \"*\"]\n\n1. c4 *\n",
+        );
+        fs::write(&database, games).unwrap();
+        assert!(Index::open(&database).is_err());
+
+        build(&database, |_| {}, &AtomicBool::new(false)).unwrap();
+        let index = Index::open(&database).unwrap();
+        let moves: Vec<_> = index
+            .moves(&Board::new_init())
+            .into_iter()
+            .map(|stats| (stats.r#move.uci(), stats.games(), stats.average_rating))
+            .collect();
+        assert_eq!(
+            moves,
+            [
+                (String::from("e2e4"), 2, Some(2300)),
+                (String::from("d2d4"), 1, None)
+            ]
+        );
+    }
+}
@@ -0,0 +1,552 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    annotation::Annotations,
+    board::Board,
+    pgn::Headers,
+    piece::{Piece, PieceColor},
+    r#move::Move,
+};
+
+/// How a game ended
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// The given player checkmated their opponent
+    Checkmate(PieceColor),
+
+    /// The player to move had no legal moves but was not in check
+    Stalemate,
+
+    /// The given player won because their opponent ran out of time
+    Timeout(PieceColor),
+
+    /// A player ran out of time, but their opponent could not have checkmated them
+    TimeoutVsInsufficientMaterial,
+}
+
+impl Outcome {
+    /// Returns the winner, or `None` for a draw
+    pub fn winner(&self) -> Option<PieceColor> {
+        match self {
+            Self::Checkmate(winner) | Self::Timeout(winner) => Some(*winner),
+            Self::Stalemate | Self::TimeoutVsInsufficientMaterial => None,
+        }
+    }
+
+    /// Returns the result as written in PGN, i.e. "1-0"
+    pub fn result(&self) -> &'static str {
+        match self.winner() {
+            Some(PieceColor::White) => "1-0",
+            Some(PieceColor::Black) => "0-1",
+            None => "1/2-1/2",
+        }
+    }
+}
+
+impl Display for Outcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = |color: &PieceColor| match color {
+            PieceColor::White => "White",
+            PieceColor::Black => "Black",
+        };
+        match self {
+            Self::Checkmate(winner) => write!(f, "{} wins by checkmate", name(winner)),
+            Self::Stalemate => write!(f, "Draw by stalemate"),
+            Self::Timeout(winner) => write!(f, "{} wins on time", name(winner)),
+            Self::TimeoutVsInsufficientMaterial => {
+                write!(f, "Draw by timeout vs insufficient material")
+            }
+        }
+    }
+}
+
+/// A move that has been played in a game
+#[derive(Clone, Debug)]
+pub struct PlayedMove {
+    /// The move itself
+    pub r#move: Move,
+
+    /// The move in standard algebraic notation
+    pub san: String,
+}
+
+/// Identifies a position in a game's tree of moves
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NodeId(usize);
+
+impl NodeId {
+    /// The starting position of every game
+    pub const ROOT: Self = Self(0);
+}
+
+/// A position in a game's tree of moves, reached by playing a move from its parent
+#[derive(Clone, Debug)]
+pub struct Node {
+    /// The move that reached this position, or `None` for the starting position
+    pub played: Option<PlayedMove>,
+
+    /// The position this one was reached from, or `None` for the starting position
+    pub parent: Option<NodeId>,
+
+    /// The positions reached from this one, with the main continuation first
+    pub children: Vec<NodeId>,
+
+    /// The number of moves played to reach this position
+    pub ply: usize,
+
+    /// The arrows and marked squares drawn on this position
+    pub annotations: Annotations,
+}
+
+/// What happens when a move is played while viewing a position that already has a continuation
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EditPast {
+    /// The move is rejected
+    #[default]
+    Forbid,
+
+    /// The move starts a new variation beside the existing continuation
+    Variation,
+
+    /// The existing continuation is discarded and replaced by the move
+    Truncate,
+}
+
+/// A chess game: a starting position, a tree of the moves played from it, and the position being
+/// viewed
+///
+/// The first child of every position is its main continuation, and any others are variations.
+pub struct Game {
+    start: Board,
+    board: Board,
+    nodes: Vec<Node>,
+    current: NodeId,
+    outcome: Option<Outcome>,
+
+    /// The length of the main line when a player ran out of time
+    timeout_length: usize,
+    edit_past: EditPast,
+    headers: Headers,
+}
+
+impl Game {
+    /// Creates a new game from the standard starting position
+    pub fn new() -> Self {
+        Self::from_board(Board::new_init())
+    }
+
+    /// Creates a new game starting from the given position
+    pub fn from_board(board: Board) -> Self {
+        let root = Node {
+            played: None,
+            parent: None,
+            children: Vec::new(),
+            ply: 0,
+            annotations: Annotations::default(),
+        };
+        Self {
+            start: board.clone(),
+            board,
+            nodes: vec![root],
+            current: NodeId::ROOT,
+            outcome: None,
+            timeout_length: 0,
+            edit_past: EditPast::Forbid,
+            headers: Headers::new(),
+        }
+    }
+
+    /// Sets what happens when a move is played while viewing an earlier position
+    pub fn set_edit_past(&mut self, edit_past: EditPast) {
+        self.edit_past = edit_past;
+    }
+
+    /// Returns the tags describing the game, such as the players and event
+    pub fn headers(&self) -> &Headers {
+        &self.headers
+    }
+
+    /// Returns a mutable reference to the tags describing the game
+    pub fn headers_mut(&mut self) -> &mut Headers {
+        &mut self.headers
+    }
+
+    /// Returns the position the game started from
+    pub fn start(&self) -> &Board {
+        &self.start
+    }
+
+    /// Returns the position currently being viewed
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// Returns the node of the position currently being viewed
+    pub fn current(&self) -> NodeId {
+        self.current
+    }
+
+    /// Returns the given node
+    pub fn node(&self, id: NodeId) -> &Node {
+        &self.nodes[id.0]
+    }
+
+    /// Returns a mutable reference to the arrows and marked squares drawn on the given node
+    pub fn annotations_mut(&mut self, id: NodeId) -> &mut Annotations {
+        &mut self.nodes[id.0].annotations
+    }
+
+    /// Returns the nodes of the main line, after the starting position
+    pub fn main_line(&self) -> Vec<NodeId> {
+        self.line_from(NodeId::ROOT)
+    }
+
+    /// Returns the nodes reached by following the main continuation from the given node, not
+    /// including the node itself
+    pub fn line_from(&self, id: NodeId) -> Vec<NodeId> {
+        let mut line = Vec::new();
+        let mut id = id;
+        while let Some(&child) = self.node(id).children.first() {
+            line.push(child);
+            id = child;
+        }
+        line
+    }
+
+    /// Returns the nodes from the starting position to the given node, not including the
+    /// starting position
+    pub fn path_to(&self, id: NodeId) -> Vec<NodeId> {
+        let mut path = Vec::new();
+        let mut id = id;
+        while let Some(parent) = self.node(id).parent {
+            path.push(id);
+            id = parent;
+        }
+        path.reverse();
+        path
+    }
+
+    /// Returns true if the given node is on the main line
+    pub fn is_main_line(&self, id: NodeId) -> bool {
+        let mut id = id;
+        while let Some(parent) = self.node(id).parent {
+            if self.node(parent).children.first() != Some(&id) {
+                return false;
+            }
+            id = parent;
+        }
+        true
+    }
+
+    /// Returns true if there are no moves played
+    pub fn is_empty(&self) -> bool {
+        self.node(NodeId::ROOT).children.is_empty()
+    }
+
+    /// Returns the number of moves played to reach the position being viewed
+    pub fn ply(&self) -> usize {
+        self.node(self.current).ply
+    }
+
+    /// Returns true if the last position of the main line is being viewed
+    pub fn is_at_end(&self) -> bool {
+        self.node(self.current).children.is_empty() && self.is_main_line(self.current)
+    }
+
+    /// Returns the move that led to the position being viewed, if any
+    pub fn last_move(&self) -> Option<Move> {
+        self.node(self.current)
+            .played
+            .as_ref()
+            .map(|played| played.r#move)
+    }
+
+    /// Returns the pieces captured on the way to the position being viewed, in the order they
+    /// were captured
+    pub fn captured_pieces(&self) -> Vec<Piece> {
+        self.path_to(self.current)
+            .into_iter()
+            .filter_map(|id| self.node(id).played.as_ref()?.r#move.captured())
+            .collect()
+    }
+
+    /// Returns how the game ended, if it has
+    pub fn outcome(&self) -> Option<Outcome> {
+        self.outcome
+    }
+
+    /// Ends the game because the given player ran out of time
+    ///
+    /// The game is drawn if their opponent could not have checkmated them.
+    pub fn flag(&mut self, color: PieceColor) {
+        if self.outcome.is_some() {
+            return;
+        }
+        let line = self.main_line();
+        let latest = self.board_at(line.last().copied().unwrap_or(NodeId::ROOT));
+        self.timeout_length = line.len();
+        self.outcome = Some(match latest.has_insufficient_material(color.opposite()) {
+            true => Outcome::TimeoutVsInsufficientMaterial,
+            false => Outcome::Timeout(color.opposite()),
+        });
+    }
+
+    /// Returns the moves played from the starting position to reach the given node
+    pub fn moves_to(&self, id: NodeId) -> Vec<Move> {
+        self.path_to(id)
+            .into_iter()
+            .map(|node| self.move_at(node))
+            .collect()
+    }
+
+    /// Returns the position at the given node
+    pub fn board_at(&self, id: NodeId) -> Board {
+        let mut board = self.start.clone();
+        for node in self.path_to(id) {
+            board.play_move(self.move_at(node));
+        }
+        board
+    }
+
+    /// Returns the fullmove number and color of the move at the given ply
+    pub fn move_number(&self, ply: usize) -> (u16, PieceColor) {
+        let offset = match self.start.to_move() {
+            PieceColor::White => ply,
+            PieceColor::Black => ply + 1,
+        };
+        let color = match offset % 2 {
+            0 => PieceColor::White,
+            _ => PieceColor::Black,
+        };
+        (self.start.fullmove_number() + (offset / 2) as u16, color)
+    }
+
+    /// Plays the given move from the position being viewed
+    ///
+    /// If the move was already played from this position, its existing node is viewed instead.
+    /// Otherwise, if the position already has a continuation, the move is handled as set by
+    /// [`Game::set_edit_past`].
+    pub fn play(&mut self, r#move: Move) -> Result<NodeId, &'static str> {
+        let node = self.node(self.current);
+        if let Some(&existing) = node.children.iter().find(|&&child| {
+            self.node(child).played.as_ref().map(|played| played.r#move) == Some(r#move)
+        }) {
+            self.go_to(existing);
+            return Ok(existing);
+        }
+
+        if node.children.is_empty() && self.outcome.is_some() && self.is_main_line(self.current) {
+            return Err("the game is over");
+        }
+        if !self.board.is_legal(r#move) {
+            return Err("illegal move");
+        }
+
+        if !self.node(self.current).children.is_empty() {
+            match self.edit_past {
+                EditPast::Forbid => {
+                    return Err("moves can only be played from the latest position");
+                }
+                EditPast::Variation => {}
+                EditPast::Truncate => self.truncate(self.current),
+            }
+        }
+
+        let san = self.board.san(r#move);
+        let id = NodeId(self.nodes.len());
+        self.nodes.push(Node {
+            played: Some(PlayedMove { r#move, san }),
+            parent: Some(self.current),
+            children: Vec::new(),
+            ply: self.ply() + 1,
+            annotations: Annotations::default(),
+        });
+        self.nodes[self.current.0].children.push(id);
+        self.board.play_move(r#move);
+        self.current = id;
+
+        if self.is_main_line(id) {
+            self.update_outcome();
+        }
+        Ok(id)
+    }
+
+    /// Adds the given moves as a line from the given node, beside any continuation it already
+    /// has, without changing the position being viewed, and returns the last node of the line
+    ///
+    /// The line stops at the first move that cannot be played, whose error is returned.
+    pub fn add_line(&mut self, from: NodeId, moves: &[Move]) -> Result<NodeId, &'static str> {
+        let (current, edit_past) = (self.current, self.edit_past);
+        self.go_to(from);
+        self.edit_past = EditPast::Variation;
+        let mut last = Ok(from);
+        for &r#move in moves {
+            last = self.play(r#move);
+            if last.is_err() {
+                break;
+            }
+        }
+        self.edit_past = edit_past;
+        self.go_to(current);
+        last
+    }
+
+    /// Discards every move played after the given node
+    pub fn truncate(&mut self, id: NodeId) {
+        if id == NodeId::ROOT || self.path_to(self.current).contains(&id) {
+            self.go_to(id);
+        }
+        self.nodes[id.0].children.clear();
+        self.update_outcome();
+    }
+
+    /// Deletes the given node and every move played after it
+    pub fn remove(&mut self, id: NodeId) {
+        let Some(parent) = self.node(id).parent else {
+            return;
+        };
+        if self.path_to(self.current).contains(&id) {
+            self.go_to(parent);
+        }
+        self.nodes[parent.0].children.retain(|&child| child != id);
+        self.update_outcome();
+    }
+
+    /// Makes the line through the given node the main line
+    pub fn promote_to_main_line(&mut self, id: NodeId) {
+        let mut id = id;
+        while let Some(parent) = self.node(id).parent {
+            let children = &mut self.nodes[parent.0].children;
+            let index = children
+                .iter()
+                .position(|&child| child == id)
+                .expect("a node is one of its parent's children");
+            let child = children.remove(index);
+            children.insert(0, child);
+            id = parent;
+        }
+        self.update_outcome();
+    }
+
+    /// Recomputes whether the main line ends in checkmate or stalemate
+    ///
+    /// A timeout is kept as long as the main line is unchanged in length.
+    fn update_outcome(&mut self) {
+        let line = self.main_line();
+        let timeout = matches!(
+            self.outcome,
+            Some(Outcome::Timeout(_) | Outcome::TimeoutVsInsufficientMaterial)
+        );
+        if timeout && line.len() == self.timeout_length {
+            return;
+        }
+
+        let board = self.board_at(line.last().copied().unwrap_or(NodeId::ROOT));
+        self.outcome = match board.legal_moves().is_empty() {
+            true if board.is_in_check(board.to_move()) => {
+                Some(Outcome::Checkmate(board.to_move().opposite()))
+            }
+            true => Some(Outcome::Stalemate),
+            false => None,
+        };
+    }
+
+    /// Returns the move that reached the given node, which must not be the starting position
+    fn move_at(&self, id: NodeId) -> Move {
+        self.node(id)
+            .played
+            .as_ref()
+            .expect("only the starting position has no move")
+            .r#move
+    }
+
+    /// Views the position at the given node
+    pub fn go_to(&mut self, id: NodeId) {
+        let target = self.path_to(id);
+        let mut current = self.path_to(self.current);
+
+        // step back to the last position shared by both paths, then forward to the target
+        while !current.is_empty() && target.get(current.len() - 1) != current.last() {
+            let node = current.pop().expect("path is not empty");
+            self.board.undo_move(self.move_at(node));
+        }
+        for &node in &target[current.len()..] {
+            self.board.play_move(self.move_at(node));
+        }
+        self.current = id;
+    }
+
+    /// Views the previous position, returning false if already at the start
+    pub fn step_back(&mut self) -> bool {
+        let Some(parent) = self.node(self.current).parent else {
+            return false;
+        };
+        self.board.undo_move(self.move_at(self.current));
+        self.current = parent;
+        true
+    }
+
+    /// Views the next position along the current line, returning false if there is none
+    pub fn step_forward(&mut self) -> bool {
+        let Some(&child) = self.node(self.current).children.first() else {
+            return false;
+        };
+        self.board.play_move(self.move_at(child));
+        self.current = child;
+        true
+    }
+
+    /// Views the last position of the line being viewed
+    pub fn go_to_end(&mut self) {
+        while self.step_forward() {}
+    }
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play(game: &mut Game, uci: &str) -> NodeId {
+        let r#move = game.board().parse_uci(uci).unwrap();
+        game.play(r#move).unwrap()
+    }
+
+    #[test]
+    fn timeout_kept_while_the_main_line_is_unchanged() {
+        let mut game = Game::new();
+        game.set_edit_past(EditPast::Variation);
+        let e4 = play(&mut game, "e2e4");
+        play(&mut game, "e7e5");
+        game.flag(PieceColor::White);
+        assert_eq!(game.outcome(), Some(Outcome::Timeout(PieceColor::Black)));
+
+        // a variation leaves the main line as it was
+        game.go_to(NodeId::ROOT);
+        play(&mut game, "d2d4");
+        assert_eq!(game.outcome(), Some(Outcome::Timeout(PieceColor::Black)));
+
+        // taking back the last move does not
+        game.truncate(e4);
+        assert_eq!(game.outcome(), None);
+        game.go_to(e4);
+        play(&mut game, "e7e5");
+        assert_eq!(game.outcome(), None);
+    }
+
+    #[test]
+    fn checkmate_ends_the_game() {
+        let mut game = Game::new();
+        for uci in ["f2f3", "e7e5", "g2g4", "d8h4"] {
+            play(&mut game, uci);
+        }
+        assert_eq!(game.outcome(), Some(Outcome::Checkmate(PieceColor::Black)));
+    }
+}
@@ -13,10 +13,61 @@ pub mod r#move;
 /// A chess piece
 pub mod piece;
 
+/// A chess game and its move history
+pub mod game;
+
+/// Chess clocks and time controls
+pub mod clock;
+
+/// Arrows and marked squares drawn on positions
+pub mod annotation;
+
+/// Reading and writing games in Portable Game Notation
+pub mod pgn;
+
+/// Chess engines, built in and external
+pub mod engine;
+
+/// Opening books in the Polyglot format
+pub mod book;
+
+/// Naming openings by their code in the Encyclopaedia of Chess Openings
+pub mod eco;
+
+/// Exploring the moves played in databases of games
+pub mod explorer;
+
+/// Storing and searching collections of games
+pub mod database;
+
+/// Judging the moves of games with an engine
+pub mod review;
+
+/// Endgame tablebases in the Syzygy format
+pub mod tablebase;
+
 /// The chess GUI
 pub mod app;
 
+/// Temporary files for the tests
+#[cfg(test)]
+pub mod testing;
+
 fn main() {
+    // `chess uci` runs the built-in engine for other chess programs instead of the app
+    if std::env::args().nth(1).as_deref() == Some("uci") {
+        engine::server::run();
+        return;
+    }
+    // `chess book` builds an opening book from games instead
+    if std::env::args().nth(1).as_deref() == Some("book") {
+        if let Err(error) = book::builder::run(std::env::args().skip(2)) {
+            eprintln!("{error}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
     eframe::run_native(
         "Chess",
         eframe::NativeOptions::default(),
@@ -1,7 +1,10 @@
-use crate::{board::Square, piece::Piece};
+use crate::{
+    board::{CastlingFiles, File, Rank, Square},
+    piece::{Piece, PieceColor},
+};
 
 /// A move on the chess board
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Move {
     /// A piece moving with no capture
     Move {
@@ -64,14 +67,172 @@ pub enum Move {
     },
 
     /// White castling kingside
-    KingsideCastleWhite,
+    KingsideCastleWhite {
+        /// The file the king starts on
+        king: File,
+
+        /// The file the rook starts on
+        rook: File,
+    },
 
     /// White castling queenside
-    QueensideCastleWhite,
+    QueensideCastleWhite {
+        /// The file the king starts on
+        king: File,
+
+        /// The file the rook starts on
+        rook: File,
+    },
 
     /// Black castling kingside
-    KingsideCastleBlack,
+    KingsideCastleBlack {
+        /// The file the king starts on
+        king: File,
+
+        /// The file the rook starts on
+        rook: File,
+    },
 
     /// Black castling queenside
-    QueensideCastleBlack,
+    QueensideCastleBlack {
+        /// The file the king starts on
+        king: File,
+
+        /// The file the rook starts on
+        rook: File,
+    },
+}
+
+impl Move {
+    /// Returns the square the moving piece starts on, which is the king's square for castling
+    pub fn from(&self) -> Square {
+        match self {
+            Self::Move { from, .. }
+            | Self::Capture { from, .. }
+            | Self::PromotionMove { from, .. }
+            | Self::PromotionCapture { from, .. }
+            | Self::EnPassantCapture { from, .. } => *from,
+            Self::KingsideCastleWhite { king, .. } | Self::QueensideCastleWhite { king, .. } => {
+                Square::new(*king, Rank::new(1))
+            }
+            Self::KingsideCastleBlack { king, .. } | Self::QueensideCastleBlack { king, .. } => {
+                Square::new(*king, Rank::new(8))
+            }
+        }
+    }
+
+    /// Returns the square the moving piece ends on, which is the king's square for castling
+    pub fn to(&self) -> Square {
+        match self {
+            Self::Move { to, .. }
+            | Self::Capture { to, .. }
+            | Self::PromotionMove { to, .. }
+            | Self::PromotionCapture { to, .. }
+            | Self::EnPassantCapture { to, .. } => *to,
+            Self::KingsideCastleWhite { .. } => Square::from_indices(6, 0),
+            Self::QueensideCastleWhite { .. } => Square::from_indices(2, 0),
+            Self::KingsideCastleBlack { .. } => Square::from_indices(6, 7),
+            Self::QueensideCastleBlack { .. } => Square::from_indices(2, 7),
+        }
+    }
+
+    /// Returns the piece captured by this move, if any
+    pub fn captured(&self) -> Option<Piece> {
+        match self {
+            Self::Capture { capturing, .. }
+            | Self::PromotionCapture { capturing, .. }
+            | Self::EnPassantCapture { capturing, .. } => Some(*capturing),
+            _ => None,
+        }
+    }
+
+    /// Returns the piece a pawn promotes to with this move, if any
+    pub fn promotion(&self) -> Option<Piece> {
+        match self {
+            Self::PromotionMove { promoting, .. } | Self::PromotionCapture { promoting, .. } => {
+                Some(*promoting)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the square the captured piece was on, which differs from the destination for en
+    /// passant captures
+    pub fn capture_square(&self) -> Option<Square> {
+        match self {
+            Self::EnPassantCapture { from, to, .. } => Some(Square::new(to.file, from.rank)),
+            _ => self.captured().map(|_| self.to()),
+        }
+    }
+
+    /// Returns the squares the castling rook moves from and to, if this move is a castle
+    pub fn castling_rook(&self) -> Option<(Square, Square)> {
+        match self {
+            Self::KingsideCastleWhite { rook, .. } => {
+                Some((Square::new(*rook, Rank::new(1)), "f1".into()))
+            }
+            Self::QueensideCastleWhite { rook, .. } => {
+                Some((Square::new(*rook, Rank::new(1)), "d1".into()))
+            }
+            Self::KingsideCastleBlack { rook, .. } => {
+                Some((Square::new(*rook, Rank::new(8)), "f8".into()))
+            }
+            Self::QueensideCastleBlack { rook, .. } => {
+                Some((Square::new(*rook, Rank::new(8)), "d8".into()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns this move in the long algebraic notation used by the Universal Chess Interface,
+    /// i.e. "e2e4", "e7e8q", or "e1g1" for castling
+    pub fn uci(&self) -> String {
+        let mut uci = format!("{}{}", self.from(), self.to());
+        if let Some(promoting) = self.promotion() {
+            uci.push(promoting.kind.letter().to_ascii_lowercase());
+        }
+        uci
+    }
+
+    /// Returns this move in the long algebraic notation used by the Universal Chess Interface in
+    /// Chess960, where castling is written as the king capturing its own rook, i.e. "e1h1"
+    pub fn uci_chess960(&self) -> String {
+        match self.castling_rook() {
+            Some((rook, _)) => format!("{}{rook}", self.from()),
+            None => self.uci(),
+        }
+    }
+
+    /// Returns true if this move is a castle
+    pub fn is_castle(&self) -> bool {
+        self.castling_rook().is_some()
+    }
+
+    /// Returns true if this move is a castle to the kingside
+    pub fn is_kingside_castle(&self) -> bool {
+        matches!(
+            self,
+            Self::KingsideCastleWhite { .. } | Self::KingsideCastleBlack { .. }
+        )
+    }
+
+    /// Returns the kingside castling move for the given color, with the king and rook starting on
+    /// the given files
+    pub const fn kingside_castle(color: PieceColor, files: CastlingFiles) -> Self {
+        let (king, rook) = (files.king, files.kingside_rook);
+        match color {
+            PieceColor::White => Self::KingsideCastleWhite { king, rook },
+            PieceColor::Black => Self::KingsideCastleBlack { king, rook },
+        }
+    }
+
+    /// Returns the queenside castling move for the given color, with the king and rook starting
+    /// on the given files
+    pub const fn queenside_castle(color: PieceColor, files: CastlingFiles) -> Self {
+        let (king, rook) = (files.king, files.queenside_rook);
+        match color {
+            PieceColor::White => Self::QueensideCastleWhite { king, rook },
+            PieceColor::Black => Self::QueensideCastleBlack { king, rook },
+        }
+    }
 }
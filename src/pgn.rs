@@ -0,0 +1,588 @@
+use crate::{
+    annotation::glyph_nag,
+    board::Board,
+    game::{EditPast, Game, NodeId},
+    piece::PieceColor,
+    r#move::Move,
+};
+
+/// The longest line written in PGN movetext
+const MAX_LINE_LENGTH: usize = 80;
+
+/// The tags every PGN game starts with, in order, and their values when unknown
+const SEVEN_TAG_ROSTER: [(&str, &str); 7] = [
+    ("Event", "?"),
+    ("Site", "?"),
+    ("Date", "????.??.??"),
+    ("Round", "?"),
+    ("White", "?"),
+    ("Black", "?"),
+    ("Result", "*"),
+];
+
+/// The tag pairs describing a game, i.e. `[White "Carlsen, Magnus"]`, in the order they are
+/// written
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Headers {
+    tags: Vec<(String, String)>,
+}
+
+impl Headers {
+    /// Constructs new headers with every tag of the seven tag roster unknown
+    pub fn new() -> Self {
+        Self {
+            tags: SEVEN_TAG_ROSTER
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+        }
+    }
+
+    /// Returns the value of the given tag, if it is set
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Sets the value of the given tag, keeping its place if it is already set
+    pub fn set(&mut self, name: &str, value: impl Into<String>) {
+        let value = value.into();
+        match self.tags.iter_mut().find(|(tag, _)| tag == name) {
+            Some((_, existing)) => *existing = value,
+            None => self.tags.push((name.to_string(), value)),
+        }
+    }
+
+    /// Removes the given tag
+    pub fn remove(&mut self, name: &str) {
+        self.tags.retain(|(tag, _)| tag != name);
+    }
+
+    /// Iterates through every tag name and value, in order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.tags
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+}
+
+impl Default for Headers {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Writes the given game in Portable Game Notation, with its variations
+///
+/// The result tag is taken from the game's outcome if it has one, and the starting position is
+/// recorded in the FEN tag if it is not the standard one.
+pub fn write(game: &Game) -> String {
+    let mut headers = game.headers().clone();
+    let result = match game.outcome() {
+        Some(outcome) => outcome.result(),
+        None => headers.get("Result").unwrap_or("*"),
+    }
+    .to_string();
+    headers.set("Result", result.clone());
+
+    let fen = game.start().fen();
+    if fen != Board::STARTING_FEN {
+        headers.set("SetUp", "1");
+        headers.set("FEN", fen);
+    }
+
+    let mut pgn = String::new();
+    for (name, value) in headers.iter() {
+        let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+        pgn.push_str(&format!("[{name} \"{value}\"]\n"));
+    }
+    pgn.push('\n');
+
+    let mut tokens = Vec::new();
+    write_annotations(
+        game.node(NodeId::ROOT).annotations.to_commands(),
+        &mut tokens,
+    );
+    write_line(game, NodeId::ROOT, true, &mut tokens);
+    tokens.push(result);
+
+    // wrap the movetext, keeping parentheses against the moves they enclose
+    let mut line = String::new();
+    let mut previous: Option<&str> = None;
+    for token in &tokens {
+        let joined = previous == Some("(") || token == ")";
+        if !joined && !line.is_empty() && line.len() + 1 + token.len() > MAX_LINE_LENGTH {
+            pgn.push_str(&line);
+            pgn.push('\n');
+            line.clear();
+        } else if !joined && !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(token);
+        previous = Some(token);
+    }
+    pgn.push_str(&line);
+    pgn.push('\n');
+    pgn
+}
+
+/// Adds the tokens of the moves after the given node, with variations in parentheses after the
+/// main move they are alternatives to
+fn write_line(game: &Game, parent: NodeId, numbered: bool, tokens: &mut Vec<String>) {
+    let children = &game.node(parent).children;
+    let Some(&main) = children.first() else {
+        return;
+    };
+
+    write_move(game, main, numbered, tokens);
+    for &variation in &children[1..] {
+        tokens.push(String::from("("));
+        write_move(game, variation, true, tokens);
+        write_line(game, variation, false, tokens);
+        tokens.push(String::from(")"));
+    }
+
+    // black's move needs its number again after a variation interrupts it
+    write_line(game, main, children.len() > 1, tokens);
+}
+
+/// Adds the tokens of a single move, numbered if it is white's or `numbered` is set
+fn write_move(game: &Game, id: NodeId, numbered: bool, tokens: &mut Vec<String>) {
+    let node = game.node(id);
+    let (number, color) = game.move_number(node.ply - 1);
+    match color {
+        PieceColor::White => tokens.push(format!("{number}.")),
+        PieceColor::Black if numbered => tokens.push(format!("{number}...")),
+        PieceColor::Black => {}
+    }
+    let played = node.played.as_ref().expect("moves have been played");
+    tokens.push(played.san.clone());
+    for nag in &node.annotations.nags {
+        tokens.push(format!("${nag}"));
+    }
+    write_annotations(node.annotations.to_commands(), tokens);
+}
+
+/// Adds a comment holding the given annotation commands, unless there are none
+fn write_annotations(commands: String, tokens: &mut Vec<String>) {
+    if !commands.is_empty() {
+        tokens.push(format!("{{{commands}}}"));
+    }
+}
+
+/// Reads the first game from text in Portable Game Notation
+pub fn read(text: &str) -> Result<Game, &'static str> {
+    Reader::new(text).next().unwrap_or(Err("no game found"))
+}
+
+/// The tags of a game and the moves of its main line, read without building the whole game
+#[derive(Clone)]
+pub struct MainLine {
+    /// The game's tag pairs
+    pub headers: Headers,
+
+    /// The position the game starts from
+    pub start: Board,
+
+    /// The moves of the main line, from the start
+    pub moves: Vec<Move>,
+}
+
+/// A token of Portable Game Notation
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Token<'a> {
+    /// A tag pair, with the value still escaped
+    Tag(&'a str, &'a str),
+
+    /// A move in standard algebraic notation
+    San(&'a str),
+
+    /// The text of a brace comment, without its braces
+    Comment(&'a str),
+
+    /// A numeric annotation glyph, i.e. `$2` or `?` for a mistake
+    Nag(u8),
+
+    /// The start of a variation
+    Open,
+
+    /// The end of a variation
+    Close,
+
+    /// The result that ends a game's movetext
+    Result,
+}
+
+/// Reads every game from text in Portable Game Notation, one at a time
+///
+/// Numeric annotation glyphs, and arrows, marked squares, and evaluations in comments are kept as
+/// annotations, and the rest of each comment and move numbers are skipped. A game that fails to parse
+/// is returned as an error, and reading continues from the next game.
+pub struct Reader<'a> {
+    text: &'a str,
+    position: usize,
+    peeked: Option<Token<'a>>,
+}
+
+impl<'a> Reader<'a> {
+    /// Constructs a new reader of the games in the given text
+    pub fn new(text: &'a str) -> Self {
+        Self {
+            text,
+            position: 0,
+            peeked: None,
+        }
+    }
+
+    /// Returns the next token, or `None` at the end of the text
+    fn next_token(&mut self) -> Option<Token<'a>> {
+        if let Some(token) = self.peeked.take() {
+            return Some(token);
+        }
+
+        loop {
+            let rest = &self.text[self.position..];
+            let trimmed = rest.trim_start();
+            self.position += rest.len() - trimmed.len();
+            let c = trimmed.chars().next()?;
+
+            let (token, length) = match c {
+                '{' => {
+                    let end = trimmed.find('}').unwrap_or(trimmed.len());
+                    (Some(Token::Comment(&trimmed[1..end])), end + 1)
+                }
+                ';' | '%' => {
+                    let end = trimmed.find('\n').unwrap_or(trimmed.len());
+                    (None, end)
+                }
+                '[' => {
+                    let end = tag_end(trimmed);
+                    (parse_tag(&trimmed[..end]), end)
+                }
+                '(' => (Some(Token::Open), 1),
+                ')' => (Some(Token::Close), 1),
+                '*' => (Some(Token::Result), 1),
+                _ => {
+                    let end = trimmed
+                        .find(|c: char| c.is_whitespace() || "{}()[];".contains(c))
+                        .unwrap_or(trimmed.len());
+                    let word = &trimmed[..end];
+                    (classify(word), end)
+                }
+            };
+
+            self.position += length.max(1).min(trimmed.len());
+            if let Some(token) = token {
+                return Some(token);
+            }
+        }
+    }
+
+    /// Reads the tags and at most the given number of main line moves of the next game, which is
+    /// much quicker than reading the whole game when its variations and comments are not needed
+    pub fn next_main_line(&mut self, max_ply: usize) -> Option<Result<MainLine, &'static str>> {
+        let (headers, tokens) = self.read_tokens()?;
+        Some(build_main_line(headers, &tokens, max_ply))
+    }
+
+    /// Reads the tokens of the next game into a game, skipping to its end on errors
+    fn read_game(&mut self) -> Option<Result<Game, &'static str>> {
+        let (headers, tokens) = self.read_tokens()?;
+        Some(build_game(headers, &tokens))
+    }
+
+    /// Reads the tags and movetext tokens of the next game, or returns `None` at the end of the
+    /// text
+    fn read_tokens(&mut self) -> Option<(Headers, Vec<Token<'a>>)> {
+        let mut headers = Headers::new();
+        let mut tokens = Vec::new();
+        let mut depth = 0;
+
+        while let Some(token) = self.next_token() {
+            match token {
+                // tags after the movetext start the next game
+                Token::Tag(..) if !tokens.is_empty() => {
+                    self.peeked = Some(token);
+                    break;
+                }
+                Token::Tag(name, value) => {
+                    headers.set(name, value.replace("\\\"", "\"").replace("\\\\", "\\"));
+                    continue;
+                }
+                Token::Open => depth += 1,
+                Token::Close => depth -= 1,
+                Token::Result if depth == 0 => {
+                    tokens.push(token);
+                    break;
+                }
+                _ => {}
+            }
+            tokens.push(token);
+        }
+
+        if tokens.is_empty() && headers == Headers::new() {
+            return None;
+        }
+        Some((headers, tokens))
+    }
+}
+
+impl Iterator for Reader<'_> {
+    type Item = Result<Game, &'static str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_game()
+    }
+}
+
+/// Returns the length of the tag pair at the start of the text, including its brackets
+fn tag_end(text: &str) -> usize {
+    let mut escaped = false;
+    let mut quoted = false;
+    for (index, c) in text.char_indices() {
+        match c {
+            '\\' if quoted && !escaped => {
+                escaped = true;
+                continue;
+            }
+            '"' if !escaped => quoted = !quoted,
+            ']' if !quoted => return index + 1,
+            _ => {}
+        }
+        escaped = false;
+    }
+    text.len()
+}
+
+/// Parses a tag pair such as `[White "Carlsen, Magnus"]`
+fn parse_tag(text: &str) -> Option<Token<'_>> {
+    let inner = text.strip_prefix('[')?.trim_end_matches(']').trim();
+    let (name, value) = inner.split_once(char::is_whitespace)?;
+    let value = value.trim().strip_prefix('"')?.strip_suffix('"')?;
+    Some(Token::Tag(name, value))
+}
+
+/// Classifies a word of movetext, skipping move numbers and annotation glyphs
+fn classify(word: &str) -> Option<Token<'_>> {
+    match word {
+        "1-0" | "0-1" | "1/2-1/2" => Some(Token::Result),
+        _ if word.starts_with('$') => word[1..].parse().ok().map(Token::Nag),
+        _ if word.chars().all(|c| "!?".contains(c)) => glyph_nag(word).map(Token::Nag),
+        // move numbers may run into the move or the result, i.e. "1.e4", "1.0-0", or "40.1-0"
+        _ if word.starts_with(|c: char| c.is_ascii_digit()) => {
+            let dots = word.trim_start_matches(|c: char| c.is_ascii_digit());
+            let rest = dots.trim_start_matches('.');
+            match rest.len() < dots.len() {
+                true if rest.is_empty() => None,
+                true => classify(rest),
+                false if rest.is_empty() => None,
+                // castling with zeros, or a malformed word left for the move parser to reject
+                false => Some(Token::San(word)),
+            }
+        }
+        _ => Some(Token::San(word)),
+    }
+}
+
+/// Returns the position described by the FEN tag of the given headers, or the standard starting
+/// position if there is none, failing if it could not be reached in a game
+fn start_position(headers: &Headers) -> Result<Board, &'static str> {
+    let Some(fen) = headers.get("FEN") else {
+        return Ok(Board::new_init());
+    };
+    let board = Board::from_fen(fen)?;
+    board.validate()?;
+    Ok(board)
+}
+
+/// Plays at most the given number of moves of the main line of the given movetext from the
+/// position described by the headers
+fn build_main_line(
+    headers: Headers,
+    tokens: &[Token],
+    max_ply: usize,
+) -> Result<MainLine, &'static str> {
+    let start = start_position(&headers)?;
+
+    let mut board = start.clone();
+    let mut moves = Vec::new();
+    let mut depth = 0;
+    for token in tokens {
+        match *token {
+            Token::San(san) if depth == 0 => {
+                if moves.len() == max_ply {
+                    break;
+                }
+                let r#move = board.parse_san(san)?;
+                board.play_move(r#move);
+                moves.push(r#move);
+            }
+            Token::Open => depth += 1,
+            Token::Close => depth -= 1,
+            _ => {}
+        }
+    }
+    Ok(MainLine {
+        headers,
+        start,
+        moves,
+    })
+}
+
+/// Builds a game by playing the given movetext from the position described by the headers
+fn build_game(headers: Headers, tokens: &[Token]) -> Result<Game, &'static str> {
+    let board = start_position(&headers)?;
+
+    let mut game = Game::from_board(board);
+    game.set_edit_past(EditPast::Variation);
+
+    // the positions to return to when each open variation ends
+    let mut returns = Vec::new();
+    for token in tokens {
+        match *token {
+            Token::San(san) => {
+                let r#move = game.board().parse_san(san)?;
+                let id = game.play(r#move)?;
+                // a glyph may be written against the move, i.e. "Nf3?!"
+                let symbol = &san[san.trim_end_matches(['!', '?']).len()..];
+                if let Some(nag) = glyph_nag(symbol) {
+                    game.annotations_mut(id).nags.push(nag);
+                }
+            }
+            Token::Nag(nag) => {
+                let current = game.current();
+                game.annotations_mut(current).nags.push(nag);
+            }
+            Token::Open => {
+                let current = game.current();
+                let parent = game
+                    .node(current)
+                    .parent
+                    .ok_or("variation before any move")?;
+                returns.push(current);
+                game.go_to(parent);
+            }
+            Token::Close => {
+                let back = returns.pop().ok_or("unbalanced parentheses")?;
+                game.go_to(back);
+            }
+            Token::Comment(comment) => {
+                let current = game.current();
+                game.annotations_mut(current).add_commands(comment);
+            }
+            Token::Tag(..) | Token::Result => {}
+        }
+    }
+
+    game.set_edit_past(EditPast::Forbid);
+    game.go_to(NodeId::ROOT);
+    game.go_to_end();
+    *game.headers_mut() = headers;
+    Ok(game)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::Score;
+
+    /// Returns the moves of the main line of the given game in standard algebraic notation
+    fn main_line_san(game: &Game) -> Vec<String> {
+        game.main_line()
+            .into_iter()
+            .map(|id| {
+                game.node(id)
+                    .played
+                    .as_ref()
+                    .expect("moves have been played")
+                    .san
+                    .clone()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let text = concat!(
+            "[Event \"A \\\"quoted\\\" event\"]\n",
+            "[White \"Anderssen\"]\n",
+            "[Black \"Kieseritzky\"]\n",
+            "[Result \"1-0\"]\n",
+            "\n",
+            "{[%csl Ge4]} 1. e4 e5 2. f4 $1 {[%eval 0.35][%cal Rf4e5]} exf4 (2... d5 3. exd5 (3. Nc3)",
+            " 3... c6) 3. Bc4?! Qh4+ 4. Kf1 b5 1-0\n",
+        );
+        let game = read(text).unwrap();
+        assert_eq!(game.headers().get("Event"), Some("A \"quoted\" event"));
+        assert_eq!(
+            main_line_san(&game),
+            ["e4", "e5", "f4", "exf4", "Bc4", "Qh4+", "Kf1", "b5"]
+        );
+
+        let main_line = game.main_line();
+        let f4 = game.node(main_line[2]);
+        assert_eq!(f4.annotations.nags, [1]);
+        assert_eq!(f4.annotations.eval, Some(Score::Centipawns(35)));
+        assert_eq!(f4.annotations.arrows.len(), 1);
+        assert_eq!(game.node(main_line[4]).annotations.nags, [6]);
+        assert_eq!(game.node(NodeId::ROOT).annotations.squares.len(), 1);
+        assert_eq!(game.node(main_line[2]).children.len(), 2);
+
+        let written = write(&game);
+        let reread = read(&written).unwrap();
+        assert_eq!(main_line_san(&reread), main_line_san(&game));
+        assert_eq!(write(&reread), written);
+        assert!(written.contains("2. f4 $1 {[%eval 0.35][%cal Rf4e5]} exf4 (2... d5"));
+        assert!(written.contains("3. Bc4 $6 Qh4+"));
+        assert!(written.ends_with("1-0\n"));
+    }
+
+    #[test]
+    fn round_trip_from_a_position() {
+        let fen = "4k3/8/8/8/8/8/4P3/4K3 b - - 0 40";
+        let mut game = Game::from_board(Board::from_fen(fen).unwrap());
+        for san in ["Kd7", "e4", "Ke6"] {
+            let r#move = game.board().parse_san(san).unwrap();
+            game.play(r#move).unwrap();
+        }
+
+        let written = write(&game);
+        assert!(written.contains("[SetUp \"1\"]"));
+        assert!(written.contains("40... Kd7 41. e4 Ke6 *"));
+        let reread = read(&written).unwrap();
+        assert_eq!(reread.start().fen(), fen);
+        assert_eq!(main_line_san(&reread), ["Kd7", "e4", "Ke6"]);
+        assert_eq!(write(&reread), written);
+    }
+
+    #[test]
+    fn impossible_starting_positions() {
+        let game = |fen: &str| format!("[FEN \"{fen}\"]\n[SetUp \"1\"]\n\n*\n");
+        assert!(read(&game("4k3/8/8/8/8/8/8/4K3 w - - 0 1")).is_ok());
+        // no white king
+        assert!(read(&game("4k3/8/8/8/8/8/8/8 w - - 0 1")).is_err());
+        // no pawn just moved past the en passant square
+        let text = game("4k3/8/8/8/8/8/8/4K3 w - e6 0 1");
+        assert!(read(&text).is_err());
+        assert!(Reader::new(&text).next_main_line(10).unwrap().is_err());
+    }
+
+    #[test]
+    fn move_numbers_run_into_moves_and_results() {
+        let mut reader = Reader::new("1.e4 e5 2.Nf3 Nc6 3.Bc4 Nf6 4.0-0 Bc5 5.1-0\n\n1.d4 d5 0-1");
+        let game = reader.next().unwrap().unwrap();
+        assert_eq!(
+            main_line_san(&game),
+            ["e4", "e5", "Nf3", "Nc6", "Bc4", "Nf6", "O-O", "Bc5"]
+        );
+        let game = reader.next().unwrap().unwrap();
+        assert_eq!(main_line_san(&game), ["d4", "d5"]);
+        assert!(reader.next().is_none());
+
+        let game = read("1.e4 e5 2.Nf3 Nc6 3.Bc4 Nf6 4.0-0 4...1/2-1/2").unwrap();
+        assert_eq!(main_line_san(&game).len(), 7);
+        assert!(read("1.e4 e5 2-").is_err());
+    }
+}
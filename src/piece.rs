@@ -1,7 +1,7 @@
 use std::fmt::Display;
 
 /// A chess piece with an associated kind and color
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct Piece {
     /// The piece's kind
     pub kind: PieceKind,
@@ -100,7 +100,7 @@ impl Piece {
 }
 
 /// A kind of chess piece
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum PieceKind {
     /// A pawn
     Pawn,
@@ -121,6 +121,45 @@ pub enum PieceKind {
     King,
 }
 
+impl PieceKind {
+    /// Returns the kind of piece with the given letter, in either case, i.e. 'N' or 'n' for a
+    /// knight
+    pub const fn from_letter(letter: char) -> Option<Self> {
+        match letter.to_ascii_uppercase() {
+            'P' => Some(Self::Pawn),
+            'N' => Some(Self::Knight),
+            'B' => Some(Self::Bishop),
+            'R' => Some(Self::Rook),
+            'Q' => Some(Self::Queen),
+            'K' => Some(Self::King),
+            _ => None,
+        }
+    }
+
+    /// Returns the conventional value of this kind of piece in pawns, which is 0 for a king
+    pub const fn value(&self) -> u32 {
+        match self {
+            Self::Pawn => 1,
+            Self::Knight | Self::Bishop => 3,
+            Self::Rook => 5,
+            Self::Queen => 9,
+            Self::King => 0,
+        }
+    }
+
+    /// Returns the uppercase letter of this kind of piece, which is 'P' for a pawn
+    pub const fn letter(&self) -> char {
+        match self {
+            Self::Pawn => 'P',
+            Self::Knight => 'N',
+            Self::Bishop => 'B',
+            Self::Rook => 'R',
+            Self::Queen => 'Q',
+            Self::King => 'K',
+        }
+    }
+}
+
 impl Display for PieceKind {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -135,7 +174,7 @@ impl Display for PieceKind {
 }
 
 /// A color of chess piece
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum PieceColor {
     /// A white piece
     White,
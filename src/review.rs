@@ -0,0 +1,242 @@
+use crate::{
+    annotation::glyph,
+    engine::Score,
+    game::{Game, NodeId},
+    piece::PieceColor,
+    r#move::Move,
+};
+
+/// The most moves of the engine's better line added as a variation after a bad move
+const MAX_VARIATION_PLIES: usize = 8;
+
+/// The most centipawns a move can lose, so a missed mate counts as a large loss rather than an
+/// endless one
+const MAX_LOSS: i32 = 1000;
+
+/// How badly a move was judged, by how much it lowered the mover's chance of winning
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Judgement {
+    /// A move that lost at least 5 points of winning chance
+    Inaccuracy,
+
+    /// A move that lost at least 10 points of winning chance
+    Mistake,
+
+    /// A move that lost at least 15 points of winning chance
+    Blunder,
+}
+
+impl Judgement {
+    /// Judges a move by the points of winning chance, out of 100, it lost, or returns `None` for
+    /// a good move
+    pub fn from_loss(loss: f32) -> Option<Self> {
+        match loss {
+            15.0.. => Some(Self::Blunder),
+            10.0.. => Some(Self::Mistake),
+            5.0.. => Some(Self::Inaccuracy),
+            _ => None,
+        }
+    }
+
+    /// Returns the numeric annotation glyph marking a move so judged, i.e. 2 for a mistake
+    pub const fn nag(&self) -> u8 {
+        match self {
+            Self::Inaccuracy => 6,
+            Self::Mistake => 2,
+            Self::Blunder => 4,
+        }
+    }
+}
+
+/// What an engine found in a position of a game
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Evaluation {
+    /// How good the position is for the player to move
+    pub score: Score,
+
+    /// The best line, starting with the best move, or empty if there are no legal moves
+    pub pv: Vec<Move>,
+}
+
+/// How well one player played over a game
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PlayerReview {
+    /// The average accuracy of the player's moves, from 0 to 100
+    pub accuracy: f32,
+
+    /// The average number of centipawns each of the player's moves lost
+    pub average_loss: f32,
+
+    /// The number of the player's moves judged inaccuracies
+    pub inaccuracies: u32,
+
+    /// The number of the player's moves judged mistakes
+    pub mistakes: u32,
+
+    /// The number of the player's moves judged blunders
+    pub blunders: u32,
+}
+
+/// Returns the given score in centipawns, with mates counted as the largest possible advantage,
+/// and a checkmated player's `Mate(0)` as the largest disadvantage
+pub fn centipawns(score: Score) -> i32 {
+    match score {
+        Score::Centipawns(centipawns) => centipawns.clamp(-MAX_LOSS, MAX_LOSS),
+        Score::Mate(moves) if moves > 0 => MAX_LOSS,
+        Score::Mate(_) => -MAX_LOSS,
+    }
+}
+
+/// Returns the chance, out of 100, that the player with the given score goes on to win, by the
+/// curve fitted to rated games on Lichess
+pub fn win_chance(score: Score) -> f32 {
+    let centipawns = centipawns(score) as f32;
+    50.0 + 50.0 * (2.0 / (1.0 + (-0.003_682_08 * centipawns).exp()) - 1.0)
+}
+
+/// Returns the accuracy, from 0 to 100, of a move that lowered the mover's chance of winning
+/// from `before` to `after`
+pub fn move_accuracy(before: f32, after: f32) -> f32 {
+    (103.166_8 * (-0.043_54 * (before - after).max(0.0)).exp() - 3.166_9).clamp(0.0, 100.0)
+}
+
+/// Annotates the moves of the given line of a game with the engine's evaluations of the
+/// positions they reached, returning how well white and black played
+///
+/// The line runs from a position through the moves played after it, with an evaluation for
+/// each. Every move gets its evaluation as an `[%eval]` command, and inaccuracies, mistakes, and
+/// blunders get their glyph and the engine's better line as a variation. Glyphs from an earlier
+/// review are replaced.
+pub fn annotate(game: &mut Game, line: &[NodeId], evaluations: &[Evaluation]) -> [PlayerReview; 2] {
+    let mut reviews = [PlayerReview::default(); 2];
+    let mut accuracy_sums = [0.0; 2];
+    let mut loss_sums = [0; 2];
+    let mut counts = [0; 2];
+
+    for (index, pair) in evaluations.windows(2).enumerate() {
+        let (before, after) = (&pair[0], &pair[1]);
+        let (parent, id) = (line[index], line[index + 1]);
+        let mover = game.board_at(parent).to_move();
+        let side = match mover {
+            PieceColor::White => 0,
+            PieceColor::Black => 1,
+        };
+
+        // both are taken from the mover's point of view, turning the score after the move
+        // around rather than the score itself, as a checkmated side's score has no sign to flip
+        let (chance_before, chance_after) =
+            (win_chance(before.score), 100.0 - win_chance(after.score));
+        let loss = chance_before - chance_after;
+        accuracy_sums[side] += move_accuracy(chance_before, chance_after);
+        loss_sums[side] += (centipawns(before.score) + centipawns(after.score)).max(0);
+        counts[side] += 1;
+
+        let annotations = game.annotations_mut(id);
+        annotations.eval = Some(match mover {
+            PieceColor::White => after.score.negate(),
+            PieceColor::Black => after.score,
+        });
+        annotations.nags.retain(|&nag| glyph(nag).is_none());
+        let Some(judgement) = Judgement::from_loss(loss) else {
+            continue;
+        };
+        annotations.nags.push(judgement.nag());
+        match judgement {
+            Judgement::Inaccuracy => reviews[side].inaccuracies += 1,
+            Judgement::Mistake => reviews[side].mistakes += 1,
+            Judgement::Blunder => reviews[side].blunders += 1,
+        }
+
+        let played = game.node(id).played.as_ref().map(|played| played.r#move);
+        if before.pv.first().is_some_and(|&best| Some(best) != played) {
+            let plies = before.pv.len().min(MAX_VARIATION_PLIES);
+            // the line was legal in the position it was found in, so it can always be played
+            let _ = game.add_line(parent, &before.pv[..plies]);
+        }
+    }
+
+    for (side, review) in reviews.iter_mut().enumerate() {
+        if counts[side] > 0 {
+            review.accuracy = accuracy_sums[side] / counts[side] as f32;
+            review.average_loss = loss_sums[side] as f32 / counts[side] as f32;
+        }
+    }
+    reviews
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pgn;
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 0.01
+    }
+
+    #[test]
+    fn formulas() {
+        assert_eq!(win_chance(Score::Centipawns(0)), 50.0);
+        assert!(close(win_chance(Score::Centipawns(30)), 52.76));
+        assert!(close(win_chance(Score::Centipawns(-300)), 24.89));
+        // mates and huge advantages count as a thousand centipawns
+        assert!(close(win_chance(Score::Mate(3)), 97.54));
+        assert_eq!(centipawns(Score::Centipawns(5000)), 1000);
+        assert_eq!(centipawns(Score::Mate(-2)), -1000);
+        assert_eq!(centipawns(Score::Mate(0)), -1000);
+
+        assert!(close(move_accuracy(50.0, 50.0), 100.0));
+        assert!(close(move_accuracy(50.0, 70.0), 100.0));
+        assert!(close(move_accuracy(60.0, 40.0), 40.02));
+        assert_eq!(move_accuracy(100.0, 0.0), 0.0);
+
+        assert_eq!(Judgement::from_loss(4.9), None);
+        assert_eq!(Judgement::from_loss(5.0), Some(Judgement::Inaccuracy));
+        assert_eq!(Judgement::from_loss(10.0), Some(Judgement::Mistake));
+        assert_eq!(Judgement::from_loss(15.0), Some(Judgement::Blunder));
+    }
+
+    #[test]
+    fn annotating() {
+        let mut game = pgn::read("1. e4 e5 *").unwrap();
+        let line: Vec<_> = [NodeId::ROOT].into_iter().chain(game.main_line()).collect();
+        let better = game.board_at(line[1]).parse_uci("c7c5").unwrap();
+        let evaluations = [
+            Evaluation {
+                score: Score::Centipawns(30),
+                pv: Vec::new(),
+            },
+            Evaluation {
+                score: Score::Centipawns(-30),
+                pv: vec![better],
+            },
+            Evaluation {
+                score: Score::Centipawns(300),
+                pv: Vec::new(),
+            },
+        ];
+        let [white, black] = annotate(&mut game, &line, &evaluations);
+
+        // 1. e4 keeps white's chances, and 1... e5 loses 22 points of black's
+        assert!(close(white.accuracy, 100.0));
+        assert_eq!(white.average_loss, 0.0);
+        assert_eq!(
+            (white.inaccuracies, white.mistakes, white.blunders),
+            (0, 0, 0)
+        );
+        assert!(close(black.accuracy, 35.81));
+        assert_eq!(black.average_loss, 270.0);
+        assert_eq!(
+            (black.inaccuracies, black.mistakes, black.blunders),
+            (0, 0, 1)
+        );
+
+        // evaluations are written for white, and the better move is added as a variation
+        let (e4, e5) = (game.node(line[1]), game.node(line[2]));
+        assert_eq!(e4.annotations.eval, Some(Score::Centipawns(30)));
+        assert!(e4.annotations.nags.is_empty());
+        assert_eq!(e5.annotations.eval, Some(Score::Centipawns(300)));
+        assert_eq!(e5.annotations.nags, [Judgement::Blunder.nag()]);
+        assert_eq!(e4.children.len(), 2);
+        assert_eq!(game.main_line(), line[1..]);
+    }
+}
@@ -0,0 +1,502 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    board::{Board, CastlingRights},
+    piece::{PieceColor, PieceKind},
+    r#move::Move,
+};
+
+use self::table::{Dtz, Table, TableKind};
+
+/// Reading Syzygy table files
+pub mod table;
+
+/// The kinds of piece in the order tables name them, after the king
+const NAME_ORDER: [PieceKind; 5] = [
+    PieceKind::Queen,
+    PieceKind::Rook,
+    PieceKind::Bishop,
+    PieceKind::Knight,
+    PieceKind::Pawn,
+];
+
+/// Whether a position is won, drawn, or lost for the player to move, with perfect play
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Wdl {
+    /// Lost
+    Loss,
+
+    /// Lost, but drawn by the fifty-move rule
+    BlessedLoss,
+
+    /// Drawn
+    Draw,
+
+    /// Won, but drawn by the fifty-move rule
+    CursedWin,
+
+    /// Won
+    Win,
+}
+
+impl Wdl {
+    /// Returns the result stored in tables as the given value, from -2 for a loss to 2 for a win
+    fn from_value(value: i32) -> Option<Self> {
+        match value {
+            -2 => Some(Self::Loss),
+            -1 => Some(Self::BlessedLoss),
+            0 => Some(Self::Draw),
+            1 => Some(Self::CursedWin),
+            2 => Some(Self::Win),
+            _ => None,
+        }
+    }
+
+    /// Returns the result for the other player
+    pub const fn negate(self) -> Self {
+        match self {
+            Self::Loss => Self::Win,
+            Self::BlessedLoss => Self::CursedWin,
+            Self::Draw => Self::Draw,
+            Self::CursedWin => Self::BlessedLoss,
+            Self::Win => Self::Loss,
+        }
+    }
+
+    /// Returns 1 for a win, -1 for a loss, and 0 for a draw, counting the fifty-move rule
+    /// draws with the wins and losses they would otherwise be
+    const fn signum(self) -> i32 {
+        match self {
+            Self::Loss | Self::BlessedLoss => -1,
+            Self::Draw => 0,
+            Self::CursedWin | Self::Win => 1,
+        }
+    }
+
+    /// Returns the distance to zeroing of a position just before a capture or pawn move that
+    /// leads to this result
+    const fn dtz_before_zeroing(self) -> i32 {
+        match self {
+            Self::Loss => -1,
+            Self::BlessedLoss => -101,
+            Self::Draw => 0,
+            Self::CursedWin => 101,
+            Self::Win => 1,
+        }
+    }
+}
+
+/// What a tablebase knows about a position
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Probe {
+    /// Whether the position is won, drawn, or lost for the player to move
+    pub wdl: Wdl,
+
+    /// The number of plies until the next capture or pawn move with perfect play, negative if
+    /// the player to move is losing, and offset by 100 for results the fifty-move rule changes
+    pub dtz: i32,
+
+    /// The moves that keep the best result soonest, or lose it latest
+    pub best_moves: Vec<Move>,
+}
+
+/// A directory of Syzygy endgame tables, each read the first time it is needed
+///
+/// WDL tables tell whether a position is won, drawn, or lost, and DTZ tables how far it is from
+/// the next capture or pawn move, which together are enough to play endgames perfectly.
+pub struct Tablebase {
+    directory: PathBuf,
+
+    /// The names of the table files in the directory, i.e. "KRvK.rtbw"
+    files: HashSet<String>,
+    max_pieces: usize,
+
+    /// The tables read so far, by file name, or `None` for those that could not be read
+    tables: Mutex<HashMap<String, Option<Arc<Table>>>>,
+}
+
+impl Tablebase {
+    /// Finds the tables in the given directory
+    pub fn open(directory: &Path) -> Result<Self, &'static str> {
+        let entries = fs::read_dir(directory).map_err(|_| "unable to read the directory")?;
+        let files: HashSet<String> = entries
+            .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+            .filter(|name| name.ends_with(".rtbw") || name.ends_with(".rtbz"))
+            .collect();
+        let max_pieces = files
+            .iter()
+            .filter_map(|name| name.split_once('.'))
+            .map(|(material, _)| material.len() - 1)
+            .max()
+            .ok_or("no Syzygy tables found")?;
+
+        Ok(Self {
+            directory: directory.to_path_buf(),
+            files,
+            max_pieces,
+            tables: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Returns the most pieces, kings included, of any table
+    pub fn max_pieces(&self) -> usize {
+        self.max_pieces
+    }
+
+    /// Returns true if the given position could be in the tables: it has few enough pieces and
+    /// neither side can castle
+    pub fn covers(&self, board: &Board) -> bool {
+        board.castling_rights() == CastlingRights::NONE && board.pieces().count() <= self.max_pieces
+    }
+
+    /// Returns whether the given position is won, drawn, or lost for the player to move, or
+    /// `None` if it is not in the tables
+    pub fn probe_wdl(&self, board: &Board) -> Option<Wdl> {
+        if !self.covers(board) {
+            return None;
+        }
+        self.search(board, false).map(|(wdl, _)| wdl)
+    }
+
+    /// Returns the number of plies until the next capture or pawn move with perfect play,
+    /// negative if the player to move is losing, or `None` if the position is not in the tables
+    ///
+    /// Results the fifty-move rule changes are offset by 100, so a cursed win is more than 100.
+    pub fn probe_dtz(&self, board: &Board) -> Option<i32> {
+        if !self.covers(board) {
+            return None;
+        }
+        self.dtz(board)
+    }
+
+    /// Returns what the tables know about the given position, with its best moves, or `None` if
+    /// it is not in the tables
+    pub fn probe(&self, board: &Board) -> Option<Probe> {
+        if !self.covers(board) {
+            return None;
+        }
+        let (wdl, _) = self.search(board, false)?;
+        let dtz = self.dtz(board)?;
+
+        let mut ranked = Vec::new();
+        for r#move in board.legal_moves() {
+            let mut next = board.clone();
+            next.play_move(r#move);
+            let dtz = match next.halfmove_clock() {
+                // a capture or pawn move starts counting again
+                0 => self.search(&next, false)?.0.negate().dtz_before_zeroing(),
+                _ => {
+                    let dtz = -self.dtz(&next)?;
+                    dtz + dtz.signum()
+                }
+            };
+            let dtz = match next.is_checkmate() {
+                true => 1,
+                false => dtz,
+            };
+            ranked.push((r#move, dtz));
+        }
+
+        // wins are best when soonest, and losses when latest
+        let rank = |dtz: i32| match dtz.signum() {
+            1 => (2, -dtz),
+            0 => (1, 0),
+            _ => (0, -dtz),
+        };
+        let best = ranked.iter().map(|&(_, dtz)| rank(dtz)).max();
+        let best_moves = ranked
+            .iter()
+            .filter(|&&(_, dtz)| Some(rank(dtz)) == best)
+            .map(|&(r#move, _)| r#move)
+            .collect();
+        Some(Probe {
+            wdl,
+            dtz,
+            best_moves,
+        })
+    }
+
+    /// Returns the result of the given position and whether a capture or pawn move is best,
+    /// having tried the moves that may be better than what the table stores
+    ///
+    /// Tables store any value for positions where a capture wins, as they are never needed, and
+    /// no value for positions where a capture en passant is possible, so captures are always
+    /// tried. DTZ tables likewise leave out positions where a pawn move wins, so pawn moves are
+    /// tried too when `pawn_moves` is set.
+    fn search(&self, board: &Board, pawn_moves: bool) -> Option<(Wdl, bool)> {
+        let moves = board.legal_moves();
+        let mut best = Wdl::Loss;
+        let mut tried = 0;
+        for &r#move in &moves {
+            let is_pawn_move = board
+                .get_piece(r#move.from())
+                .is_some_and(|piece| piece.is_pawn());
+            if r#move.captured().is_none() && !(pawn_moves && is_pawn_move) {
+                continue;
+            }
+            tried += 1;
+
+            let mut next = board.clone();
+            next.play_move(r#move);
+            let value = self.search(&next, false)?.0.negate();
+            if value > best {
+                best = value;
+                if value == Wdl::Win {
+                    return Some((value, true));
+                }
+            }
+        }
+
+        // with every move tried, the table is not needed and may be wrong
+        let tried_all = tried > 0 && tried == moves.len();
+        let value = match tried_all {
+            true => best,
+            false => self.probe_table_wdl(board)?,
+        };
+        match best >= value {
+            true => Some((best, best > Wdl::Draw || tried_all)),
+            false => Some((value, false)),
+        }
+    }
+
+    /// Returns the distance to zeroing of the given position
+    fn dtz(&self, board: &Board) -> Option<i32> {
+        let (wdl, zeroing) = self.search(board, true)?;
+        if wdl == Wdl::Draw {
+            return Some(0);
+        }
+        if zeroing {
+            return Some(wdl.dtz_before_zeroing());
+        }
+
+        let (table, black_stronger) = self.table(board, TableKind::Dtz)?;
+        match table.dtz(board, black_stronger, wdl)? {
+            Dtz::Found(dtz) => {
+                let cursed = matches!(wdl, Wdl::CursedWin | Wdl::BlessedLoss);
+                Some((dtz + 100 * cursed as i32) * wdl.signum())
+            }
+            // the table only stores the other player to move, so their best reply is found
+            Dtz::OtherSide => {
+                let mut best = None;
+                for r#move in board.legal_moves() {
+                    let is_pawn_move = board
+                        .get_piece(r#move.from())
+                        .is_some_and(|piece| piece.is_pawn());
+                    let zeroing = r#move.captured().is_some() || is_pawn_move;
+                    let mut next = board.clone();
+                    next.play_move(r#move);
+
+                    let mut dtz = match zeroing {
+                        true => -self.search(&next, false)?.0.dtz_before_zeroing(),
+                        false => -self.dtz(&next)?,
+                    };
+                    if dtz == 1 && next.is_checkmate() {
+                        best = Some(1);
+                    }
+                    if !zeroing {
+                        dtz += dtz.signum();
+                    }
+                    if dtz.signum() == wdl.signum() && best.is_none_or(|best| dtz < best) {
+                        best = Some(dtz);
+                    }
+                }
+                // with no moves, the player to move is checkmated
+                Some(best.unwrap_or(-1))
+            }
+        }
+    }
+
+    /// Returns whether the given position is won, drawn, or lost, as stored in its WDL table
+    fn probe_table_wdl(&self, board: &Board) -> Option<Wdl> {
+        // two bare kings have no table
+        if board.pieces().count() == 2 {
+            return Some(Wdl::Draw);
+        }
+        let (table, black_stronger) = self.table(board, TableKind::Wdl)?;
+        table.wdl(board, black_stronger)
+    }
+
+    /// Returns the table of the given kind with the material of the given position, reading it
+    /// if it has not been read yet, and whether black has the pieces it names first
+    fn table(&self, board: &Board, kind: TableKind) -> Option<(Arc<Table>, bool)> {
+        let white = material(board, PieceColor::White);
+        let black = material(board, PieceColor::Black);
+        let extension = kind.extension();
+        let (material, black_stronger) = [
+            (format!("{white}v{black}"), false),
+            (format!("{black}v{white}"), true),
+        ]
+        .into_iter()
+        .find(|(material, _)| self.files.contains(&format!("{material}.{extension}")))?;
+
+        let name = format!("{material}.{extension}");
+        let mut tables = self
+            .tables
+            .lock()
+            .unwrap_or_else(|error| error.into_inner());
+        let table = tables
+            .entry(name)
+            .or_insert_with_key(|name| {
+                Table::open(&self.directory.join(name), kind, &material).map(Arc::new)
+            })
+            .clone()?;
+        Some((table, black_stronger))
+    }
+}
+
+/// Returns the pieces of one side as tables name them, i.e. "KRP"
+fn material(board: &Board, color: PieceColor) -> String {
+    let mut name = String::from("K");
+    for kind in NAME_ORDER {
+        let count = board
+            .pieces()
+            .filter(|(_, piece)| piece.color == color && piece.kind == kind)
+            .count();
+        for _ in 0..count {
+            name.push(kind.letter());
+        }
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Scratch;
+
+    /// The directory of the tables the tests read, from the crate's root
+    const FIXTURES: &str = "tests/fixtures/syzygy";
+
+    fn fixtures() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join(FIXTURES)
+    }
+
+    fn board(fen: &str) -> Board {
+        Board::from_fen(fen).unwrap()
+    }
+
+    fn probe(tablebase: &Tablebase, fen: &str) -> (Wdl, i32) {
+        let board = board(fen);
+        let wdl = tablebase.probe_wdl(&board).unwrap();
+        let dtz = tablebase.probe_dtz(&board).unwrap();
+        (wdl, dtz)
+    }
+
+    #[test]
+    fn queen_and_rook() {
+        let tablebase = Tablebase::open(&fixtures()).unwrap();
+        assert_eq!(tablebase.max_pieces(), 3);
+
+        let mate_in_one = "4k3/8/4K3/8/8/8/8/7Q w - - 0 1";
+        assert_eq!(probe(&tablebase, mate_in_one), (Wdl::Win, 1));
+        let best = tablebase.probe(&board(mate_in_one)).unwrap().best_moves;
+        assert!(best.iter().any(|r#move| r#move.uci() == "h1h8"));
+
+        let mated = "4k2Q/8/4K3/8/8/8/8/8 b - - 0 1";
+        assert_eq!(probe(&tablebase, mated), (Wdl::Loss, -1));
+        // the only move, Ka7, allows Qb7#
+        let mated_next = "k7/8/2K5/8/8/8/8/1Q6 b - - 0 1";
+        assert_eq!(probe(&tablebase, mated_next), (Wdl::Loss, -2));
+
+        let stalemate = "k7/2Q5/1K6/8/8/8/8/8 b - - 0 1";
+        assert_eq!(probe(&tablebase, stalemate), (Wdl::Draw, 0));
+        let hanging = "8/8/8/8/8/8/1Qk5/7K b - - 0 1";
+        assert_eq!(probe(&tablebase, hanging), (Wdl::Draw, 0));
+
+        let rook_mate = "4k3/8/4K3/8/8/8/8/7R w - - 0 1";
+        assert_eq!(probe(&tablebase, rook_mate), (Wdl::Win, 1));
+
+        // among the longest wins, as a queen mates in at most 10 moves and a rook in at most 16
+        let longest_queen = "7K/6Q1/8/8/8/3k4/8/8 w - - 0 1";
+        assert_eq!(probe(&tablebase, longest_queen), (Wdl::Win, 19));
+        let longest_rook = "7K/8/8/8/8/8/2k5/1R6 w - - 0 1";
+        assert_eq!(probe(&tablebase, longest_rook), (Wdl::Win, 31));
+    }
+
+    #[test]
+    fn pawn() {
+        let tablebase = Tablebase::open(&fixtures()).unwrap();
+
+        // the pawn cannot move, so the king steps aside first and the pawn moves after
+        let king_in_front = "4k3/8/4K3/4P3/8/8/8/8 w - - 0 1";
+        assert_eq!(probe(&tablebase, king_in_front), (Wdl::Win, 3));
+        let corner = "k7/8/8/8/8/K7/P7/8 w - - 0 1";
+        assert_eq!(probe(&tablebase, corner), (Wdl::Draw, 0));
+
+        // the same positions with black leading are found with the table turned around
+        let king_in_front = "8/8/8/8/4p3/4k3/8/4K3 b - - 0 1";
+        assert_eq!(probe(&tablebase, king_in_front), (Wdl::Win, 3));
+        let corner = "8/p7/k7/8/8/8/8/K7 b - - 0 1";
+        assert_eq!(probe(&tablebase, corner), (Wdl::Draw, 0));
+
+        // white's king is outside the square of the pawn
+        let runaway = "k7/p7/8/8/8/8/8/7K b - - 0 1";
+        assert_eq!(probe(&tablebase, runaway), (Wdl::Win, 1));
+        let runaway = "k7/p7/8/8/8/8/8/7K w - - 0 1";
+        assert_eq!(probe(&tablebase, runaway), (Wdl::Loss, -2));
+    }
+
+    #[test]
+    fn positions_outside_the_tables() {
+        let tablebase = Tablebase::open(&fixtures()).unwrap();
+        assert_eq!(tablebase.probe_wdl(&Board::new_init()), None);
+        assert_eq!(
+            tablebase.probe_wdl(&board("8/8/8/8/8/2k5/8/R1R1K3 w - - 0 1")),
+            None
+        );
+    }
+
+    #[test]
+    fn missing_tables() {
+        assert!(Tablebase::open(&fixtures().join("missing")).is_err());
+        let scratch = Scratch::new("tablebase-missing");
+        let directory = scratch.path();
+        assert!(Tablebase::open(directory).is_err());
+
+        // only the WDL table of KQvK is there
+        fs::copy(fixtures().join("KQvK.rtbw"), directory.join("KQvK.rtbw")).unwrap();
+        let tablebase = Tablebase::open(directory).unwrap();
+        let queen = board("4k3/8/4K3/8/8/8/8/7Q w - - 0 1");
+        assert_eq!(tablebase.probe_wdl(&queen), Some(Wdl::Win));
+        assert_eq!(tablebase.probe_dtz(&queen), None);
+        assert_eq!(tablebase.probe(&queen), None);
+        assert_eq!(
+            tablebase.probe_wdl(&board("4k3/8/4K3/8/8/8/8/7R w - - 0 1")),
+            None
+        );
+
+        // and then not even that
+        let tablebase = Tablebase::open(directory).unwrap();
+        fs::remove_file(directory.join("KQvK.rtbw")).unwrap();
+        assert_eq!(tablebase.probe_wdl(&queen), None);
+    }
+
+    #[test]
+    fn truncated_tables() {
+        let scratch = Scratch::new("tablebase-truncated");
+        let directory = scratch.path();
+        for entry in fs::read_dir(fixtures()).unwrap() {
+            let path = entry.unwrap().path();
+            fs::copy(&path, directory.join(path.file_name().unwrap())).unwrap();
+        }
+
+        let position = board("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1");
+        for name in ["KPvK.rtbw", "KPvK.rtbz"] {
+            let bytes = fs::read(fixtures().join(name)).unwrap();
+            for len in (0..bytes.len()).step_by(61) {
+                fs::write(directory.join(name), &bytes[..len]).unwrap();
+                let tablebase = Tablebase::open(directory).unwrap();
+                let probed = tablebase.probe(&position);
+                if len <= bytes.len() / 2 {
+                    assert_eq!(probed, None, "{name} cut to {len} bytes");
+                }
+            }
+            fs::write(directory.join(name), &bytes).unwrap();
+        }
+    }
+}
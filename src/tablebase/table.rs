@@ -0,0 +1,910 @@
+use std::{fs::File, path::Path, sync::OnceLock};
+
+use memmap2::Mmap;
+
+use crate::{
+    board::Board,
+    piece::{Piece, PieceColor, PieceKind},
+};
+
+use super::Wdl;
+
+/// The most pieces a table can hold
+const MAX_PIECES: usize = 7;
+
+/// The first bytes of every WDL table
+const WDL_MAGIC: [u8; 4] = [0x71, 0xe8, 0x23, 0x5d];
+
+/// The first bytes of every DTZ table
+const DTZ_MAGIC: [u8; 4] = [0xd7, 0x66, 0x0c, 0xa5];
+
+/// A DTZ table stores the positions with black to move rather than white
+const FLAG_STM: u8 = 1;
+
+/// A DTZ table's values are mapped through a table of the values that occur
+const FLAG_MAPPED: u8 = 2;
+
+/// A DTZ table stores winning distances in plies rather than moves
+const FLAG_WIN_PLIES: u8 = 4;
+
+/// A DTZ table stores losing distances in plies rather than moves
+const FLAG_LOSS_PLIES: u8 = 8;
+
+/// A DTZ table's value map has 16-bit entries rather than 8-bit ones
+const FLAG_WIDE: u8 = 16;
+
+/// Every position in a table has the same value
+const FLAG_SINGLE_VALUE: u8 = 128;
+
+/// A table holds one of two kinds of information about its positions
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TableKind {
+    /// Win, draw, or loss, in files ending ".rtbw"
+    Wdl,
+
+    /// Distance to zeroing the fifty-move counter, in files ending ".rtbz"
+    Dtz,
+}
+
+impl TableKind {
+    /// Returns the extension of the files holding tables of this kind
+    pub const fn extension(&self) -> &'static str {
+        match self {
+            Self::Wdl => "rtbw",
+            Self::Dtz => "rtbz",
+        }
+    }
+}
+
+/// The result of looking up a position in a DTZ table
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dtz {
+    /// The distance to zeroing, in plies, before adjusting for cursed wins and blessed losses
+    Found(i32),
+
+    /// The table only stores positions with the other player to move
+    OtherSide,
+}
+
+/// The encoding of the positions of one side to move and leading pawn file in a table, and
+/// where their compressed values are
+#[derive(Clone, Debug, Default)]
+struct PairsData {
+    flags: u8,
+
+    /// The pieces in the order they are encoded, with white as the stronger side
+    pieces: [u8; MAX_PIECES],
+
+    /// The number of pieces in each group encoded together, ending with zero
+    group_len: [usize; MAX_PIECES + 1],
+
+    /// The factor each group's index is multiplied by, with the number of positions last
+    group_idx: [u64; MAX_PIECES + 1],
+
+    block_size: usize,
+    span: u64,
+    num_blocks: usize,
+    max_sym_len: u8,
+
+    /// The shortest symbol length, or the value of every position in a single valued table
+    min_sym_len: u8,
+
+    /// Where the lowest symbol of each length is
+    lowest_sym: usize,
+
+    /// Where the pairs each symbol expands into are
+    btree: usize,
+
+    /// The lowest symbol of each length, padded to 64 bits
+    base64: Vec<u64>,
+
+    /// The number of values each symbol expands into, less one
+    symlen: Vec<u16>,
+
+    sparse_index: usize,
+    sparse_index_size: usize,
+    block_length: usize,
+    block_length_size: usize,
+
+    /// Where the compressed blocks start
+    data: usize,
+
+    /// Where the value map of each result starts in a DTZ table, relative to the map
+    map_idx: [u16; 4],
+}
+
+/// A Syzygy table of positions with one set of material, as stored on disk
+pub struct Table {
+    data: Mmap,
+    kind: TableKind,
+
+    /// Whether both sides have the same pieces
+    symmetric: bool,
+    has_pawns: bool,
+    has_unique_pieces: bool,
+    piece_count: usize,
+
+    /// The number of pawns of the leading color, then the other
+    pawn_count: [usize; 2],
+
+    /// The encoding of each leading pawn file, a to d, for each side to move
+    pairs: Vec<Vec<PairsData>>,
+
+    /// Where the value map of a DTZ table starts
+    map: usize,
+}
+
+impl Table {
+    /// Opens the table of the given kind and material at the given path, where the material is
+    /// named as in the file name, i.e. "KRPvKR"
+    pub fn open(path: &Path, kind: TableKind, material: &str) -> Option<Self> {
+        let file = File::open(path).ok()?;
+        // SAFETY: tablebase files are only read, and are not expected to change while mapped
+        let data = unsafe { Mmap::map(&file) }.ok()?;
+        let magic = match kind {
+            TableKind::Wdl => WDL_MAGIC,
+            TableKind::Dtz => DTZ_MAGIC,
+        };
+        if data.get(..4)? != magic {
+            return None;
+        }
+
+        let (white, black) = material.split_once('v')?;
+        let count = |pieces: &str, kind: PieceKind| {
+            pieces
+                .chars()
+                .filter(|&letter| PieceKind::from_letter(letter) == Some(kind))
+                .count()
+        };
+        let has_unique_pieces = [white, black].iter().any(|pieces| {
+            [
+                PieceKind::Pawn,
+                PieceKind::Knight,
+                PieceKind::Bishop,
+                PieceKind::Rook,
+                PieceKind::Queen,
+            ]
+            .iter()
+            .any(|&kind| count(pieces, kind) == 1)
+        });
+        // the side with fewer pawns leads, as that compresses better
+        let (white_pawns, black_pawns) =
+            (count(white, PieceKind::Pawn), count(black, PieceKind::Pawn));
+        let pawn_count = match black_pawns == 0 || (white_pawns > 0 && black_pawns >= white_pawns) {
+            true => [white_pawns, black_pawns],
+            false => [black_pawns, white_pawns],
+        };
+
+        let mut table = Self {
+            data,
+            kind,
+            symmetric: white == black,
+            has_pawns: white_pawns + black_pawns > 0,
+            has_unique_pieces,
+            piece_count: white.len() + black.len(),
+            pawn_count,
+            pairs: Vec::new(),
+            map: 0,
+        };
+        if table.piece_count > MAX_PIECES {
+            return None;
+        }
+        table.read_header()?;
+        Some(table)
+    }
+
+    /// Reads where everything is in the table, from the header at the start of the file
+    fn read_header(&mut self) -> Option<()> {
+        let data = &self.data[..];
+        let mut pos = 4;
+        let split = data.get(pos)? & 1 != 0;
+        pos += 1;
+
+        let sides = match self.kind == TableKind::Wdl && split {
+            true => 2,
+            false => 1,
+        };
+        let files = match self.has_pawns {
+            true => 4,
+            false => 1,
+        };
+        // both sides have pawns
+        let pp = self.has_pawns && self.pawn_count[1] > 0;
+
+        let mut pairs = vec![vec![PairsData::default(); sides]; files];
+        for (file, sides) in pairs.iter_mut().enumerate() {
+            let second = match pp {
+                true => *data.get(pos + 1)?,
+                false => 0xff,
+            };
+            let first = *data.get(pos)?;
+            let order = [[first & 0xf, second & 0xf], [first >> 4, second >> 4]];
+            pos += 1 + pp as usize;
+
+            for k in 0..self.piece_count {
+                let byte = *data.get(pos)?;
+                for (side, d) in sides.iter_mut().enumerate() {
+                    d.pieces[k] = match side {
+                        0 => byte & 0xf,
+                        _ => byte >> 4,
+                    };
+                }
+                pos += 1;
+            }
+            for (side, d) in sides.iter_mut().enumerate() {
+                self.set_groups(d, order[side], file);
+            }
+        }
+        pos += pos & 1;
+
+        for d in pairs.iter_mut().flatten() {
+            pos = set_sizes(data, d, pos)?;
+        }
+        let map = pos;
+        if self.kind == TableKind::Dtz {
+            for sides in pairs.iter_mut() {
+                let d = &mut sides[0];
+                if d.flags & FLAG_MAPPED == 0 {
+                    continue;
+                }
+                match d.flags & FLAG_WIDE != 0 {
+                    true => {
+                        pos += pos & 1;
+                        for index in &mut d.map_idx {
+                            *index = ((pos - map) / 2 + 1) as u16;
+                            pos += 2 * read_u16_le(data, pos)? as usize + 2;
+                        }
+                    }
+                    false => {
+                        for index in &mut d.map_idx {
+                            *index = (pos - map + 1) as u16;
+                            pos += *data.get(pos)? as usize + 1;
+                        }
+                    }
+                }
+            }
+            pos += pos & 1;
+        }
+
+        for d in pairs.iter_mut().flatten() {
+            d.sparse_index = pos;
+            pos += d.sparse_index_size * 6;
+        }
+        for d in pairs.iter_mut().flatten() {
+            d.block_length = pos;
+            pos += d.block_length_size * 2;
+        }
+        for d in pairs.iter_mut().flatten() {
+            // the compressed blocks are aligned to 64 bytes
+            pos = (pos + 0x3f) & !0x3f;
+            d.data = pos;
+            pos += d.num_blocks * d.block_size;
+        }
+        // a file cut short is not read at all, rather than its missing blocks read as zeros
+        let last_block = |d: &PairsData| Some(d.data + d.num_blocks.checked_sub(1)? * d.block_size);
+        if pairs.iter().flatten().filter_map(last_block).max() >= Some(data.len()) {
+            return None;
+        }
+
+        self.pairs = pairs;
+        self.map = map;
+        Some(())
+    }
+
+    /// Works out how the pieces are grouped and the factor each group's index is multiplied by
+    fn set_groups(&self, d: &mut PairsData, order: [u8; 2], file: usize) {
+        let tables = tables();
+        let mut n = 0;
+        let mut first_len: i32 = match (self.has_pawns, self.has_unique_pieces) {
+            (true, _) => 0,
+            (false, true) => 3,
+            (false, false) => 2,
+        };
+        d.group_len[0] = 1;
+        for i in 1..self.piece_count {
+            first_len -= 1;
+            if first_len > 0 || d.pieces[i] == d.pieces[i - 1] {
+                d.group_len[n] += 1;
+            } else {
+                n += 1;
+                d.group_len[n] = 1;
+            }
+        }
+        n += 1;
+        d.group_len[n] = 0;
+
+        // the groups are encoded in the order the table gives, which need not be the order of
+        // the pieces
+        let pp = self.has_pawns && self.pawn_count[1] > 0;
+        let mut next = match pp {
+            true => 2,
+            false => 1,
+        };
+        let mut free_squares = 64 - d.group_len[0] - if pp { d.group_len[1] } else { 0 };
+        let mut idx: u64 = 1;
+        let mut k = 0;
+        while next < n || k == order[0] as usize || k == order[1] as usize {
+            if k == order[0] as usize {
+                d.group_idx[0] = idx;
+                idx *= match (self.has_pawns, self.has_unique_pieces) {
+                    (true, _) => tables.lead_pawns_size[d.group_len[0]][file],
+                    (false, true) => 31332,
+                    (false, false) => 462,
+                };
+            } else if k == order[1] as usize {
+                d.group_idx[1] = idx;
+                idx *= tables.binomial[d.group_len[1]][48 - d.group_len[0]];
+            } else {
+                d.group_idx[next] = idx;
+                idx *= tables.binomial[d.group_len[next]][free_squares];
+                free_squares -= d.group_len[next];
+                next += 1;
+            }
+            k += 1;
+        }
+        d.group_idx[n] = idx;
+    }
+
+    /// Returns whether the given position is won, drawn, or lost for the player to move, as
+    /// stored in this WDL table
+    ///
+    /// `black_stronger` is set if black has the pieces the table names first.
+    pub fn wdl(&self, board: &Board, black_stronger: bool) -> Option<Wdl> {
+        let (d, index) = self.index(board, black_stronger)??;
+        Wdl::from_value(self.decompress(d, index)? as i32 - 2)
+    }
+
+    /// Returns the distance to zeroing of the given position with the given result, as stored in
+    /// this DTZ table
+    pub fn dtz(&self, board: &Board, black_stronger: bool, wdl: Wdl) -> Option<Dtz> {
+        let Some((d, index)) = self.index(board, black_stronger)? else {
+            return Some(Dtz::OtherSide);
+        };
+        let mut value = self.decompress(d, index)? as i32;
+
+        // the distances are numbered by how often they occur, and mapped back to what they are
+        let flags = d.flags;
+        if flags & FLAG_MAPPED != 0 {
+            let map = match wdl {
+                Wdl::Loss => 1,
+                Wdl::BlessedLoss => 3,
+                Wdl::Draw | Wdl::Win => 0,
+                Wdl::CursedWin => 2,
+            };
+            let index = d.map_idx[map] as usize + value as usize;
+            value = match flags & FLAG_WIDE != 0 {
+                true => read_u16_le(&self.data, self.map + 2 * index)? as i32,
+                false => *self.data.get(self.map + index)? as i32,
+            };
+        }
+
+        let in_moves = match wdl {
+            Wdl::Win => flags & FLAG_WIN_PLIES == 0,
+            Wdl::Loss => flags & FLAG_LOSS_PLIES == 0,
+            Wdl::CursedWin | Wdl::BlessedLoss => true,
+            Wdl::Draw => false,
+        };
+        if in_moves {
+            value *= 2;
+        }
+        Some(Dtz::Found(value + 1))
+    }
+
+    /// Returns the index of the given position in the table, with the encoding it is stored
+    /// under, or `Some(None)` if this DTZ table only stores the other player to move
+    fn index(&self, board: &Board, black_stronger: bool) -> Option<Option<(&PairsData, u64)>> {
+        let tables = tables();
+
+        // the table stores white as the stronger side, and only white to move if both sides have
+        // the same pieces, so other positions are looked up with the colors swapped
+        let black_to_move = board.to_move() == PieceColor::Black;
+        let flip = (self.symmetric && black_to_move) || black_stronger;
+        let (flip_color, flip_squares) = match flip {
+            true => (8, 56),
+            false => (0, 0),
+        };
+        let stm = (flip ^ black_to_move) as usize;
+
+        let mut placed: Vec<(u8, u8)> = board
+            .pieces()
+            .map(|(square, piece)| {
+                let (file, rank) = square.indices();
+                (rank * 8 + file, piece_code(piece))
+            })
+            .collect();
+        placed.sort_unstable();
+
+        let mut squares = [0u8; MAX_PIECES];
+        let mut pieces = [0u8; MAX_PIECES];
+        let mut size = 0;
+        let mut lead_pawns = 0;
+        let mut file = 0;
+        let mut lead_pawn = None;
+
+        // tables with pawns are split by the file of the leading pawn: the one nearest the edge,
+        // and then nearest its own side
+        if self.has_pawns {
+            let pawn = self.pairs[0][0].pieces[0] ^ flip_color;
+            for &(square, code) in &placed {
+                if code == pawn {
+                    squares[size] = square ^ flip_squares;
+                    size += 1;
+                }
+            }
+            lead_pawns = size;
+            let leading = (0..lead_pawns)
+                .rev()
+                .max_by_key(|&i| tables.map_pawns[squares[i] as usize])?;
+            squares.swap(0, leading);
+            let pawn_file = (squares[0] % 8) as usize;
+            file = pawn_file.min(7 - pawn_file);
+            lead_pawn = Some(pawn);
+        }
+
+        let d = match self.kind {
+            TableKind::Wdl => &self.pairs[file][stm.min(self.pairs[file].len() - 1)],
+            TableKind::Dtz => {
+                let d = &self.pairs[file][0];
+                // symmetric tables without pawns serve both players to move
+                let one_sided = self.has_pawns || !self.symmetric;
+                if one_sided && (d.flags & FLAG_STM) as usize != stm {
+                    return Some(None);
+                }
+                d
+            }
+        };
+
+        for &(square, code) in &placed {
+            if Some(code) != lead_pawn {
+                squares[size] = square ^ flip_squares;
+                pieces[size] = code ^ flip_color;
+                size += 1;
+            }
+        }
+        if size != self.piece_count {
+            return None;
+        }
+
+        // the pieces are put in the order the table encodes them in
+        for i in lead_pawns..size.saturating_sub(1) {
+            for j in i + 1..size {
+                if d.pieces[i] == pieces[j] {
+                    pieces.swap(i, j);
+                    squares.swap(i, j);
+                    break;
+                }
+            }
+        }
+
+        // the leading piece is mirrored onto the queenside
+        if squares[0] % 8 > 3 {
+            for square in &mut squares[..size] {
+                *square ^= 7;
+            }
+        }
+
+        let mut index: u64;
+        if self.has_pawns {
+            index = tables.lead_pawn_idx[lead_pawns][squares[0] as usize];
+            squares[1..lead_pawns].sort_by_key(|&square| tables.map_pawns[square as usize]);
+            for (i, &square) in squares[..lead_pawns].iter().enumerate().skip(1) {
+                index += tables.binomial[i][tables.map_pawns[square as usize] as usize];
+            }
+        } else {
+            // without pawns, the leading piece is also mirrored onto the lower half of the board,
+            // and the first piece off the long diagonal below it
+            if squares[0] / 8 > 3 {
+                for square in &mut squares[..size] {
+                    *square ^= 56;
+                }
+            }
+            for i in 0..d.group_len[0] {
+                let off = off_diagonal(squares[i]);
+                if off == 0 {
+                    continue;
+                }
+                if off > 0 {
+                    for square in &mut squares[i..size] {
+                        *square = ((*square >> 3) | (*square << 3)) & 63;
+                    }
+                }
+                break;
+            }
+
+            index = match self.has_unique_pieces {
+                true => {
+                    let [s0, s1, s2] = [squares[0], squares[1], squares[2]].map(|s| s as u64);
+                    let adjust1 = (s1 > s0) as u64;
+                    let adjust2 = (s2 > s0) as u64 + (s2 > s1) as u64;
+                    let rank = |square: u64| square / 8;
+                    if off_diagonal(squares[0]) != 0 {
+                        (tables.map_a1d1d4[s0 as usize] * 63 + (s1 - adjust1)) * 62 + s2 - adjust2
+                    } else if off_diagonal(squares[1]) != 0 {
+                        (6 * 63 + rank(s0) * 28 + tables.map_b1h1h7[s1 as usize]) * 62 + s2
+                            - adjust2
+                    } else if off_diagonal(squares[2]) != 0 {
+                        6 * 63 * 62
+                            + 4 * 28 * 62
+                            + rank(s0) * 7 * 28
+                            + (rank(s1) - adjust1) * 28
+                            + tables.map_b1h1h7[s2 as usize]
+                    } else {
+                        6 * 63 * 62
+                            + 4 * 28 * 62
+                            + 4 * 7 * 28
+                            + rank(s0) * 7 * 6
+                            + (rank(s1) - adjust1) * 6
+                            + (rank(s2) - adjust2)
+                    }
+                }
+                false => {
+                    let king = tables.map_a1d1d4[squares[0] as usize] as usize;
+                    tables.map_kk[king][squares[1] as usize]
+                }
+            };
+        }
+
+        // the other groups are encoded as combinations of the squares left to them
+        index *= d.group_idx[0];
+        let mut start = d.group_len[0];
+        let mut remaining_pawns = self.has_pawns && self.pawn_count[1] > 0;
+        let mut next = 1;
+        while d.group_len[next] != 0 {
+            let len = d.group_len[next];
+            squares[start..start + len].sort_unstable();
+            let mut n = 0;
+            for i in 0..len {
+                let square = squares[start + i];
+                let adjust = squares[..start].iter().filter(|&&s| square > s).count();
+                let square = square as usize - adjust - 8 * remaining_pawns as usize;
+                n += tables.binomial[i + 1][square];
+            }
+            remaining_pawns = false;
+            index += n * d.group_idx[next];
+            start += len;
+            next += 1;
+        }
+
+        Some(Some((d, index)))
+    }
+
+    /// Returns the value stored for the position at the given index
+    ///
+    /// Values are compressed by repeatedly replacing the most common pair of adjacent symbols
+    /// with a new symbol, then Huffman coding the symbols in blocks.
+    fn decompress(&self, d: &PairsData, index: u64) -> Option<u16> {
+        if d.flags & FLAG_SINGLE_VALUE != 0 {
+            return Some(d.min_sym_len as u16);
+        }
+        let data = &self.data;
+
+        // the sparse index gives the block and offset of every `span`th value, from which the
+        // block holding this one is found by walking the block lengths
+        let k = (index / d.span) as usize;
+        if k >= d.sparse_index_size {
+            return None;
+        }
+        let entry = d.sparse_index + 6 * k;
+        let mut block = read_u32_le(data, entry)? as usize;
+        let mut offset = read_u16_le(data, entry + 4)? as i64;
+        offset += (index % d.span) as i64 - (d.span / 2) as i64;
+
+        let block_length = |block: usize| -> Option<i64> {
+            match block < d.block_length_size {
+                true => Some(read_u16_le(data, d.block_length + 2 * block)? as i64),
+                false => None,
+            }
+        };
+        while offset < 0 {
+            block = block.checked_sub(1)?;
+            offset += block_length(block)? + 1;
+        }
+        while offset > block_length(block)? {
+            offset -= block_length(block)? + 1;
+            block += 1;
+        }
+
+        let mut ptr = d.data + block * d.block_size;
+        let mut buffer = read_u64_be(data, ptr);
+        ptr += 8;
+        let mut buffer_size = 64;
+        let min_sym_len = d.min_sym_len as u32;
+        let mut sym;
+        loop {
+            // longer symbols have lower values, so the length is found from the lowest symbol
+            // of each length
+            let mut len = 0;
+            while buffer < d.base64[len] {
+                len += 1;
+                if len >= d.base64.len() {
+                    return None;
+                }
+            }
+            let shift = 64 - len as u32 - min_sym_len;
+            sym = ((buffer - d.base64[len]).checked_shr(shift).unwrap_or(0)) as u16;
+            sym = sym.wrapping_add(read_u16_le(data, d.lowest_sym + 2 * len)?);
+            let expands_to = *d.symlen.get(sym as usize)? as i64 + 1;
+            if offset < expands_to {
+                break;
+            }
+
+            offset -= expands_to;
+            let len = len as u32 + min_sym_len;
+            buffer = buffer.checked_shl(len).unwrap_or(0);
+            buffer_size -= len as i32;
+            if buffer_size <= 32 {
+                buffer_size += 32;
+                buffer |= (read_u32_be(data, ptr) as u64) << (64 - buffer_size);
+                ptr += 4;
+            }
+        }
+
+        // the symbol expands into a run of values, one of which is the one wanted
+        while *d.symlen.get(sym as usize)? != 0 {
+            let (left, right) = pair(data, d.btree, sym)?;
+            let left_len = *d.symlen.get(left as usize)? as i64 + 1;
+            if offset < left_len {
+                sym = left;
+            } else {
+                offset -= left_len;
+                sym = right;
+            }
+        }
+        Some(pair(data, d.btree, sym)?.0)
+    }
+}
+
+/// Reads the sizes of one encoding's compressed data, and its Huffman code, returning where the
+/// next encoding's start
+fn set_sizes(data: &[u8], d: &mut PairsData, mut pos: usize) -> Option<usize> {
+    d.flags = *data.get(pos)?;
+    pos += 1;
+    if d.flags & FLAG_SINGLE_VALUE != 0 {
+        d.min_sym_len = *data.get(pos)?;
+        return Some(pos + 1);
+    }
+
+    let groups = d.group_len.iter().position(|&len| len == 0)?;
+    let size = d.group_idx[groups];
+    d.block_size = 1usize.checked_shl(*data.get(pos)? as u32)?;
+    d.span = 1u64.checked_shl(*data.get(pos + 1)? as u32)?;
+    d.sparse_index_size = size.div_ceil(d.span) as usize;
+    let padding = *data.get(pos + 2)? as usize;
+    d.num_blocks = read_u32_le(data, pos + 3)? as usize;
+    d.block_length_size = d.num_blocks + padding;
+    d.max_sym_len = *data.get(pos + 7)?;
+    d.min_sym_len = *data.get(pos + 8)?;
+    pos += 9;
+    d.lowest_sym = pos;
+
+    // the canonical Huffman code is rebuilt from the lowest symbol of each length
+    let lengths = (d.max_sym_len.checked_sub(d.min_sym_len)? + 1) as usize;
+    d.base64 = vec![0; lengths];
+    for i in (0..lengths - 1).rev() {
+        let lowest = read_u16_le(data, d.lowest_sym + 2 * i)? as u64;
+        let next = read_u16_le(data, d.lowest_sym + 2 * (i + 1))? as u64;
+        d.base64[i] = (d.base64[i + 1] + lowest).wrapping_sub(next) / 2;
+    }
+    for (i, base) in d.base64.iter_mut().enumerate() {
+        let shift = 64 - i as u32 - d.min_sym_len as u32;
+        *base = base.checked_shl(shift).unwrap_or(0);
+    }
+    pos += lengths * 2;
+
+    let symbols = read_u16_le(data, pos)? as usize;
+    pos += 2;
+    d.btree = pos;
+    d.symlen = vec![0; symbols];
+    let mut visited = vec![false; symbols];
+    for sym in 0..symbols {
+        if !visited[sym] {
+            d.symlen[sym] = symbol_length(data, d, sym as u16, &mut visited)?;
+        }
+    }
+    Some(pos + symbols * 3 + (symbols & 1))
+}
+
+/// Returns the number of values the given symbol expands into, less one, working out those of
+/// the symbols it expands into along the way
+fn symbol_length(data: &[u8], d: &mut PairsData, sym: u16, visited: &mut [bool]) -> Option<u16> {
+    *visited.get_mut(sym as usize)? = true;
+    let (left, right) = pair(data, d.btree, sym)?;
+    if right == 0xfff {
+        return Some(0);
+    }
+    for child in [left, right] {
+        if !*visited.get(child as usize)? {
+            d.symlen[child as usize] = symbol_length(data, d, child, visited)?;
+        }
+    }
+    Some(d.symlen[left as usize] + d.symlen[right as usize] + 1)
+}
+
+/// Returns the pair of symbols the given symbol expands into, packed into 12 bits each, or the
+/// value it stands for and 0xfff if it expands into nothing
+fn pair(data: &[u8], btree: usize, sym: u16) -> Option<(u16, u16)> {
+    let bytes = data.get(btree + 3 * sym as usize..btree + 3 * sym as usize + 3)?;
+    let left = ((bytes[1] as u16 & 0xf) << 8) | bytes[0] as u16;
+    let right = ((bytes[2] as u16) << 4) | (bytes[1] as u16 >> 4);
+    Some((left, right))
+}
+
+/// Reads a little-endian 16-bit number
+fn read_u16_le(data: &[u8], pos: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(pos..pos + 2)?.try_into().ok()?))
+}
+
+/// Reads a little-endian 32-bit number
+fn read_u32_le(data: &[u8], pos: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(pos..pos + 4)?.try_into().ok()?))
+}
+
+/// Reads a big-endian 32-bit number from compressed data, as zero past the end of the file
+fn read_u32_be(data: &[u8], pos: usize) -> u32 {
+    let mut bytes = [0; 4];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = data.get(pos + i).copied().unwrap_or(0);
+    }
+    u32::from_be_bytes(bytes)
+}
+
+/// Reads a big-endian 64-bit number from compressed data, as zero past the end of the file
+fn read_u64_be(data: &[u8], pos: usize) -> u64 {
+    (read_u32_be(data, pos) as u64) << 32 | read_u32_be(data, pos + 4) as u64
+}
+
+/// Returns the code tables use for a piece: 1 to 6 for a white pawn to king, and 9 to 14 for
+/// black
+fn piece_code(piece: Piece) -> u8 {
+    let kind = match piece.kind {
+        PieceKind::Pawn => 1,
+        PieceKind::Knight => 2,
+        PieceKind::Bishop => 3,
+        PieceKind::Rook => 4,
+        PieceKind::Queen => 5,
+        PieceKind::King => 6,
+    };
+    match piece.color {
+        PieceColor::White => kind,
+        PieceColor::Black => kind + 8,
+    }
+}
+
+/// Returns how far above the a1-h8 diagonal a square is, negative if below
+fn off_diagonal(square: u8) -> i8 {
+    (square / 8) as i8 - (square % 8) as i8
+}
+
+/// The tables used to encode positions as indices, worked out once
+struct Tables {
+    /// The squares below the a1-h8 diagonal, numbered 0 to 27
+    map_b1h1h7: [u64; 64],
+
+    /// The squares of the a1-d1-d4 triangle, numbered 0 to 9 with the diagonal last
+    map_a1d1d4: [u64; 64],
+
+    /// The 462 ways to place two kings with the first in the a1-d1-d4 triangle
+    map_kk: [[u64; 64]; 10],
+
+    /// The number of ways to choose k of n, by k and n
+    binomial: [[u64; 64]; MAX_PIECES],
+
+    /// The squares a pawn can stand on, numbered from the edges in towards the center
+    map_pawns: [u64; 64],
+
+    /// The first index of each number of leading pawns with the leader on each square
+    lead_pawn_idx: [[u64; 64]; MAX_PIECES],
+
+    /// The number of indices of each number of leading pawns on each file
+    lead_pawns_size: [[u64; 4]; MAX_PIECES],
+}
+
+/// Returns the encoding tables, working them out the first time
+fn tables() -> &'static Tables {
+    static TABLES: OnceLock<Tables> = OnceLock::new();
+    TABLES.get_or_init(|| {
+        let mut tables = Tables {
+            map_b1h1h7: [0; 64],
+            map_a1d1d4: [0; 64],
+            map_kk: [[0; 64]; 10],
+            binomial: [[0; 64]; MAX_PIECES],
+            map_pawns: [0; 64],
+            lead_pawn_idx: [[0; 64]; MAX_PIECES],
+            lead_pawns_size: [[0; 4]; MAX_PIECES],
+        };
+
+        let mut code = 0;
+        for square in 0..64 {
+            if off_diagonal(square) < 0 {
+                tables.map_b1h1h7[square as usize] = code;
+                code += 1;
+            }
+        }
+
+        let mut diagonal = Vec::new();
+        let mut code = 0;
+        for square in 0..=27 {
+            if square % 8 > 3 {
+                continue;
+            }
+            match off_diagonal(square) {
+                0 => diagonal.push(square),
+                off if off < 0 => {
+                    tables.map_a1d1d4[square as usize] = code;
+                    code += 1;
+                }
+                _ => {}
+            }
+        }
+        for square in diagonal {
+            tables.map_a1d1d4[square as usize] = code;
+            code += 1;
+        }
+
+        // two kings may not stand next to each other, and with the first on the diagonal the
+        // second is mirrored below it
+        let mut both_on_diagonal = Vec::new();
+        let mut code = 0;
+        for idx in 0..10 {
+            for first in 0..=27u8 {
+                let in_triangle = first % 8 <= 3 && off_diagonal(first) <= 0;
+                if !in_triangle || tables.map_a1d1d4[first as usize] != idx {
+                    continue;
+                }
+                for second in 0..64u8 {
+                    let adjacent = (first % 8).abs_diff(second % 8) <= 1
+                        && (first / 8).abs_diff(second / 8) <= 1;
+                    if adjacent || (off_diagonal(first) == 0 && off_diagonal(second) > 0) {
+                        continue;
+                    }
+                    match off_diagonal(first) == 0 && off_diagonal(second) == 0 {
+                        true => both_on_diagonal.push((idx, second)),
+                        false => {
+                            tables.map_kk[idx as usize][second as usize] = code;
+                            code += 1;
+                        }
+                    }
+                }
+            }
+        }
+        for (idx, second) in both_on_diagonal {
+            tables.map_kk[idx as usize][second as usize] = code;
+            code += 1;
+        }
+
+        tables.binomial[0][0] = 1;
+        for n in 1..64 {
+            for k in 0..MAX_PIECES.min(n + 1) {
+                let with = match k {
+                    0 => 0,
+                    _ => tables.binomial[k - 1][n - 1],
+                };
+                let without = match k < n {
+                    true => tables.binomial[k][n - 1],
+                    false => 0,
+                };
+                tables.binomial[k][n] = with + without;
+            }
+        }
+
+        // the squares nearest the edges come first, so have the most squares left after them
+        let mut available = 48;
+        for lead_pawns in 1..MAX_PIECES - 1 {
+            for file in 0..4 {
+                let mut idx = 0;
+                for rank in 1..=6 {
+                    let square = rank * 8 + file;
+                    if lead_pawns == 1 {
+                        available -= 2;
+                        tables.map_pawns[square] = available + 1;
+                        tables.map_pawns[square ^ 7] = available;
+                    }
+                    tables.lead_pawn_idx[lead_pawns][square] = idx;
+                    idx += tables.binomial[lead_pawns - 1][tables.map_pawns[square] as usize];
+                }
+                tables.lead_pawns_size[lead_pawns][file] = idx;
+            }
+        }
+        tables
+    })
+}
@@ -0,0 +1,32 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// An empty directory for a test to write files in, removed with everything in it when dropped
+pub struct Scratch {
+    path: PathBuf,
+}
+
+impl Scratch {
+    /// Creates an empty directory for the test of the given name, which must be unique among the
+    /// tests, as they run at the same time
+    pub fn new(name: &str) -> Self {
+        let name = format!("chess-{name}-{}", std::process::id());
+        let path = std::env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).expect("the temporary directory is writable");
+        Self { path }
+    }
+
+    /// Returns the path of the directory
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}